@@ -1,6 +1,12 @@
 // config/mod.rs - Configuration management for the application
 use crate::errors::AppError;
+use crate::models::{audio_filter, text_format};
+use crate::services::MAX_SHARE_LINK_HOURS;
+use crate::utils::jwt::MAX_JWT_LEEWAY_SECONDS;
+use crate::utils::network::IpNetwork;
+use crate::utils::{file, validation};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::env;
 
 /// Application configuration loaded from environment variables
@@ -9,9 +15,45 @@ pub struct Config {
     /// Database connection URL
     pub database_url: String,
     
-    /// JWT secret for signing tokens
+    /// Maximum number of pooled database connections
+    pub db_max_connections: u32,
+    
+    /// Number of database connections kept open even when idle
+    pub db_min_connections: u32,
+    
+    /// Seconds to wait for a free connection before failing
+    pub db_acquire_timeout_seconds: u64,
+    
+    /// Seconds after which idle connections above the minimum are closed
+    pub db_idle_timeout_seconds: u64,
+    
+    /// Attempts to reach the database at startup before giving up
+    pub db_connect_attempts: u32,
+    
+    /// Seconds before the first reconnection attempt at startup, doubled after each failure
+    pub db_connect_retry_seconds: u64,
+    
+    /// JWT secret for signing tokens (HS256)
     pub jwt_secret: String,
     
+    /// JWT signing algorithm: "HS256" or "RS256"
+    pub jwt_algorithm: String,
+    
+    /// PEM-encoded RSA private key used to sign tokens with RS256
+    pub jwt_private_key_path: Option<String>,
+    
+    /// PEM-encoded RSA public keys of previous signing keys, still accepted for verification
+    pub jwt_public_key_paths: Vec<String>,
+    
+    /// Seconds of clock skew tolerated when checking a token's `exp` and `iat`
+    pub jwt_leeway_seconds: u64,
+    
+    /// `iss` claim put in tokens and required when verifying them
+    pub jwt_issuer: Option<String>,
+    
+    /// `aud` claim put in tokens and required when verifying them
+    pub jwt_audience: Option<String>,
+    
     /// Server host address
     pub host: String,
     
@@ -24,14 +66,229 @@ pub struct Config {
     /// Refresh token expiration time in days
     pub refresh_token_expires_in: i64,
     
-    /// Path to the Whisper model file
-    pub whisper_model_path: String,
+    /// Whisper model files by name, from `WHISPER_MODELS` (or the single `WHISPER_MODEL_PATH`)
+    pub whisper_models: BTreeMap<String, String>,
+    
+    /// Model used when an upload doesn't choose one
+    pub whisper_default_model: String,
+    
+    /// Load models other than the default on first use instead of at startup
+    pub whisper_lazy_load: bool,
+    
+    /// Most models kept loaded when lazily loading; the least recently used is evicted (unlimited when unset)
+    pub whisper_max_loaded_models: Option<usize>,
+    
+    /// Whether Whisper should run on the GPU (requires a CUDA/Metal enabled build)
+    pub whisper_use_gpu: bool,
+    
+    /// Number of threads Whisper uses per transcription (default: physical cores)
+    pub whisper_threads: usize,
+    
+    /// Beam size for beam search decoding (greedy decoding when unset)
+    pub whisper_beam_size: Option<usize>,
+    
+    /// Sampling temperature of the first decoding attempt unless an upload says otherwise
+    pub whisper_temperature: f32,
+    
+    /// Temperature increase for each retry of a segment that fails the thresholds (0 disables)
+    pub whisper_temperature_inc: f32,
+    
+    /// "No speech" probability above which a segment is treated as silence
+    pub whisper_no_speech_threshold: f32,
+    
+    /// Token entropy below which a segment is considered repetitive and decoded again
+    pub whisper_entropy_threshold: f32,
+    
+    /// Audio longer than this many seconds is transcribed in parallel chunks (disabled when unset)
+    pub whisper_chunk_threshold_seconds: Option<u64>,
+    
+    /// Length of each chunk in seconds
+    pub whisper_chunk_seconds: u64,
+    
+    /// Seconds of audio shared by neighbouring chunks
+    pub whisper_chunk_overlap_seconds: u64,
+    
+    /// Maximum number of chunks transcribed concurrently
+    pub whisper_parallel_chunks: usize,
+    
+    /// Whisper states allowed to exist at once across jobs, chunks and live streams
+    /// (default: the larger of `transcription_workers` and `whisper_parallel_chunks`)
+    pub whisper_max_states: Option<usize>,
+    
+    /// Command of the speaker diarization helper (diarization disabled when unset)
+    pub diarization_cmd: Option<String>,
+    
+    /// Whether long silences are trimmed before transcription when uploads don't specify it
+    pub trim_silence: bool,
+    
+    /// Volume below which audio counts as silence, in dB
+    pub silence_threshold_db: f64,
+    
+    /// Minimum length in seconds of a silence for it to be trimmed
+    pub silence_min_duration_seconds: f64,
+    
+    /// RMS level in dBFS below which a whole recording is rejected as silent
+    pub silent_audio_threshold_db: f64,
+    
+    /// FFmpeg audio filters applied when uploads don't specify `audio_filters`
+    pub audio_filters: Vec<String>,
+    
+    /// Reading speed in words per minute used for `estimated_reading_time_seconds`
+    pub reading_wpm: u32,
+    
+    /// How segment texts are joined when uploads don't specify a `format`
+    pub text_format: String,
+    
+    /// Capitalize the start of every sentence unless an upload says otherwise
+    pub capitalize_sentences: bool,
+    
+    /// Write spoken English numbers as digits unless an upload says otherwise
+    pub numbers_to_digits: bool,
+    
+    /// Seconds of new audio between partial results on live streams
+    pub live_step_seconds: u64,
+    
+    /// Seconds of live audio after which segments are finalized
+    pub live_window_seconds: u64,
     
     /// Maximum file size for uploads in bytes (default: 50MB)
     pub max_file_size: usize,
     
+    /// Hours an upload's Idempotency-Key is remembered
+    pub idempotency_key_ttl_hours: i64,
+    
+    /// Page size of paginated listings when the request doesn't set `limit`
+    pub default_page_size: i64,
+    
+    /// Largest `limit` a paginated listing honours
+    pub max_page_size: i64,
+    
+    /// Hours a transcript share link stays valid when the request doesn't say
+    pub share_link_ttl_hours: i64,
+    
+    /// Origin clients reach the API on, e.g. `https://scribe.example.com`, used to build share links
+    /// (links are returned without a URL when unset)
+    pub public_url: Option<String>,
+    
+    /// Maximum number of files in a batch upload
+    pub max_batch_files: usize,
+    
+    /// Maximum combined size of a batch upload in bytes (default: 500MB)
+    pub max_batch_size: usize,
+    
+    /// Audio file extensions accepted for upload (lowercase, without the dot)
+    pub supported_formats: Vec<String>,
+    
     /// Directory for temporary file storage
     pub temp_dir: String,
+    
+    /// Where original uploads are kept: "local" or "s3"
+    pub storage_backend: String,
+    
+    /// Base directory of the local storage backend
+    pub storage_local_dir: String,
+    
+    /// Bucket of the S3 storage backend
+    pub s3_bucket: Option<String>,
+    
+    /// Message broker receiving transcription events (events are not published when unset)
+    pub event_broker_url: Option<String>,
+    
+    /// Write verification and password reset tokens to the log instead of sending them (development only)
+    pub dev_log_account_emails: bool,
+    
+    /// Maximum audio duration in seconds accepted for transcription (unlimited when unset)
+    pub max_audio_duration_seconds: Option<f64>,
+    
+    /// Minutes of audio each user may transcribe per calendar month (unlimited when unset)
+    pub monthly_minutes_quota: Option<f64>,
+    
+    /// Argon2 memory cost in KiB
+    pub argon2_memory_kib: u32,
+    
+    /// Argon2 number of iterations (time cost)
+    pub argon2_iterations: u32,
+    
+    /// Argon2 degree of parallelism
+    pub argon2_parallelism: u32,
+    
+    /// Minimum number of characters in a new password (at least 8)
+    pub password_min_length: usize,
+    
+    /// Require new passwords to mix upper and lower case letters
+    pub password_require_mixed_case: bool,
+    
+    /// Require new passwords to contain a digit
+    pub password_require_digit: bool,
+    
+    /// Require new passwords to contain a symbol (anything but a letter, digit or whitespace)
+    pub password_require_symbol: bool,
+    
+    /// Reject new passwords found on a list of commonly used passwords
+    pub password_reject_common: bool,
+    
+    /// Maximum requests per minute per client IP on auth endpoints
+    pub rate_limit_per_minute: usize,
+    
+    /// Maximum transcription uploads per minute per user
+    pub upload_rate_limit_per_minute: usize,
+    
+    /// Maximum transcription jobs a user may have queued or running at once
+    pub max_concurrent_jobs_per_user: usize,
+    
+    /// Proxies whose `Forwarded`/`X-Forwarded-For` headers are trusted for the client IP
+    pub trusted_proxies: Vec<IpNetwork>,
+    
+    /// Seconds to wait for running transcriptions to finish on shutdown
+    pub shutdown_timeout_seconds: u64,
+    
+    /// Seconds a transcription job may spend in FFmpeg and Whisper before it is failed
+    pub transcription_timeout_seconds: u64,
+    
+    /// Transcription jobs run at the same time, each on its own worker
+    pub transcription_workers: usize,
+    
+    /// Jobs that may wait for a worker before uploads are turned away with 503
+    pub transcription_queue_size: usize,
+    
+    /// FFmpeg/FFprobe processes allowed to run at once (default: logical cores)
+    pub max_ffmpeg_concurrency: usize,
+    
+    /// Seconds between sweeps of stale files in `temp_dir`
+    pub temp_cleanup_interval_seconds: u64,
+    
+    /// Age in seconds after which an unclaimed file in `temp_dir` is considered orphaned
+    pub temp_file_max_age_seconds: u64,
+    
+    /// Maximum number of times a transcription may run, including retries
+    pub max_transcription_attempts: i32,
+    
+    /// Origins allowed to make cross-origin requests
+    pub allowed_origins: Vec<String>,
+    
+    /// Allow any origin when no explicit origins are configured
+    pub cors_permissive: bool,
+    
+    /// Compress responses (gzip, brotli, zstd) for clients that accept it
+    pub compression_enabled: bool,
+    
+    /// Wrap successful JSON responses under `/api/` as `{"data": ..., "meta": {...}}`
+    pub response_envelope: bool,
+    
+    /// Only send the refresh token cookie over HTTPS
+    pub cookie_secure: bool,
+    
+    /// SameSite policy of the refresh token cookie: "strict", "lax" or "none"
+    pub cookie_same_site: String,
+    
+    /// Domain attribute of the refresh token cookie (host-only when unset)
+    pub cookie_domain: Option<String>,
+    
+    /// Log verbosity: "trace", "debug", "info", "warn" or "error" (`RUST_LOG` overrides it)
+    pub log_level: String,
+    
+    /// Log output: "json" or "text"
+    pub log_format: String,
 }
 
 impl Config {
@@ -40,39 +297,904 @@ impl Config {
         // Load .env file if it exists
         dotenv::dotenv().ok();
 
-        Ok(Config {
-            database_url: env::var("DATABASE_URL")
+        Self::from_lookup(|key| env::var(key))
+    }
+
+    /// Build the configuration from settings read through `var`
+    fn from_lookup(var: impl Fn(&str) -> Result<String, env::VarError>) -> Result<Self, AppError> {
+        let config = Config {
+            database_url: var("DATABASE_URL")
                 .map_err(|_| AppError::ConfigError("DATABASE_URL must be set".to_string()))?,
             
-            jwt_secret: env::var("JWT_SECRET")
-                .map_err(|_| AppError::ConfigError("JWT_SECRET must be set".to_string()))?,
+            db_max_connections: var("DB_MAX_CONNECTIONS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .ok()
+                .filter(|connections| *connections > 0)
+                .ok_or_else(|| AppError::ConfigError("DB_MAX_CONNECTIONS must be a positive number".to_string()))?,
+            
+            db_min_connections: var("DB_MIN_CONNECTIONS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("DB_MIN_CONNECTIONS must be a valid number".to_string()))?,
+            
+            db_acquire_timeout_seconds: var("DB_ACQUIRE_TIMEOUT")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("DB_ACQUIRE_TIMEOUT must be a valid number of seconds".to_string()))?,
+            
+            db_idle_timeout_seconds: var("DB_IDLE_TIMEOUT")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("DB_IDLE_TIMEOUT must be a valid number of seconds".to_string()))?,
+            
+            db_connect_attempts: var("DB_CONNECT_ATTEMPTS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .ok()
+                .filter(|attempts: &u32| *attempts > 0)
+                .ok_or_else(|| AppError::ConfigError("DB_CONNECT_ATTEMPTS must be a positive number".to_string()))?,
+            
+            db_connect_retry_seconds: var("DB_CONNECT_RETRY_INTERVAL")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("DB_CONNECT_RETRY_INTERVAL must be a valid number of seconds".to_string()))?,
+            
+            jwt_secret: var("JWT_SECRET").unwrap_or_default(),
+            
+            jwt_algorithm: match var("JWT_ALGORITHM")
+                .unwrap_or_else(|_| "HS256".to_string())
+                .to_uppercase()
+                .as_str()
+            {
+                algorithm @ ("HS256" | "RS256") => algorithm.to_string(),
+                _ => {
+                    return Err(AppError::ConfigError(
+                        "JWT_ALGORITHM must be either HS256 or RS256".to_string(),
+                    ))
+                }
+            },
+            
+            jwt_private_key_path: var("JWT_PRIVATE_KEY_PATH").ok().filter(|path| !path.is_empty()),
             
-            host: env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
+            jwt_public_key_paths: var("JWT_PUBLIC_KEY_PATHS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty())
+                .collect(),
             
-            port: env::var("PORT")
+            jwt_leeway_seconds: var("JWT_LEEWAY_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .ok()
+                .filter(|seconds| *seconds <= MAX_JWT_LEEWAY_SECONDS)
+                .ok_or_else(|| {
+                    AppError::ConfigError(format!(
+                        "JWT_LEEWAY_SECONDS must be a number of seconds no greater than {}",
+                        MAX_JWT_LEEWAY_SECONDS
+                    ))
+                })?,
+            
+            jwt_issuer: var("JWT_ISSUER").ok().filter(|issuer| !issuer.is_empty()),
+            
+            jwt_audience: var("JWT_AUDIENCE").ok().filter(|audience| !audience.is_empty()),
+            
+            host: var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
+            
+            port: var("PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .map_err(|_| AppError::ConfigError("PORT must be a valid number".to_string()))?,
             
-            access_token_expires_in: env::var("ACCESS_TOKEN_EXPIRES_IN")
+            access_token_expires_in: var("ACCESS_TOKEN_EXPIRES_IN")
                 .unwrap_or_else(|_| "15".to_string()) // 15 minutes
                 .parse()
                 .map_err(|_| AppError::ConfigError("ACCESS_TOKEN_EXPIRES_IN must be a valid number".to_string()))?,
             
-            refresh_token_expires_in: env::var("REFRESH_TOKEN_EXPIRES_IN")
+            refresh_token_expires_in: var("REFRESH_TOKEN_EXPIRES_IN")
                 .unwrap_or_else(|_| "7".to_string()) // 7 days
                 .parse()
                 .map_err(|_| AppError::ConfigError("REFRESH_TOKEN_EXPIRES_IN must be a valid number".to_string()))?,
             
-            whisper_model_path: env::var("WHISPER_MODEL_PATH")
-                .map_err(|_| AppError::ConfigError("WHISPER_MODEL_PATH must be set".to_string()))?,
+            whisper_models: match var("WHISPER_MODELS") {
+                Ok(models) => Self::parse_whisper_models(&models)?,
+                Err(_) => {
+                    let path = var("WHISPER_MODEL_PATH").map_err(|_| {
+                        AppError::ConfigError("WHISPER_MODELS or WHISPER_MODEL_PATH must be set".to_string())
+                    })?;
+                    BTreeMap::from([(Self::model_file_name(&path), path)])
+                }
+            },
+            
+            whisper_default_model: match var("WHISPER_DEFAULT_MODEL") {
+                Ok(model) if !model.trim().is_empty() => model.trim().to_string(),
+                // The first listed model, or the only one when WHISPER_MODEL_PATH is used
+                _ => match var("WHISPER_MODELS") {
+                    Ok(models) => Self::first_listed_model(&models).unwrap_or_default(),
+                    Err(_) => Self::model_file_name(&var("WHISPER_MODEL_PATH").unwrap_or_default()),
+                },
+            },
+            
+            whisper_lazy_load: var("WHISPER_LAZY_LOAD")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("WHISPER_LAZY_LOAD must be either true or false".to_string()))?,
+            
+            whisper_max_loaded_models: match var("WHISPER_MAX_LOADED_MODELS") {
+                Ok(models) => Some(
+                    models
+                        .parse()
+                        .ok()
+                        .filter(|models| *models > 0)
+                        .ok_or_else(|| AppError::ConfigError("WHISPER_MAX_LOADED_MODELS must be a positive number".to_string()))?,
+                ),
+                Err(_) => None,
+            },
+            
+            whisper_use_gpu: var("WHISPER_USE_GPU")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("WHISPER_USE_GPU must be either true or false".to_string()))?,
+            
+            whisper_threads: match var("WHISPER_THREADS") {
+                Ok(threads) => threads
+                    .parse()
+                    .ok()
+                    .filter(|threads| *threads > 0)
+                    .ok_or_else(|| AppError::ConfigError("WHISPER_THREADS must be a positive number".to_string()))?,
+                Err(_) => num_cpus::get_physical(),
+            },
+            
+            whisper_beam_size: match var("WHISPER_BEAM_SIZE") {
+                Ok(beam_size) => Some(
+                    beam_size
+                        .parse()
+                        .ok()
+                        .filter(|beam_size| *beam_size > 0)
+                        .ok_or_else(|| AppError::ConfigError("WHISPER_BEAM_SIZE must be a positive number".to_string()))?,
+                ),
+                Err(_) => None,
+            },
+            
+            whisper_temperature: var("WHISPER_TEMPERATURE")
+                .unwrap_or_else(|_| "0.0".to_string())
+                .parse()
+                .ok()
+                .filter(|temperature: &f32| (0.0..=1.0).contains(temperature))
+                .ok_or_else(|| AppError::ConfigError("WHISPER_TEMPERATURE must be a number between 0 and 1".to_string()))?,
+            
+            whisper_temperature_inc: var("WHISPER_TEMPERATURE_INC")
+                .unwrap_or_else(|_| "0.2".to_string())
+                .parse()
+                .ok()
+                .filter(|increment: &f32| (0.0..=1.0).contains(increment))
+                .ok_or_else(|| AppError::ConfigError("WHISPER_TEMPERATURE_INC must be a number between 0 and 1".to_string()))?,
+            
+            whisper_no_speech_threshold: var("WHISPER_NO_SPEECH_THRESHOLD")
+                .unwrap_or_else(|_| "0.6".to_string())
+                .parse()
+                .ok()
+                .filter(|threshold: &f32| (0.0..=1.0).contains(threshold))
+                .ok_or_else(|| AppError::ConfigError("WHISPER_NO_SPEECH_THRESHOLD must be a number between 0 and 1".to_string()))?,
+            
+            whisper_entropy_threshold: var("WHISPER_ENTROPY_THRESHOLD")
+                .unwrap_or_else(|_| "2.4".to_string())
+                .parse()
+                .ok()
+                .filter(|threshold: &f32| *threshold > 0.0)
+                .ok_or_else(|| AppError::ConfigError("WHISPER_ENTROPY_THRESHOLD must be a positive number".to_string()))?,
+            
+            whisper_chunk_threshold_seconds: match var("WHISPER_CHUNK_THRESHOLD") {
+                Ok(threshold) => Some(
+                    threshold
+                        .parse()
+                        .map_err(|_| AppError::ConfigError("WHISPER_CHUNK_THRESHOLD must be a valid number of seconds".to_string()))?,
+                ),
+                Err(_) => None,
+            },
+            
+            whisper_chunk_seconds: var("WHISPER_CHUNK_SECONDS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .ok()
+                .filter(|seconds| *seconds > 0)
+                .ok_or_else(|| AppError::ConfigError("WHISPER_CHUNK_SECONDS must be a positive number".to_string()))?,
+            
+            whisper_chunk_overlap_seconds: var("WHISPER_CHUNK_OVERLAP")
+                .unwrap_or_else(|_| "4".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("WHISPER_CHUNK_OVERLAP must be a valid number of seconds".to_string()))?,
+            
+            whisper_parallel_chunks: var("WHISPER_PARALLEL_CHUNKS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .ok()
+                .filter(|chunks| *chunks > 0)
+                .ok_or_else(|| AppError::ConfigError("WHISPER_PARALLEL_CHUNKS must be a positive number".to_string()))?,
+            
+            whisper_max_states: match var("WHISPER_MAX_STATES") {
+                Ok(states) => Some(
+                    states
+                        .parse()
+                        .ok()
+                        .filter(|states| *states > 0)
+                        .ok_or_else(|| AppError::ConfigError("WHISPER_MAX_STATES must be a positive number".to_string()))?,
+                ),
+                Err(_) => None,
+            },
+            
+            diarization_cmd: var("DIARIZATION_CMD").ok().filter(|cmd| !cmd.trim().is_empty()),
+            
+            trim_silence: var("TRIM_SILENCE")
+                .map(|value| matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"))
+                .unwrap_or(false),
+            
+            silence_threshold_db: var("SILENCE_THRESHOLD_DB")
+                .unwrap_or_else(|_| "-40".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("SILENCE_THRESHOLD_DB must be a valid number".to_string()))?,
+            
+            silence_min_duration_seconds: var("SILENCE_MIN_DURATION")
+                .unwrap_or_else(|_| "2.0".to_string())
+                .parse()
+                .ok()
+                .filter(|seconds: &f64| *seconds > 0.0)
+                .ok_or_else(|| AppError::ConfigError("SILENCE_MIN_DURATION must be a positive number of seconds".to_string()))?,
+            
+            silent_audio_threshold_db: var("SILENT_AUDIO_THRESHOLD_DB")
+                .unwrap_or_else(|_| "-60".to_string())
+                .parse()
+                .ok()
+                .filter(|db: &f64| *db <= 0.0)
+                .ok_or_else(|| AppError::ConfigError("SILENT_AUDIO_THRESHOLD_DB must be a number of dBFS no greater than 0".to_string()))?,
+            
+            audio_filters: validation::validate_audio_filters(
+                &var("AUDIO_FILTERS").unwrap_or_default(),
+            )
+            .map_err(|_| {
+                AppError::ConfigError(format!(
+                    "AUDIO_FILTERS must be a comma-separated list of: {}",
+                    audio_filter::ALL.join(", ")
+                ))
+            })?,
+            
+            reading_wpm: var("READING_WPM")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse()
+                .ok()
+                .filter(|wpm: &u32| *wpm > 0)
+                .ok_or_else(|| AppError::ConfigError("READING_WPM must be a positive number".to_string()))?,
+            
+            text_format: validation::validate_text_format(
+                &var("TEXT_FORMAT").unwrap_or_else(|_| text_format::PLAIN.to_string()),
+            )
+            .map_err(|_| {
+                AppError::ConfigError(format!(
+                    "TEXT_FORMAT must be one of: {}",
+                    text_format::ALL.join(", ")
+                ))
+            })?
+            .to_string(),
+            
+            capitalize_sentences: var("CAPITALIZE_SENTENCES")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("CAPITALIZE_SENTENCES must be either true or false".to_string()))?,
+            
+            numbers_to_digits: var("NUMBERS_TO_DIGITS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("NUMBERS_TO_DIGITS must be either true or false".to_string()))?,
+            
+            live_step_seconds: var("LIVE_STEP_SECONDS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .ok()
+                .filter(|seconds: &u64| *seconds > 0)
+                .ok_or_else(|| AppError::ConfigError("LIVE_STEP_SECONDS must be a positive number of seconds".to_string()))?,
             
-            max_file_size: env::var("MAX_FILE_SIZE")
+            live_window_seconds: var("LIVE_WINDOW_SECONDS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .ok()
+                .filter(|seconds: &u64| *seconds > 0 && *seconds <= 30)
+                .ok_or_else(|| AppError::ConfigError("LIVE_WINDOW_SECONDS must be between 1 and 30 seconds".to_string()))?,
+            
+            max_file_size: var("MAX_FILE_SIZE")
                 .unwrap_or_else(|_| "52428800".to_string()) // 50MB
                 .parse()
                 .map_err(|_| AppError::ConfigError("MAX_FILE_SIZE must be a valid number".to_string()))?,
             
-            temp_dir: env::var("TEMP_DIR").unwrap_or_else(|_| "/tmp".to_string()),
+            idempotency_key_ttl_hours: var("IDEMPOTENCY_KEY_TTL_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .ok()
+                .filter(|hours: &i64| *hours > 0)
+                .ok_or_else(|| AppError::ConfigError("IDEMPOTENCY_KEY_TTL_HOURS must be a positive number of hours".to_string()))?,
+            
+            default_page_size: var("DEFAULT_PAGE_SIZE")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .ok()
+                .filter(|size: &i64| *size > 0)
+                .ok_or_else(|| AppError::ConfigError("DEFAULT_PAGE_SIZE must be a positive number".to_string()))?,
+            
+            max_page_size: var("MAX_PAGE_SIZE")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()
+                .ok()
+                .filter(|size: &i64| *size > 0)
+                .ok_or_else(|| AppError::ConfigError("MAX_PAGE_SIZE must be a positive number".to_string()))?,
+            
+            share_link_ttl_hours: var("SHARE_LINK_TTL_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .ok()
+                .filter(|hours: &i64| (1..=MAX_SHARE_LINK_HOURS).contains(hours))
+                .ok_or_else(|| AppError::ConfigError(format!("SHARE_LINK_TTL_HOURS must be between 1 and {}", MAX_SHARE_LINK_HOURS)))?,
+            
+            public_url: Self::parse_public_url(var("PUBLIC_URL").ok().as_deref())?,
+            
+            supported_formats: Self::parse_supported_formats(
+                var("SUPPORTED_FORMATS").ok().as_deref(),
+            ),
+            
+            max_batch_files: var("MAX_BATCH_FILES")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .ok()
+                .filter(|files: &usize| *files > 0)
+                .ok_or_else(|| AppError::ConfigError("MAX_BATCH_FILES must be a positive number".to_string()))?,
+            
+            max_batch_size: var("MAX_BATCH_SIZE")
+                .unwrap_or_else(|_| "524288000".to_string()) // 500MB
+                .parse()
+                .map_err(|_| AppError::ConfigError("MAX_BATCH_SIZE must be a valid number".to_string()))?,
+            
+            temp_dir: var("TEMP_DIR").unwrap_or_else(|_| "/tmp".to_string()),
+            
+            storage_backend: match var("STORAGE_BACKEND")
+                .unwrap_or_else(|_| "local".to_string())
+                .to_lowercase()
+                .as_str()
+            {
+                backend @ ("local" | "s3") => backend.to_string(),
+                _ => {
+                    return Err(AppError::ConfigError(
+                        "STORAGE_BACKEND must be either 'local' or 's3'".to_string(),
+                    ))
+                }
+            },
+            
+            storage_local_dir: var("STORAGE_LOCAL_DIR").unwrap_or_else(|_| "./storage".to_string()),
+            
+            s3_bucket: var("S3_BUCKET").ok().filter(|bucket| !bucket.trim().is_empty()),
+            
+            event_broker_url: var("EVENT_BROKER_URL").ok().filter(|url| !url.trim().is_empty()),
+            
+            dev_log_account_emails: var("DEV_LOG_ACCOUNT_EMAILS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("DEV_LOG_ACCOUNT_EMAILS must be either true or false".to_string()))?,
+            
+            max_audio_duration_seconds: match var("MAX_AUDIO_DURATION") {
+                Ok(duration) => Some(
+                    duration
+                        .parse()
+                        .map_err(|_| AppError::ConfigError("MAX_AUDIO_DURATION must be a valid number of seconds".to_string()))?,
+                ),
+                Err(_) => None,
+            },
+            
+            monthly_minutes_quota: match var("MONTHLY_MINUTES_QUOTA") {
+                Ok(quota) => Some(
+                    quota
+                        .parse()
+                        .map_err(|_| AppError::ConfigError("MONTHLY_MINUTES_QUOTA must be a valid number of minutes".to_string()))?,
+                ),
+                Err(_) => None,
+            },
+            
+            // Defaults follow the OWASP recommendation for Argon2id (19 MiB, 2 iterations, 1 lane)
+            argon2_memory_kib: var("ARGON2_MEMORY_KIB")
+                .unwrap_or_else(|_| "19456".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("ARGON2_MEMORY_KIB must be a valid number".to_string()))?,
+            
+            argon2_iterations: var("ARGON2_ITERATIONS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("ARGON2_ITERATIONS must be a valid number".to_string()))?,
+            
+            argon2_parallelism: var("ARGON2_PARALLELISM")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("ARGON2_PARALLELISM must be a valid number".to_string()))?,
+            
+            // The request models already require 8 characters, so the policy can only tighten that
+            password_min_length: var("PASSWORD_MIN_LENGTH")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()
+                .ok()
+                .filter(|length| *length >= 8)
+                .ok_or_else(|| AppError::ConfigError("PASSWORD_MIN_LENGTH must be a number of at least 8".to_string()))?,
+            
+            password_require_mixed_case: var("PASSWORD_REQUIRE_MIXED_CASE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("PASSWORD_REQUIRE_MIXED_CASE must be either true or false".to_string()))?,
+            
+            password_require_digit: var("PASSWORD_REQUIRE_DIGIT")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("PASSWORD_REQUIRE_DIGIT must be either true or false".to_string()))?,
+            
+            password_require_symbol: var("PASSWORD_REQUIRE_SYMBOL")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("PASSWORD_REQUIRE_SYMBOL must be either true or false".to_string()))?,
+            
+            password_reject_common: var("PASSWORD_REJECT_COMMON")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("PASSWORD_REJECT_COMMON must be either true or false".to_string()))?,
+            
+            rate_limit_per_minute: var("RATE_LIMIT_PER_MINUTE")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("RATE_LIMIT_PER_MINUTE must be a valid number".to_string()))?,
+            
+            upload_rate_limit_per_minute: var("UPLOAD_RATE_LIMIT_PER_MINUTE")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .ok()
+                .filter(|uploads: &usize| *uploads > 0)
+                .ok_or_else(|| AppError::ConfigError("UPLOAD_RATE_LIMIT_PER_MINUTE must be a positive number".to_string()))?,
+            
+            max_concurrent_jobs_per_user: var("MAX_CONCURRENT_JOBS_PER_USER")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .ok()
+                .filter(|jobs: &usize| *jobs > 0)
+                .ok_or_else(|| AppError::ConfigError("MAX_CONCURRENT_JOBS_PER_USER must be a positive number".to_string()))?,
+            
+            trusted_proxies: var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|proxy| !proxy.is_empty())
+                .map(IpNetwork::parse)
+                .collect::<Option<_>>()
+                .ok_or_else(|| AppError::ConfigError("TRUSTED_PROXIES must be a comma-separated list of IP addresses or CIDR ranges".to_string()))?,
+            
+            shutdown_timeout_seconds: var("SHUTDOWN_TIMEOUT")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("SHUTDOWN_TIMEOUT must be a valid number of seconds".to_string()))?,
+            
+            transcription_timeout_seconds: var("TRANSCRIPTION_TIMEOUT_SECONDS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .ok()
+                .filter(|seconds| *seconds > 0)
+                .ok_or_else(|| AppError::ConfigError("TRANSCRIPTION_TIMEOUT_SECONDS must be a positive number".to_string()))?,
+            
+            transcription_workers: var("TRANSCRIPTION_WORKERS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .ok()
+                .filter(|workers: &usize| *workers > 0)
+                .ok_or_else(|| AppError::ConfigError("TRANSCRIPTION_WORKERS must be a positive number".to_string()))?,
+            
+            transcription_queue_size: var("TRANSCRIPTION_QUEUE_SIZE")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()
+                .ok()
+                .filter(|size: &usize| *size > 0)
+                .ok_or_else(|| AppError::ConfigError("TRANSCRIPTION_QUEUE_SIZE must be a positive number".to_string()))?,
+            
+            max_ffmpeg_concurrency: match var("MAX_FFMPEG_CONCURRENCY") {
+                Ok(limit) => limit
+                    .parse()
+                    .ok()
+                    .filter(|limit| *limit > 0)
+                    .ok_or_else(|| AppError::ConfigError("MAX_FFMPEG_CONCURRENCY must be a positive number".to_string()))?,
+                Err(_) => num_cpus::get(),
+            },
+            
+            temp_cleanup_interval_seconds: var("TEMP_CLEANUP_INTERVAL_SECONDS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .ok()
+                .filter(|seconds| *seconds > 0)
+                .ok_or_else(|| AppError::ConfigError("TEMP_CLEANUP_INTERVAL_SECONDS must be a positive number".to_string()))?,
+            
+            temp_file_max_age_seconds: var("TEMP_FILE_MAX_AGE_SECONDS")
+                .unwrap_or_else(|_| "21600".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("TEMP_FILE_MAX_AGE_SECONDS must be a valid number of seconds".to_string()))?,
+            
+            max_transcription_attempts: var("MAX_TRANSCRIPTION_ATTEMPTS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .ok()
+                .filter(|attempts| *attempts > 0)
+                .ok_or_else(|| AppError::ConfigError("MAX_TRANSCRIPTION_ATTEMPTS must be a positive number".to_string()))?,
+            
+            allowed_origins: var("ALLOWED_ORIGINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+            
+            cors_permissive: var("CORS_PERMISSIVE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("CORS_PERMISSIVE must be either true or false".to_string()))?,
+            
+            compression_enabled: var("COMPRESSION_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("COMPRESSION_ENABLED must be either true or false".to_string()))?,
+            
+            response_envelope: var("RESPONSE_ENVELOPE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("RESPONSE_ENVELOPE must be either true or false".to_string()))?,
+            
+            cookie_secure: var("COOKIE_SECURE")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .map_err(|_| AppError::ConfigError("COOKIE_SECURE must be either true or false".to_string()))?,
+            
+            cookie_same_site: match var("COOKIE_SAME_SITE")
+                .unwrap_or_else(|_| "strict".to_string())
+                .to_lowercase()
+                .as_str()
+            {
+                same_site @ ("strict" | "lax" | "none") => same_site.to_string(),
+                _ => {
+                    return Err(AppError::ConfigError(
+                        "COOKIE_SAME_SITE must be one of: strict, lax, none".to_string(),
+                    ))
+                }
+            },
+            
+            cookie_domain: var("COOKIE_DOMAIN").ok().filter(|domain| !domain.is_empty()),
+            
+            log_level: match var("LOG_LEVEL")
+                .unwrap_or_else(|_| "info".to_string())
+                .to_lowercase()
+                .as_str()
+            {
+                level @ ("trace" | "debug" | "info" | "warn" | "error") => level.to_string(),
+                _ => {
+                    return Err(AppError::ConfigError(
+                        "LOG_LEVEL must be one of: trace, debug, info, warn, error".to_string(),
+                    ))
+                }
+            },
+            
+            log_format: match var("LOG_FORMAT")
+                .unwrap_or_else(|_| "json".to_string())
+                .to_lowercase()
+                .as_str()
+            {
+                format @ ("json" | "text") => format.to_string(),
+                _ => {
+                    return Err(AppError::ConfigError(
+                        "LOG_FORMAT must be either 'json' or 'text'".to_string(),
+                    ))
+                }
+            },
+        };
+
+        // The secret is only needed when tokens are signed with it
+        if config.jwt_algorithm == "HS256" && config.jwt_secret.is_empty() {
+            return Err(AppError::ConfigError("JWT_SECRET must be set".to_string()));
+        }
+
+        if config.jwt_algorithm == "RS256" && config.jwt_private_key_path.is_none() {
+            return Err(AppError::ConfigError(
+                "JWT_PRIVATE_KEY_PATH must be set when JWT_ALGORITHM is RS256".to_string(),
+            ));
+        }
+
+        // Fail fast on Argon2 parameters the library would reject
+        config.argon2_params()?;
+
+        if config.default_page_size > config.max_page_size {
+            return Err(AppError::ConfigError(
+                "DEFAULT_PAGE_SIZE cannot exceed MAX_PAGE_SIZE".to_string(),
+            ));
+        }
+
+        if config.db_min_connections > config.db_max_connections {
+            return Err(AppError::ConfigError(
+                "DB_MIN_CONNECTIONS cannot exceed DB_MAX_CONNECTIONS".to_string(),
+            ));
+        }
+
+        // Browsers drop SameSite=None cookies that aren't also Secure
+        if config.cookie_same_site == "none" && !config.cookie_secure {
+            return Err(AppError::ConfigError(
+                "COOKIE_SAME_SITE=none requires COOKIE_SECURE=true".to_string(),
+            ));
+        }
+
+        if config.supported_formats.is_empty() {
+            return Err(AppError::ConfigError(
+                "SUPPORTED_FORMATS must list at least one file extension".to_string(),
+            ));
+        }
+
+        if config.live_step_seconds > config.live_window_seconds {
+            return Err(AppError::ConfigError(
+                "LIVE_STEP_SECONDS cannot exceed LIVE_WINDOW_SECONDS".to_string(),
+            ));
+        }
+
+        if config.whisper_chunk_overlap_seconds >= config.whisper_chunk_seconds {
+            return Err(AppError::ConfigError(
+                "WHISPER_CHUNK_OVERLAP must be shorter than WHISPER_CHUNK_SECONDS".to_string(),
+            ));
+        }
+
+        // A running job's intermediate WAV is as old as the job, so it must not count as orphaned
+        if config.temp_file_max_age_seconds <= config.transcription_timeout_seconds {
+            return Err(AppError::ConfigError(
+                "TEMP_FILE_MAX_AGE_SECONDS must be longer than TRANSCRIPTION_TIMEOUT_SECONDS".to_string(),
+            ));
+        }
+
+        config.ensure_temp_dir()?;
+
+        if !config.whisper_models.contains_key(&config.whisper_default_model) {
+            return Err(AppError::ConfigError(format!(
+                "WHISPER_DEFAULT_MODEL {} is not one of the configured Whisper models",
+                config.whisper_default_model
+            )));
+        }
+
+        if config.whisper_max_loaded_models.is_some() && !config.whisper_lazy_load {
+            return Err(AppError::ConfigError(
+                "WHISPER_MAX_LOADED_MODELS requires WHISPER_LAZY_LOAD=true".to_string(),
+            ));
+        }
+
+        for (name, path) in &config.whisper_models {
+            if !std::path::Path::new(path).is_file() {
+                return Err(AppError::ConfigError(format!(
+                    "Whisper model {} at {} does not exist or is not a file",
+                    name, path
+                )));
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Parse `WHISPER_MODELS`, a comma-separated list of `name=path` pairs
+    fn parse_whisper_models(value: &str) -> Result<BTreeMap<String, String>, AppError> {
+        let mut models = BTreeMap::new();
+
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (name, path) = entry
+                .split_once('=')
+                .map(|(name, path)| (name.trim(), path.trim()))
+                .filter(|(name, path)| !name.is_empty() && !path.is_empty())
+                .ok_or_else(|| {
+                    AppError::ConfigError(format!("WHISPER_MODELS entry {} must be name=path", entry))
+                })?;
+
+            if models.insert(name.to_string(), path.to_string()).is_some() {
+                return Err(AppError::ConfigError(format!(
+                    "WHISPER_MODELS lists the model {} more than once",
+                    name
+                )));
+            }
+        }
+
+        if models.is_empty() {
+            return Err(AppError::ConfigError(
+                "WHISPER_MODELS must list at least one model".to_string(),
+            ));
+        }
+
+        Ok(models)
+    }
+
+    /// Name of the first model in `WHISPER_MODELS`, in the order it was written
+    fn first_listed_model(value: &str) -> Option<String> {
+        value
+            .split(',')
+            .find_map(|entry| entry.split_once('='))
+            .map(|(name, _)| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Name of a model file, which names the model when only `WHISPER_MODEL_PATH` is set
+    fn model_file_name(path: &str) -> String {
+        std::path::Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(path)
+            .to_string()
+    }
+
+    /// Log filter directives for `LOG_LEVEL`
+    /// sqlx logs every query at info, so it stays at warn unless debugging
+    pub fn log_filter(&self) -> String {
+        let sqlx_level = match self.log_level.as_str() {
+            "info" => "warn",
+            level => level,
+        };
+        format!("{level},actix_web={level},sqlx={sqlx_level}", level = self.log_level)
+    }
+
+    /// Parse `SUPPORTED_FORMATS`, comma-separated extensions with or without a leading dot
+    /// An unset or blank value gives the default formats.
+    fn parse_supported_formats(value: Option<&str>) -> Vec<String> {
+        match value.filter(|formats| !formats.trim().is_empty()) {
+            Some(formats) => formats
+                .split(',')
+                .map(|format| format.trim().trim_start_matches('.').to_lowercase())
+                .filter(|format| !format.is_empty())
+                .collect(),
+            None => file::DEFAULT_SUPPORTED_FORMATS
+                .iter()
+                .map(|format| format.to_string())
+                .collect(),
+        }
+    }
+
+    /// Parse `PUBLIC_URL`, an http(s) origin without a trailing slash; unset or blank means none
+    fn parse_public_url(value: Option<&str>) -> Result<Option<String>, AppError> {
+        let Some(url) = value.map(str::trim).filter(|url| !url.is_empty()) else {
+            return Ok(None);
+        };
+
+        let url = url.trim_end_matches('/');
+        let is_origin = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))
+            .is_some_and(|host| !host.is_empty() && !host.contains(['/', '?', '#']));
+        if !is_origin {
+            return Err(AppError::ConfigError(
+                "PUBLIC_URL must be an http:// or https:// origin without a path".to_string(),
+            ));
+        }
+
+        Ok(Some(url.to_string()))
+    }
+
+    /// Create `TEMP_DIR` if missing and prove it is writable, so uploads can't fail on it later
+    fn ensure_temp_dir(&self) -> Result<(), AppError> {
+        std::fs::create_dir_all(&self.temp_dir).map_err(|e| {
+            AppError::ConfigError(format!("TEMP_DIR {} cannot be created: {}", self.temp_dir, e))
+        })?;
+
+        let probe = std::path::Path::new(&self.temp_dir)
+            .join(format!(".write_probe_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&probe, b"probe").map_err(|e| {
+            AppError::ConfigError(format!("TEMP_DIR {} is not writable: {}", self.temp_dir, e))
+        })?;
+        std::fs::remove_file(&probe).map_err(|e| {
+            AppError::ConfigError(format!("TEMP_DIR {} is not writable: {}", self.temp_dir, e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Argon2 cost parameters used when hashing new passwords
+    pub fn argon2_params(&self) -> Result<argon2::Params, AppError> {
+        argon2::Params::new(
+            self.argon2_memory_kib,
+            self.argon2_iterations,
+            self.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| AppError::ConfigError(format!("Invalid Argon2 parameters: {}", e)))
+    }
+
+    /// Rules new passwords must meet on registration and reset
+    pub fn password_policy(&self) -> crate::utils::password::PasswordPolicy {
+        crate::utils::password::PasswordPolicy {
+            min_length: self.password_min_length,
+            require_mixed_case: self.password_require_mixed_case,
+            require_digit: self.password_require_digit,
+            require_symbol: self.password_require_symbol,
+            reject_common: self.password_reject_common,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Config {
+    /// Configuration built from fixed values, leaving the process environment alone
+    pub fn for_tests() -> Self {
+        let values = [
+            ("DATABASE_URL", "postgresql://localhost/ai_scribe_test"),
+            ("JWT_SECRET", "test-secret"),
+            // Only checked to be a file, never loaded
+            ("WHISPER_MODEL_PATH", concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")),
+        ];
+
+        Self::from_lookup(|key| {
+            values
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+                .ok_or(env::VarError::NotPresent)
         })
+        .expect("test configuration")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Vec<String> {
+        file::DEFAULT_SUPPORTED_FORMATS.iter().map(|format| format.to_string()).collect()
+    }
+
+    #[test]
+    fn supported_formats_default_when_unset_or_blank() {
+        assert_eq!(Config::parse_supported_formats(None), defaults());
+        assert_eq!(Config::parse_supported_formats(Some("")), defaults());
+        assert_eq!(Config::parse_supported_formats(Some("  ")), defaults());
+    }
+
+    #[test]
+    fn supported_formats_override_replaces_defaults() {
+        assert_eq!(Config::parse_supported_formats(Some("mp3,wav")), vec!["mp3", "wav"]);
+    }
+
+    #[test]
+    fn supported_formats_are_normalized() {
+        let formats = Config::parse_supported_formats(Some(" MP3 , .Wav,,.FLAC "));
+
+        assert_eq!(formats, vec!["mp3", "wav", "flac"]);
+    }
+
+    #[test]
+    fn supported_formats_without_extensions_are_empty() {
+        // Rejected by `from_env` rather than silently falling back
+        assert!(Config::parse_supported_formats(Some(", .")).is_empty());
+    }
+
+    #[test]
+    fn public_url_is_optional_and_normalized() {
+        assert_eq!(Config::parse_public_url(None).unwrap(), None);
+        assert_eq!(Config::parse_public_url(Some("  ")).unwrap(), None);
+        assert_eq!(
+            Config::parse_public_url(Some(" https://scribe.example.com/ ")).unwrap().as_deref(),
+            Some("https://scribe.example.com")
+        );
+        assert_eq!(
+            Config::parse_public_url(Some("http://localhost:8080")).unwrap().as_deref(),
+            Some("http://localhost:8080")
+        );
+    }
+
+    #[test]
+    fn public_url_must_be_an_origin() {
+        for url in ["scribe.example.com", "ftp://scribe.example.com", "https://", "https://example.com/api"] {
+            assert!(Config::parse_public_url(Some(url)).is_err(), "{} was accepted", url);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parsed_formats_drive_upload_checks() {
+        let formats = Config::parse_supported_formats(Some("ogg, .M4A"));
+
+        assert!(file::is_supported_audio_format("memo.m4a", &formats));
+        assert!(file::is_supported_audio_format("song.OGG", &formats));
+        assert!(!file::is_supported_audio_format("song.mp3", &formats));
+        assert!(!file::is_supported_audio_format("ogg", &formats));
+        assert!(file::is_supported_audio_format("song.mp3", &defaults()));
+    }
+}
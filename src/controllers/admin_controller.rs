@@ -0,0 +1,116 @@
+use crate::controllers::PaginationQuery;
+use crate::AppState;
+use crate::errors::{AppError, AppResult};
+use crate::middlewares::extract_user_id;
+use crate::models::*;
+use crate::services::{TranscriptionService, UserService};
+use actix_web::{HttpRequest, HttpResponse, web};
+use uuid::Uuid;
+
+/// Administration controller, mounted behind `RequireRole::admin()`
+pub struct AdminController;
+
+impl AdminController {
+    /// List transcripts of every user with pagination
+    pub async fn get_all_transcripts(
+        app_state: web::Data<AppState>,
+        query: web::Query<PaginationQuery>,
+    ) -> AppResult<HttpResponse> {
+        let (page, limit) = query.page_and_limit(&app_state.config)?;
+
+        let (transcripts, total) =
+            TranscriptionService::get_all_transcripts(&app_state.db, page, limit).await?;
+
+        let response = PaginatedResponse::new(
+            transcripts
+                .into_iter()
+                .map(|transcript| AdminTranscriptResponse::new(transcript, app_state.config.reading_wpm))
+                .collect(),
+            page,
+            limit,
+            total,
+        );
+
+        Ok(HttpResponse::Ok().json(response))
+    }
+
+    /// Delete a user account and everything it owns
+    pub async fn delete_user(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        let admin_id = extract_user_id(&req)?;
+        let user_id = path.into_inner();
+
+        // Guard against an administrator locking everyone out by deleting themselves
+        if admin_id == user_id {
+            return Err(AppError::ValidationError(
+                "Administrators cannot delete their own account".to_string(),
+            ));
+        }
+
+        let transcripts = UserService::delete_user(&app_state.db, user_id).await?;
+        app_state.account_status.invalidate(user_id);
+
+        // Uploads of unfinished jobs and stored originals would otherwise be left behind
+        TranscriptionService::remove_transcript_files(app_state.storage.as_ref(), &transcripts)
+            .await;
+
+        log::info!("User {} deleted by administrator {}", user_id, admin_id);
+
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "User deleted successfully"
+        })))
+    }
+
+    /// Disable a user account; its tokens stop working and it can no longer sign in
+    pub async fn disable_user(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        Self::set_user_disabled(app_state, req, path.into_inner(), true).await
+    }
+
+    /// Re-enable a disabled user account
+    pub async fn enable_user(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        Self::set_user_disabled(app_state, req, path.into_inner(), false).await
+    }
+
+    /// Helper function shared by `disable_user` and `enable_user`
+    async fn set_user_disabled(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        user_id: Uuid,
+        disabled: bool,
+    ) -> AppResult<HttpResponse> {
+        let admin_id = extract_user_id(&req)?;
+
+        // Guard against an administrator locking themselves out
+        if admin_id == user_id {
+            return Err(AppError::ValidationError(
+                "Administrators cannot disable their own account".to_string(),
+            ));
+        }
+
+        let user = UserService::set_disabled(&app_state.db, user_id, disabled).await?;
+        app_state.account_status.invalidate(user_id);
+
+        log::info!(
+            "User {} {} by administrator {}",
+            user_id,
+            if disabled { "disabled" } else { "enabled" },
+            admin_id
+        );
+
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "disabled_at": user.disabled_at,
+            "user": UserResponse::from(user)
+        })))
+    }
+}
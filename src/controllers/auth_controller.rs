@@ -1,14 +1,17 @@
 // controllers/mod.rs - Route handlers and response logic
 use crate::AppState;
+use crate::config::Config;
 use crate::errors::{AppError, AppResult};
-use crate::middlewares::extract_user_id;
+use crate::middlewares::{client_ip, extract_user_id};
 use crate::models::*;
-use crate::services::UserService;
-use crate::utils::{jwt, validation};
+use crate::notifications::AccountNotice;
+use crate::services::{TranscriptionService, UserService};
+use crate::utils::{jwt, password, validation};
 use actix_web::cookie::time::Duration;
 use actix_web::{
     HttpRequest, HttpResponse,
     cookie::{Cookie, SameSite},
+    http::header,
     web,
 };
 use uuid::Uuid;
@@ -24,78 +27,135 @@ impl AuthController {
     ) -> AppResult<HttpResponse> {
         // Validate request
         validation::validate_request(&*request)?;
+        password::validate_strength(
+            &request.password,
+            &app_state.config.password_policy(),
+            "password",
+        )?;
 
         // Register user
-        let user =
-            UserService::register_user(&app_state.db, &request.email, &request.password).await?;
-
-        // Generate tokens
-        let access_token = jwt::generate_access_token(
-            user.id,
-            &user.email,
-            &app_state.config.jwt_secret,
-            app_state.config.access_token_expires_in,
-        )?;
+        let user = UserService::register_user(
+            &app_state.db,
+            &request.email,
+            &request.password,
+            &app_state.config.argon2_params()?,
+        )
+        .await?;
 
-        let refresh_token = jwt::generate_refresh_token(
-            user.id,
-            &user.email,
-            &app_state.config.jwt_secret,
-            app_state.config.refresh_token_expires_in,
-        )?;
+        // Issue an email verification token instead of treating the account as active
+        let verification_token =
+            UserService::create_email_verification(&app_state.db, user.id).await?;
 
-        let cookie = Cookie::build("refresh_token", refresh_token)
-            .path("/")
-            .http_only(true)
-            .secure(true)
-            .max_age(Duration::days(7))
-            .same_site(SameSite::Strict)
-            .finish();
+        let notice = AccountNotice::EmailVerification { token: &verification_token };
+        if let Err(e) = app_state.notifier.send(&user.email, notice).await {
+            log::error!("Failed to send email verification notice: {}", e);
+        }
 
-        let response = AuthResponse {
-            access_token,
-            token_type: "Bearer".to_string(),
-            expires_in: app_state.config.access_token_expires_in * 60, // Convert to seconds
+        let response = RegisterResponse {
+            message: "Registration successful. Please verify your email address before transcribing."
+                .to_string(),
+            verification_pending: true,
             user: user.into(),
         };
 
-        Ok(HttpResponse::Created().cookie(cookie).json(response))
+        Ok(HttpResponse::Created().json(response))
+    }
+
+    /// Verify a user's email address
+    pub async fn verify_email(
+        app_state: web::Data<AppState>,
+        query: web::Query<VerifyEmailQuery>,
+    ) -> AppResult<HttpResponse> {
+        let user = UserService::verify_email(&app_state.db, &query.token).await?;
+
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Email verified successfully",
+            "user": UserResponse::from(user)
+        })))
+    }
+
+    /// Issue a fresh verification token, e.g. when the first one expired
+    pub async fn resend_verification(
+        app_state: web::Data<AppState>,
+        request: web::Json<ResendVerificationRequest>,
+    ) -> AppResult<HttpResponse> {
+        validation::validate_request(&*request)?;
+
+        if !app_state.notifier.is_enabled() {
+            return Err(AppError::NotImplemented(
+                "Verification emails are not configured on this server".to_string(),
+            ));
+        }
+
+        if let Some(verification_token) =
+            UserService::renew_email_verification(&app_state.db, &request.email).await?
+        {
+            let notice = AccountNotice::EmailVerification { token: &verification_token };
+            if let Err(e) = app_state.notifier.send(&request.email, notice).await {
+                log::error!("Failed to send email verification notice: {}", e);
+            }
+        }
+
+        // Same answer whether the email is unknown, unverified or already verified
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "If an unverified account exists for this email, a new verification link has been sent"
+        })))
     }
 
     /// Login user
     pub async fn login(
         app_state: web::Data<AppState>,
+        req: HttpRequest,
         request: web::Json<LoginRequest>,
     ) -> AppResult<HttpResponse> {
         // Validate request
         validation::validate_request(&*request)?;
 
         // Authenticate user
-        let user = UserService::authenticate_user(&app_state.db, &request.email, &request.password)
-            .await?;
+        let user = UserService::authenticate_user(
+            &app_state.db,
+            &request.email,
+            &request.password,
+            &app_state.config.argon2_params()?,
+        )
+        .await?;
 
         // Generate tokens
         let access_token = jwt::generate_access_token(
             user.id,
             &user.email,
-            &app_state.config.jwt_secret,
+            &user.role,
+            &app_state.jwt_keys,
             app_state.config.access_token_expires_in,
         )?;
 
+        // Each refresh token is tracked as a session keyed by its jti
+        let session_id = Uuid::new_v4().to_string();
         let refresh_token = jwt::generate_refresh_token(
             user.id,
             &user.email,
-            &app_state.config.jwt_secret,
+            &user.role,
+            &session_id,
+            &app_state.jwt_keys,
             app_state.config.refresh_token_expires_in,
         )?;
 
-        let cookie = Cookie::build("refresh_token", refresh_token)
-            .path("/")
-            .http_only(true)
-            .secure(true)
-            .max_age(Duration::days(7))
-            .same_site(SameSite::Strict)
-            .finish();
+        let user_agent = req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok());
+        let ip = client_ip(&req);
+        UserService::create_session(
+            &app_state.db,
+            &session_id,
+            user.id,
+            user_agent,
+            Some(ip.as_str()),
+            app_state.config.refresh_token_expires_in,
+        )
+        .await?;
+
+        let cookie = refresh_token_cookie(&app_state.config, refresh_token, Duration::days(7));
 
         let response = AuthResponse {
             access_token,
@@ -122,8 +182,12 @@ impl AuthController {
                 ));
             }
         };
-        let claims =
-            UserService::verify_refresh_token(&refresh_token, &app_state.config.jwt_secret)?;
+        let claims = UserService::verify_refresh_token(
+            &app_state.db,
+            &refresh_token,
+            &app_state.jwt_keys,
+        )
+        .await?;
 
         // Get user from database to ensure they still exist
         let user_id: Uuid = claims
@@ -132,21 +196,46 @@ impl AuthController {
             .map_err(|_| AppError::AuthError("Invalid user ID in token".to_string()))?;
 
         let user = UserService::get_user_by_id(&app_state.db, user_id).await?;
+        if user.disabled_at.is_some() {
+            return Err(AppError::Forbidden);
+        }
 
         // Generate new tokens
         let access_token = jwt::generate_access_token(
             user.id,
             &user.email,
-            &app_state.config.jwt_secret,
+            &user.role,
+            &app_state.jwt_keys,
             app_state.config.access_token_expires_in,
         )?;
 
-        // let refresh_token = jwt::generate_refresh_token(
-        //     user.id,
-        //     &user.email,
-        //     &app_state.config.jwt_secret,
-        //     app_state.config.refresh_token_expires_in,
-        // )?;
+        // Rotate the refresh token so each one can only be used once
+        let session_id = Uuid::new_v4().to_string();
+        let refresh_token = jwt::generate_refresh_token(
+            user.id,
+            &user.email,
+            &user.role,
+            &session_id,
+            &app_state.jwt_keys,
+            app_state.config.refresh_token_expires_in,
+        )?;
+
+        let user_agent = request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok());
+        let ip = client_ip(&request);
+        UserService::rotate_session(
+            &app_state.db,
+            &claims,
+            &session_id,
+            user_agent,
+            Some(ip.as_str()),
+            app_state.config.refresh_token_expires_in,
+        )
+        .await?;
+
+        let cookie = refresh_token_cookie(&app_state.config, refresh_token, Duration::days(7));
 
         let response = AuthResponse {
             access_token,
@@ -155,9 +244,133 @@ impl AuthController {
             user: user.into(),
         };
 
+        Ok(HttpResponse::Ok().cookie(cookie).json(response))
+    }
+
+    /// Request a password reset token
+    pub async fn forgot_password(
+        app_state: web::Data<AppState>,
+        request: web::Json<ForgotPasswordRequest>,
+    ) -> AppResult<HttpResponse> {
+        validation::validate_request(&*request)?;
+
+        // A token nobody can receive would only be a dangling credential
+        if !app_state.notifier.is_enabled() {
+            return Err(AppError::NotImplemented(
+                "Password reset emails are not configured on this server".to_string(),
+            ));
+        }
+
+        if let Some(reset_token) =
+            UserService::create_password_reset(&app_state.db, &request.email).await?
+        {
+            let notice = AccountNotice::PasswordReset { token: &reset_token };
+            // A failure is only logged, so the response can't reveal that the account exists
+            if let Err(e) = app_state.notifier.send(&request.email, notice).await {
+                log::error!("Failed to send password reset notice: {}", e);
+            }
+        }
+
+        // Always respond the same way to avoid revealing which emails are registered
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "If an account exists for this email, a password reset link has been sent"
+        })))
+    }
+
+    /// Reset password using a reset token
+    pub async fn reset_password(
+        app_state: web::Data<AppState>,
+        request: web::Json<ResetPasswordRequest>,
+    ) -> AppResult<HttpResponse> {
+        validation::validate_request(&*request)?;
+        password::validate_strength(
+            &request.new_password,
+            &app_state.config.password_policy(),
+            "new_password",
+        )?;
+
+        UserService::reset_password(
+            &app_state.db,
+            &request.token,
+            &request.new_password,
+            &app_state.config.argon2_params()?,
+        )
+        .await?;
+
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Password has been reset successfully"
+        })))
+    }
+
+    /// Logout user by revoking the refresh token and clearing the cookie
+    pub async fn logout(
+        app_state: web::Data<AppState>,
+        request: HttpRequest,
+    ) -> AppResult<HttpResponse> {
+        if let Some(cookie) = request.cookie("refresh_token") {
+            // An invalid or expired token needs no revocation, so just clear the cookie
+            match jwt::verify_token(cookie.value(), &app_state.jwt_keys) {
+                Ok(claims) if claims.token_type == "refresh" => {
+                    UserService::revoke_refresh_token(&app_state.db, &claims).await?;
+                }
+                _ => log::debug!("Logout called with an invalid refresh token"),
+            }
+        }
+
+        // Overwrite the cookie with an expired one using the same path and flags
+        let cookie = refresh_token_cookie(&app_state.config, String::new(), Duration::ZERO);
+
+        Ok(HttpResponse::Ok().cookie(cookie).json(serde_json::json!({
+            "message": "Logged out successfully"
+        })))
+    }
+
+    /// List the current user's active sessions
+    pub async fn list_sessions(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+
+        // The refresh cookie identifies which session made this request
+        let current_session = req
+            .cookie("refresh_token")
+            .and_then(|cookie| jwt::verify_token(cookie.value(), &app_state.jwt_keys).ok())
+            .map(|claims| claims.jti);
+
+        let sessions = UserService::get_active_sessions(&app_state.db, user_id).await?;
+        let response: Vec<SessionResponse> = sessions
+            .into_iter()
+            .map(|session| SessionResponse {
+                current: current_session.as_deref() == Some(session.id.as_str()),
+                id: session.id,
+                user_agent: session.user_agent,
+                ip: session.ip,
+                created_at: session.created_at,
+                last_used_at: session.last_used_at,
+                expires_at: session.expires_at,
+            })
+            .collect();
+
         Ok(HttpResponse::Ok().json(response))
     }
 
+    /// Revoke one of the current user's sessions
+    pub async fn revoke_session(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<String>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let session_id = path.into_inner();
+
+        UserService::revoke_session(&app_state.db, user_id, &session_id).await?;
+
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Session revoked successfully"
+        })))
+    }
+
     /// Get current user profile
     pub async fn me(app_state: web::Data<AppState>, req: HttpRequest) -> AppResult<HttpResponse> {
         let user_id = extract_user_id(&req)?;
@@ -165,4 +378,96 @@ impl AuthController {
 
         Ok(HttpResponse::Ok().json(UserResponse::from(user)))
     }
+
+    /// Publish the public keys that verify access and refresh tokens (empty for HS256)
+    pub async fn jwks(app_state: web::Data<AppState>) -> AppResult<HttpResponse> {
+        Ok(HttpResponse::Ok()
+            .insert_header((header::CACHE_CONTROL, "public, max-age=300"))
+            .json(app_state.jwt_keys.jwks()))
+    }
+
+    /// Permanently delete the current user's account and all of its data
+    pub async fn delete_account(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        request: web::Json<DeleteAccountRequest>,
+    ) -> AppResult<HttpResponse> {
+        validation::validate_request(&*request)?;
+        let user_id = extract_user_id(&req)?;
+
+        // An access token can outlive the account; repeating the request is not an error
+        let cleared_cookie = refresh_token_cookie(&app_state.config, String::new(), Duration::ZERO);
+        let user = match UserService::get_user_by_id(&app_state.db, user_id).await {
+            Ok(user) => user,
+            Err(AppError::NotFound(_)) => {
+                return Ok(HttpResponse::NoContent().cookie(cleared_cookie).finish());
+            }
+            Err(e) => return Err(e),
+        };
+
+        if !password::verify_password(&request.password, &user.password_hash)? {
+            return Err(AppError::AuthError("Invalid password".to_string()));
+        }
+
+        // Transcripts, segments, sessions and tokens go with the user row in one transaction
+        let transcripts = match UserService::delete_user(&app_state.db, user_id).await {
+            Ok(transcripts) => transcripts,
+            Err(AppError::NotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        app_state.account_status.invalidate(user_id);
+        TranscriptionService::remove_transcript_files(app_state.storage.as_ref(), &transcripts)
+            .await;
+
+        log::info!("User {} deleted their account", user_id);
+
+        Ok(HttpResponse::NoContent().cookie(cleared_cookie).finish())
+    }
+}
+
+/// Build the refresh token cookie with consistent path and the configured security flags
+fn refresh_token_cookie(config: &Config, value: String, max_age: Duration) -> Cookie<'static> {
+    let same_site = match config.cookie_same_site.as_str() {
+        "lax" => SameSite::Lax,
+        "none" => SameSite::None,
+        _ => SameSite::Strict,
+    };
+
+    let mut cookie = Cookie::build("refresh_token", value)
+        .path("/")
+        .http_only(true) // Never readable from JavaScript, whatever the environment
+        .secure(config.cookie_secure)
+        .max_age(max_age)
+        .same_site(same_site)
+        .finish();
+
+    if let Some(domain) = &config.cookie_domain {
+        cookie.set_domain(domain.clone());
+    }
+
+    cookie
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleared_refresh_cookie_matches_issued_cookie() {
+        let config = Config::for_tests();
+        let issued = refresh_token_cookie(&config, "token".to_string(), Duration::days(7));
+
+        let cleared = refresh_token_cookie(&config, String::new(), Duration::ZERO);
+
+        assert_eq!(cleared.name(), issued.name());
+        assert_eq!(cleared.value(), "");
+        assert_eq!(cleared.path(), issued.path());
+        assert_eq!(cleared.domain(), issued.domain());
+        assert_eq!(cleared.http_only(), Some(true));
+        assert_eq!(cleared.secure(), issued.secure());
+        assert_eq!(cleared.same_site(), issued.same_site());
+        assert_eq!(cleared.max_age(), Some(Duration::ZERO));
+        assert!(cleared.to_string().contains("Max-Age=0"));
+    }
 }
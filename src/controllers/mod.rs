@@ -1,30 +1,228 @@
 // controllers/mod.rs - Route handlers and response logic
-use crate::errors::AppResult;
-use actix_web::HttpResponse;
+use crate::AppState;
+use crate::config::Config;
+use crate::errors::{AppError, AppResult};
+use crate::models::{TranscriptFilter, TranscriptSort};
+use crate::utils::validation;
+use actix_web::{HttpResponse, web};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use serde_json::json;
 
+pub mod admin_controller;
 pub mod auth_controller;
+pub mod stream_controller;
 pub mod transcription_controller;
+pub mod webhook_controller;
 
+pub use admin_controller::*;
 pub use auth_controller::*;
+pub use stream_controller::*;
 pub use transcription_controller::*;
+pub use webhook_controller::*;
 
 /// Health check controller
 pub struct HealthController;
 
+/// Status of the services the application depends on
+#[derive(Debug, Serialize)]
+pub struct DependencyStatus {
+    pub database: bool,
+    pub ffmpeg: bool,
+    pub whisper_model: bool,
+}
+
+impl DependencyStatus {
+    /// Whether every dependency required to serve requests is up
+    pub fn critical_ok(&self) -> bool {
+        self.database && self.whisper_model
+    }
+
+    /// Whether every dependency is up
+    pub fn all_ok(&self) -> bool {
+        self.critical_ok() && self.ffmpeg
+    }
+}
+
 impl HealthController {
-    /// Health check endpoint
-    pub async fn health() -> AppResult<HttpResponse> {
+    /// Health check endpoint reporting the status of each dependency
+    pub async fn health(app_state: web::Data<AppState>) -> AppResult<HttpResponse> {
+        let dependencies = Self::check_dependencies(&app_state).await;
+
+        let status = if dependencies.all_ok() { "healthy" } else { "degraded" };
+        let mut response = if dependencies.critical_ok() {
+            HttpResponse::Ok()
+        } else {
+            HttpResponse::ServiceUnavailable()
+        };
+
+        Ok(response.json(json!({
+            "status": status,
+            "dependencies": dependencies,
+            "timestamp": chrono::Utc::now()
+        })))
+    }
+
+    /// Liveness probe: succeeds whenever the process can serve requests, without touching the DB
+    pub async fn live() -> AppResult<HttpResponse> {
         Ok(HttpResponse::Ok().json(json!({
-            "status": "healthy",
+            "status": "alive",
+            "timestamp": chrono::Utc::now()
+        })))
+    }
+
+    /// Readiness probe: 503 unless the default Whisper model and the database are available
+    pub async fn ready(app_state: web::Data<AppState>) -> AppResult<HttpResponse> {
+        let dependencies = Self::check_dependencies(&app_state).await;
+
+        let mut response = if dependencies.critical_ok() {
+            HttpResponse::Ok()
+        } else {
+            HttpResponse::ServiceUnavailable()
+        };
+
+        Ok(response.json(json!({
+            "status": if dependencies.critical_ok() { "ready" } else { "not_ready" },
+            "dependencies": dependencies,
             "timestamp": chrono::Utc::now()
         })))
     }
+
+    /// Run cheap checks against the database, FFmpeg and the Whisper model
+    async fn check_dependencies(app_state: &AppState) -> DependencyStatus {
+        let database = sqlx::query("SELECT 1").execute(&app_state.db).await.is_ok();
+
+        let ffmpeg = tokio::process::Command::new("ffmpeg")
+            .arg("-version")
+            .output()
+            .await
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+
+        let whisper_model = app_state.whisper_models.default_model_available().await;
+
+        DependencyStatus {
+            database,
+            ffmpeg,
+            whisper_model,
+        }
+    }
 }
 
-/// Query parameters for pagination
+/// Query parameters for transcript export
+#[derive(serde::Deserialize)]
+pub struct ExportQuery {
+    pub format: String,
+}
+
+/// Query parameters for transcript segments
+#[derive(serde::Deserialize)]
+pub struct SegmentsQuery {
+    /// Only return segments with a confidence below this value (0-1)
+    pub max_confidence: Option<f32>,
+    /// `segment` (default) or `word` for the word timings of transcripts that recorded them
+    pub granularity: Option<String>,
+}
+
+/// Query parameters for transcript diffs; paging comes from `PaginationQuery`
+#[derive(serde::Deserialize)]
+pub struct DiffQuery {
+    /// `word` (default) or `line`
+    pub granularity: Option<String>,
+}
+
+/// Query parameters for live transcription streams
+#[derive(serde::Deserialize)]
+pub struct StreamQuery {
+    /// Access token, since browsers can't send an Authorization header on a WebSocket
+    pub token: Option<String>,
+    pub language: Option<String>,
+}
+
+/// Query parameters for pagination, with optional transcript filters
 #[derive(serde::Deserialize)]
 pub struct PaginationQuery {
     pub page: Option<i64>,
     pub limit: Option<i64>,
+    /// Only transcripts created at or after this time (RFC 3339)
+    pub from: Option<DateTime<Utc>>,
+    /// Only transcripts created before this time (RFC 3339)
+    pub to: Option<DateTime<Utc>>,
+    /// Minimum audio duration in seconds
+    pub min_duration: Option<f64>,
+    /// Maximum audio duration in seconds
+    pub max_duration: Option<f64>,
+    /// Only transcripts carrying this tag
+    pub tag: Option<String>,
+    /// `created_at` (default), `duration`, `filename` or `word_count`
+    pub sort: Option<String>,
+    /// `asc` or `desc` (default)
+    pub order: Option<String>,
+}
+
+impl PaginationQuery {
+    /// Validate `page` and `limit` into `(page, limit)` under the configured page sizes.
+    /// A missing limit uses `DEFAULT_PAGE_SIZE` and a larger one is capped at `MAX_PAGE_SIZE`;
+    /// values below 1 are rejected rather than clamped.
+    pub fn page_and_limit(&self, config: &Config) -> AppResult<(i64, i64)> {
+        let page = self.page.unwrap_or(1);
+        if page < 1 {
+            return Err(AppError::ValidationError("page must be 1 or greater".to_string()));
+        }
+
+        let limit = self.limit.unwrap_or(config.default_page_size);
+        if limit < 1 {
+            return Err(AppError::ValidationError("limit must be 1 or greater".to_string()));
+        }
+
+        Ok((page, limit.min(config.max_page_size)))
+    }
+
+    /// Validate the filter parameters into a transcript filter
+    pub fn transcript_filter(&self) -> AppResult<TranscriptFilter> {
+        let sort = match self.sort.as_deref() {
+            None => TranscriptSort::default(),
+            Some(value) => TranscriptSort::parse(value).ok_or_else(|| {
+                AppError::ValidationError(
+                    "sort must be one of: created_at, duration, filename, word_count".to_string(),
+                )
+            })?,
+        };
+
+        let ascending = match self.order.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("desc") => false,
+            Some("asc") => true,
+            Some(_) => {
+                return Err(AppError::ValidationError(
+                    "order must be either asc or desc".to_string(),
+                ))
+            }
+        };
+
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from > to
+        {
+            return Err(AppError::ValidationError("from must be before to".to_string()));
+        }
+
+        if let (Some(min), Some(max)) = (self.min_duration, self.max_duration)
+            && min > max
+        {
+            return Err(AppError::ValidationError(
+                "min_duration must not exceed max_duration".to_string(),
+            ));
+        }
+
+        let tag = self.tag.as_deref().map(validation::validate_tag).transpose()?;
+
+        Ok(TranscriptFilter {
+            from: self.from,
+            to: self.to,
+            min_duration: self.min_duration,
+            max_duration: self.max_duration,
+            tag,
+            sort,
+            ascending,
+        })
+    }
 }
@@ -0,0 +1,296 @@
+use crate::controllers::StreamQuery;
+use crate::AppState;
+use crate::errors::{AppError, AppResult};
+use crate::middlewares::check_rate_limit;
+use crate::models::TranscriptSegmentResponse;
+use crate::services::{JobSlot, LiveBuffer, TranscriptionService, UserService, billing_period};
+use crate::utils::{jwt, validation};
+use actix_web::{HttpRequest, HttpResponse, http::header, web};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use serde_json::json;
+use uuid::Uuid;
+
+/// Subprotocol carrying the access token for clients that can't use the query string
+const BEARER_PROTOCOL: &str = "bearer";
+
+/// Largest accepted WebSocket frame (one second of audio is 32KB)
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// Live transcription over WebSocket
+pub struct StreamController;
+
+impl StreamController {
+    /// Upgrade to a WebSocket that accepts 16kHz mono 16-bit PCM frames and sends back
+    /// `partial` text and `final` segments as JSON messages
+    pub async fn transcribe_stream(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        body: web::Payload,
+        query: web::Query<StreamQuery>,
+    ) -> AppResult<HttpResponse> {
+        // Browsers can't set Authorization on a WebSocket, so the token comes from the
+        // query string or the `Sec-WebSocket-Protocol: bearer, <token>` header
+        let (token, via_protocol) = Self::stream_token(&req, &query)?;
+        let user_id = Self::authenticate(&app_state, &token).await?;
+        let language = validation::validate_language(query.language.as_deref())?;
+
+        // A stream counts against the same per-user job, upload rate and quota limits as an
+        // upload; the job slot is held until the stream ends
+        let slot = app_state
+            .job_slots
+            .try_acquire(user_id, app_state.config.max_concurrent_jobs_per_user)?;
+        check_rate_limit(
+            &app_state.upload_rate_limits,
+            &user_id.to_string(),
+            app_state.config.upload_rate_limit_per_minute,
+        )?;
+        let quota_seconds = Self::remaining_quota_seconds(&app_state, user_id).await?;
+
+        let (mut response, session, msg_stream) = actix_ws::handle(&req, body)
+            .map_err(|e| AppError::BadRequest(format!("WebSocket handshake failed: {}", e)))?;
+        if via_protocol {
+            response.headers_mut().insert(
+                header::SEC_WEBSOCKET_PROTOCOL,
+                header::HeaderValue::from_static(BEARER_PROTOCOL),
+            );
+        }
+
+        log::info!("Live transcription stream opened for user {}", user_id);
+
+        let span = tracing::Span::current();
+        actix_web::rt::spawn(tracing::Instrument::instrument(
+            Self::run_session(
+                app_state.into_inner(),
+                session,
+                msg_stream.max_frame_size(MAX_FRAME_SIZE),
+                language,
+                user_id,
+                slot,
+                quota_seconds,
+            ),
+            span,
+        ));
+
+        Ok(response)
+    }
+
+    /// Read audio until the client stops or disconnects, or the stream uses up the user's
+    /// `quota_seconds`. Whisper runs inline, so frames arriving meanwhile wait in the socket
+    /// buffers and a fast client is throttled. The audio received is charged to the user's
+    /// usage once the stream ends.
+    async fn run_session(
+        app_state: std::sync::Arc<AppState>,
+        mut session: Session,
+        mut msg_stream: MessageStream,
+        language: Option<&'static str>,
+        user_id: Uuid,
+        _slot: JobSlot,
+        quota_seconds: Option<f64>,
+    ) {
+        let mut buffer = LiveBuffer::new(
+            app_state.config.live_step_seconds,
+            app_state.config.live_window_seconds,
+        );
+
+        if session.text(json!({ "type": "ready" }).to_string()).await.is_err() {
+            return;
+        }
+
+        let close_reason = loop {
+            let message = match msg_stream.recv().await {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => {
+                    log::warn!("Live transcription stream error for user {}: {}", user_id, e);
+                    break Some(CloseReason::from(CloseCode::Protocol));
+                }
+                // Client went away without a close frame; nothing left to send to
+                None => break None,
+            };
+
+            match message {
+                Message::Binary(bytes) => {
+                    buffer.push_pcm(&bytes);
+                    if quota_seconds.is_some_and(|quota| buffer.received_seconds() >= quota) {
+                        // Transcribe what was received, then stop
+                        break match Self::finish(&app_state, &mut session, &mut buffer, language)
+                            .await
+                        {
+                            Some(CloseReason { code: CloseCode::Normal, .. }) => {
+                                Self::error_close(&mut session, AppError::QuotaExceeded(0.0)).await
+                            }
+                            close_reason => close_reason,
+                        };
+                    }
+                    if buffer.is_due() {
+                        let flush = buffer.is_full();
+                        let result =
+                            Self::transcribe(&app_state, &mut session, &mut buffer, language, flush)
+                                .await;
+                        if let Err(e) = result {
+                            break Self::error_close(&mut session, e).await;
+                        }
+                    }
+                }
+                Message::Text(text) => {
+                    let stop = serde_json::from_str::<serde_json::Value>(&text)
+                        .map(|command| command["type"] == "stop")
+                        .unwrap_or(false);
+                    if stop {
+                        break Self::finish(&app_state, &mut session, &mut buffer, language).await;
+                    }
+                }
+                Message::Ping(bytes) if session.pong(&bytes).await.is_err() => break None,
+                Message::Close(reason) => {
+                    log::debug!("Client closed live transcription stream: {:?}", reason);
+                    break Self::finish(&app_state, &mut session, &mut buffer, language).await;
+                }
+                _ => {}
+            }
+        };
+
+        log::info!("Live transcription stream closed for user {}", user_id);
+        if close_reason.is_some() {
+            session.close(close_reason).await.ok();
+        }
+
+        let received_seconds = buffer.received_seconds();
+        if received_seconds > 0.0
+            && let Err(e) =
+                TranscriptionService::record_live_usage(&app_state.db, user_id, received_seconds)
+                    .await
+        {
+            log::error!("Failed to record live transcription usage for user {}: {}", user_id, e);
+        }
+    }
+
+    /// Transcribe what is left in the buffer before closing normally
+    async fn finish(
+        app_state: &AppState,
+        session: &mut Session,
+        buffer: &mut LiveBuffer,
+        language: Option<&'static str>,
+    ) -> Option<CloseReason> {
+        if !buffer.is_empty()
+            && let Err(e) = Self::transcribe(app_state, session, buffer, language, true).await
+        {
+            return Self::error_close(session, e).await;
+        }
+        Some(CloseReason::from(CloseCode::Normal))
+    }
+
+    /// Run Whisper over the pending audio and send partial text or finalized segments
+    async fn transcribe(
+        app_state: &AppState,
+        session: &mut Session,
+        buffer: &mut LiveBuffer,
+        language: Option<&'static str>,
+        flush: bool,
+    ) -> AppResult<()> {
+        let (samples, offset_ms) = buffer.snapshot();
+        let segments = TranscriptionService::transcribe_samples(
+            app_state.whisper_models.get(None).await?.1,
+            samples,
+            offset_ms,
+            &app_state.config,
+            &app_state.whisper_states,
+            language,
+        )
+        .await?;
+
+        let message = if flush || buffer.is_full() {
+            let segments: Vec<TranscriptSegmentResponse> = buffer
+                .commit(segments, flush)
+                .into_iter()
+                .filter(|segment| !segment.text.is_empty())
+                .map(TranscriptSegmentResponse::from)
+                .collect();
+            json!({ "type": "final", "segments": segments })
+        } else {
+            let text = segments
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            json!({ "type": "partial", "start_ms": offset_ms, "text": text })
+        };
+
+        session
+            .text(message.to_string())
+            .await
+            .map_err(|_| AppError::BadRequest("Client disconnected".to_string()))
+    }
+
+    /// Report an error to the client and pick the close code
+    async fn error_close(session: &mut Session, error: AppError) -> Option<CloseReason> {
+        log::warn!("Live transcription failed: {}", error);
+        session
+            .text(json!({ "type": "error", "message": error.to_string() }).to_string())
+            .await
+            .ok();
+        Some(CloseReason::from(CloseCode::Error))
+    }
+
+    /// Helper function to find how many seconds of audio the user's monthly quota has left
+    /// (`None` without a quota), refusing the stream when there is nothing left
+    async fn remaining_quota_seconds(
+        app_state: &AppState,
+        user_id: Uuid,
+    ) -> AppResult<Option<f64>> {
+        let Some(quota_minutes) = app_state.config.monthly_minutes_quota else {
+            return Ok(None);
+        };
+
+        let (period_start, _) = billing_period(chrono::Utc::now());
+        let used_seconds =
+            TranscriptionService::get_usage_seconds(&app_state.db, user_id, period_start).await?;
+        let remaining_seconds = quota_minutes * 60.0 - used_seconds;
+        if remaining_seconds <= 0.0 {
+            return Err(AppError::QuotaExceeded(0.0));
+        }
+        Ok(Some(remaining_seconds))
+    }
+
+    /// Helper function to find the access token of a WebSocket request
+    fn stream_token(req: &HttpRequest, query: &StreamQuery) -> AppResult<(String, bool)> {
+        if let Some(token) = &query.token {
+            return Ok((token.clone(), false));
+        }
+
+        let protocols: Vec<&str> = req
+            .headers()
+            .get(header::SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+
+        match protocols.as_slice() {
+            [BEARER_PROTOCOL, token, ..] => Ok((token.to_string(), true)),
+            _ => Err(AppError::AuthError("Missing access token".to_string())),
+        }
+    }
+
+    /// Helper function to check an access token the way `JwtAuth` and
+    /// `RequireVerifiedEmail` do for regular requests
+    async fn authenticate(app_state: &AppState, token: &str) -> AppResult<Uuid> {
+        let claims = jwt::verify_token(token, &app_state.jwt_keys)?;
+        if claims.token_type != "access" {
+            return Err(AppError::AuthError("Invalid token type".to_string()));
+        }
+
+        let user_id: Uuid = claims
+            .sub
+            .parse()
+            .map_err(|_| AppError::AuthError("Invalid user ID in token".to_string()))?;
+
+        if !app_state.account_status.is_active(&app_state.db, user_id).await? {
+            return Err(AppError::Forbidden);
+        }
+
+        if !UserService::is_email_verified(&app_state.db, user_id).await? {
+            return Err(AppError::Forbidden);
+        }
+
+        Ok(user_id)
+    }
+}
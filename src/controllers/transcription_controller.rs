@@ -1,183 +1,705 @@
-use crate::controllers::PaginationQuery;
+use crate::config::Config;
+use crate::controllers::{DiffQuery, ExportQuery, PaginationQuery, SegmentsQuery};
 use crate::AppState;
 use crate::errors::{AppError, AppResult};
-use crate::middlewares::extract_user_id;
+use crate::middlewares::{check_rate_limit, client_ip, extract_user_id};
 use crate::models::*;
-use crate::services::TranscriptionService;
-use crate::utils::file;
-use actix_multipart::Multipart;
-use actix_web::{HttpRequest, HttpResponse, web};
-use futures_util::TryStreamExt;
+use crate::services::{
+    IdempotencyClaim, IdempotencyService, JobService, JobSlot, ProgressEvent, QueuePermit,
+    RESTORE_WINDOW_DAYS, SHARE_FORMATS, ShareService, TranscriptionService, UploadService,
+    billing_period, diff, export,
+};
+use crate::utils::{file, validation};
+use actix_multipart::{Field, Multipart, MultipartError};
+use actix_web::{
+    HttpRequest, HttpResponse, ResponseError, error::PayloadError, http::header, web, web::Bytes,
+};
+use futures_util::{StreamExt, TryStreamExt, stream};
+use serde::{Deserialize, Deserializer};
 use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
 use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::broadcast::error::RecvError;
 use uuid::Uuid;
 
+/// Allowance on top of the file size limit for multipart boundaries, part headers and text fields
+const MULTIPART_OVERHEAD_BYTES: usize = 1024 * 1024;
+
+/// Largest accepted non-file form field
+const MAX_FORM_FIELD_BYTES: usize = 64 * 1024;
+
+/// Longest User-Agent recorded with an upload
+const MAX_USER_AGENT_CHARS: usize = 512;
+
+/// `response_format` values accepted by the OpenAI-compatible endpoint
+const OPENAI_RESPONSE_FORMATS: &[&str] = &["json", "text", "srt", "vtt", "verbose_json"];
+
+/// Header clients send to make upload retries safe
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Response header marking a replayed idempotent response
+const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
 /// Transcription controller
 pub struct TranscriptionController;
 
+/// Transcription options validated from the non-file upload form fields
+struct UploadOptions {
+    language: Option<String>,
+    diarize: bool,
+    trim_silence: bool,
+    initial_prompt: Option<String>,
+    text_format: &'static str,
+    word_timestamps: bool,
+    model: String,
+    capitalize_sentences: bool,
+    numbers_to_digits: bool,
+    temperature: f32,
+    temperature_inc: f32,
+    audio_filters: Vec<String>,
+}
+
+impl UploadOptions {
+    /// Hash identifying an upload request by its file content and options
+    fn fingerprint(&self, content_sha256: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(content_sha256.as_bytes());
+        hasher.update(format!(
+            "\n{:?}\n{}\n{}\n{:?}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{:?}",
+            self.language,
+            self.diarize,
+            self.trim_silence,
+            self.initial_prompt,
+            self.text_format,
+            self.word_timestamps,
+            self.model,
+            self.capitalize_sentences,
+            self.numbers_to_digits,
+            self.temperature,
+            self.temperature_inc,
+            self.audio_filters
+        ));
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Raw non-file upload form fields, collected in whatever order the client sends them
+/// Finalizing a resumable upload takes the same fields as a JSON object
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct UploadFormFields {
+    language: Option<String>,
+    diarize: bool,
+    trim_silence: Option<bool>,
+    prompt: Option<String>,
+    format: Option<String>,
+    word_timestamps: bool,
+    model: Option<String>,
+    capitalize_sentences: Option<bool>,
+    numbers_to_digits: Option<bool>,
+    #[serde(deserialize_with = "number_as_string")]
+    temperature: Option<String>,
+    #[serde(deserialize_with = "number_as_string")]
+    temperature_inc: Option<String>,
+    audio_filters: Option<String>,
+}
+
+/// Read a numeric field sent as a JSON number or as a string, the way multipart forms send it
+fn number_as_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(Option::<serde_json::Value>::deserialize(deserializer)?.map(|value| match value {
+        serde_json::Value::String(value) => value,
+        value => value.to_string(),
+    }))
+}
+
+impl UploadFormFields {
+    /// Record a text field; unknown fields are ignored
+    async fn read_field(&mut self, name: &str, field: &mut Field) -> AppResult<()> {
+        match name {
+            "language" => {
+                self.language = Some(TranscriptionController::read_text_field(field).await?);
+            }
+            "diarize" => {
+                let value = TranscriptionController::read_text_field(field).await?;
+                self.diarize = matches!(value.to_lowercase().as_str(), "true" | "1" | "yes");
+            }
+            "prompt" => {
+                self.prompt = Some(TranscriptionController::read_text_field(field).await?);
+            }
+            "trim_silence" => {
+                let value = TranscriptionController::read_text_field(field).await?;
+                self.trim_silence =
+                    Some(matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"));
+            }
+            "format" => {
+                self.format = Some(TranscriptionController::read_text_field(field).await?);
+            }
+            "word_timestamps" => {
+                let value = TranscriptionController::read_text_field(field).await?;
+                self.word_timestamps = matches!(value.to_lowercase().as_str(), "true" | "1" | "yes");
+            }
+            "model" => {
+                self.model = Some(TranscriptionController::read_text_field(field).await?);
+            }
+            "capitalize_sentences" => {
+                let value = TranscriptionController::read_text_field(field).await?;
+                self.capitalize_sentences =
+                    Some(matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"));
+            }
+            "numbers_to_digits" => {
+                let value = TranscriptionController::read_text_field(field).await?;
+                self.numbers_to_digits =
+                    Some(matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"));
+            }
+            "temperature" => {
+                self.temperature = Some(TranscriptionController::read_text_field(field).await?);
+            }
+            "temperature_inc" => {
+                self.temperature_inc = Some(TranscriptionController::read_text_field(field).await?);
+            }
+            "audio_filters" => {
+                self.audio_filters = Some(TranscriptionController::read_text_field(field).await?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Reject unknown languages, formats, models and audio filters, oversized prompts and
+    /// out-of-range temperatures
+    fn to_options(&self, config: &Config) -> AppResult<UploadOptions> {
+        let language = validation::validate_language(self.language.as_deref())?;
+        let initial_prompt = validation::validate_initial_prompt(self.prompt.as_deref())?;
+        let text_format = validation::validate_text_format(
+            self.format.as_deref().unwrap_or(&config.text_format),
+        )?;
+        let model = match self.model.as_deref().map(str::trim) {
+            None | Some("") => config.whisper_default_model.clone(),
+            Some(model) if config.whisper_models.contains_key(model) => model.to_string(),
+            Some(model) => {
+                return Err(AppError::BadRequest(format!(
+                    "Unknown model {}; available models: {}",
+                    model,
+                    config.whisper_models.keys().cloned().collect::<Vec<_>>().join(", ")
+                )));
+            }
+        };
+        let temperature = match self.temperature.as_deref() {
+            Some(value) => validation::validate_temperature("temperature", value)?,
+            None => config.whisper_temperature,
+        };
+        let temperature_inc = match self.temperature_inc.as_deref() {
+            Some(value) => validation::validate_temperature("temperature_inc", value)?,
+            None => config.whisper_temperature_inc,
+        };
+        let audio_filters = match self.audio_filters.as_deref() {
+            Some(filters) => validation::validate_audio_filters(filters)?,
+            None => config.audio_filters.clone(),
+        };
+        Ok(UploadOptions {
+            language: language.map(|code| code.to_string()),
+            diarize: self.diarize,
+            trim_silence: self.trim_silence.unwrap_or(config.trim_silence),
+            initial_prompt,
+            text_format,
+            word_timestamps: self.word_timestamps,
+            model,
+            capitalize_sentences: self.capitalize_sentences.unwrap_or(config.capitalize_sentences),
+            numbers_to_digits: self.numbers_to_digits.unwrap_or(config.numbers_to_digits),
+            temperature,
+            temperature_inc,
+            audio_filters,
+        })
+    }
+}
+
 impl TranscriptionController {
-    /// Upload and transcribe audio file with enhanced processing
+    /// Upload an audio file and queue it for background transcription
     pub async fn upload_and_transcribe(
         app_state: web::Data<AppState>,
         req: HttpRequest,
-        mut payload: Multipart,
+        payload: web::Payload,
     ) -> AppResult<HttpResponse> {
         let start_time = Instant::now();
         let user_id = extract_user_id(&req)?;
 
         log::info!("Starting transcription request for user: {}", user_id);
 
-        // Process multipart form data
+        let mut payload = Self::limited_multipart(&req, payload, app_state.config.max_file_size)?;
+        let idempotency_key = Self::idempotency_key(&req)?;
+
+        // Process multipart form data, streaming the audio straight to disk
         let mut file_upload: Option<FileUpload> = None;
+        let mut fields = UploadFormFields::default();
 
-        while let Some(mut field) = payload
-            .try_next()
-            .await
-            .map_err(|e| AppError::BadRequest(format!("Failed to read multipart data: {}", e)))?
-        {
-            let content_disposition = field.content_disposition();
-
-            if let Some(name) = content_disposition.get_name() {
-                if name == "audio_file" {
-                    // Get filename
-                    let filename = content_disposition
-                        .get_filename()
-                        .ok_or_else(|| AppError::BadRequest("Filename is required".to_string()))?
-                        .to_string();
-
-                    log::info!("Processing uploaded file: {}", filename);
-
-                    // Validate file format (now supports more formats thanks to FFmpeg)
-                    if !Self::is_supported_audio_format(&filename) {
-                        return Err(AppError::ValidationError(
-                            "Unsupported audio format. Supported formats: wav, mp3, m4a, flac, ogg, aac, wma, aiff, au"
-                                .to_string(),
-                        ));
-                    }
+        let form_result: AppResult<()> = async {
+            while let Some(mut field) = payload
+                .try_next()
+                .await
+                .map_err(|e| Self::multipart_error("Failed to read multipart data", e))?
+            {
+                let field_name = field
+                    .content_disposition()
+                    .get_name()
+                    .unwrap_or_default()
+                    .to_string();
 
-                    // Read file data
-                    let mut file_data = Vec::new();
-                    while let Some(chunk) = field.try_next().await.map_err(|e| {
-                        AppError::BadRequest(format!("Failed to read audio file chunk: {}", e))
-                    })? {
-                        file_data.extend_from_slice(&chunk);
+                match field_name.as_str() {
+                    "audio_file" if file_upload.is_none() => {
+                        // Get filename
+                        let filename = field
+                            .content_disposition()
+                            .get_filename()
+                            .ok_or_else(|| AppError::BadRequest("Filename is required".to_string()))?
+                            .to_string();
+
+                        file_upload = Some(
+                            Self::receive_audio_file(
+                                &mut field,
+                                &filename,
+                                &app_state.config,
+                                app_state.config.max_file_size,
+                            )
+                            .await?,
+                        );
                     }
+                    name => fields.read_field(name, &mut field).await?,
+                }
+            }
 
-                    // Validate file size
-                    file::validate_file_size(file_data.len(), app_state.config.max_file_size)?;
+            Ok(())
+        }
+        .await;
 
-                    log::info!("File uploaded successfully: {} bytes", file_data.len());
+        // Reject unknown languages and oversized prompts before doing any audio work
+        let validated = form_result.and_then(|_| {
+            let options = fields.to_options(&app_state.config)?;
+            let file_upload = file_upload
+                .clone()
+                .ok_or_else(|| AppError::BadRequest("No audio file provided".to_string()))?;
+            Ok((file_upload, options))
+        });
 
-                    // Get content type
-                    let content_type = field
-                        .content_type()
-                        .map(|ct| ct.to_string())
-                        .unwrap_or_else(|| Self::guess_content_type(&filename));
+        let (file_upload, options) = match validated {
+            Ok(validated) => validated,
+            Err(e) => {
+                // Don't leave a partially processed upload behind
+                if let Some(upload) = &file_upload {
+                    tokio::fs::remove_file(&upload.path).await.ok();
+                }
+                return Err(e);
+            }
+        };
 
-                    file_upload = Some(FileUpload {
-                        filename: file::generate_unique_filename(&filename),
-                        content_type,
-                        size: file_data.len(),
-                        data: file_data,
-                    });
-                    break;
+        // A retry carrying the same Idempotency-Key gets the original response back
+        if let Some(key) = &idempotency_key {
+            let request_hash = options.fingerprint(&file_upload.sha256);
+            match IdempotencyService::claim(
+                &app_state.db,
+                user_id,
+                key,
+                &request_hash,
+                app_state.config.idempotency_key_ttl_hours,
+            )
+            .await
+            {
+                Ok(IdempotencyClaim::Acquired) => {}
+                Ok(IdempotencyClaim::Replay(response)) => {
+                    tokio::fs::remove_file(&file_upload.path).await.ok();
+                    log::info!("Replaying upload response for idempotency key {}", key);
+                    return Ok(HttpResponse::Accepted()
+                        .insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"))
+                        .json(response));
+                }
+                Err(e) => {
+                    tokio::fs::remove_file(&file_upload.path).await.ok();
+                    return Err(e);
                 }
             }
         }
 
-        let file_upload = file_upload
-            .ok_or_else(|| AppError::BadRequest("No audio file provided".to_string()))?;
+        // Replays above don't count against the user's upload limits
+        let queued = match Self::acquire_upload_slot(&app_state, user_id) {
+            Ok((slot, permit)) => {
+                Self::queue_upload(
+                    &app_state,
+                    &req,
+                    user_id,
+                    &file_upload,
+                    &options,
+                    Some(slot),
+                    permit,
+                )
+                .await
+            }
+            Err(e) => {
+                tokio::fs::remove_file(&file_upload.path).await.ok();
+                Err(e)
+            }
+        };
+
+        let (transcript, duration_seconds) = match queued {
+            Ok(queued) => queued,
+            Err(e) => {
+                // Let the client retry the failed request with the same key
+                if let Some(key) = &idempotency_key
+                    && let Err(release_err) =
+                        IdempotencyService::release(&app_state.db, user_id, key).await
+                {
+                    log::warn!("Failed to release idempotency key {}: {}", key, release_err);
+                }
+                return Err(e);
+            }
+        };
+        let transcript_id = transcript.id;
 
-        let original_filename = file_upload.filename.clone();
-        
         log::info!(
-            "Processing transcription for file: {} (size: {} bytes)",
-            file_upload.filename,
-            file_upload.size
+            "Transcription job {} queued in {:.2}s for file: {}",
+            transcript.id,
+            start_time.elapsed().as_secs_f64(),
+            file_upload.filename
         );
 
-        // Create temporary file path for duration calculation
-        let temp_file_path = format!("{}/{}", app_state.config.temp_dir, file_upload.filename);
-        
-        // Write file temporarily to get duration
-        tokio::fs::write(&temp_file_path, &file_upload.data).await
-            .map_err(|e| AppError::FileError(format!("Failed to write temporary file: {}", e)))?;
+        let response =
+            Self::queued_response(&app_state, transcript, duration_seconds, file_upload.size);
 
-        // Get audio duration before transcription
-        let duration_seconds = match TranscriptionService::get_audio_duration(&temp_file_path).await {
-            Ok(duration) => {
-                log::info!("Audio duration: {:.2} seconds", duration);
-                Some(duration)
+        if let Some(key) = &idempotency_key
+            && let Err(e) =
+                IdempotencyService::complete(&app_state.db, user_id, key, transcript_id, &response)
+                    .await
+        {
+            log::warn!("Failed to record response for idempotency key {}: {}", key, e);
+        }
+
+        Ok(HttpResponse::Accepted().json(response))
+    }
+
+    /// Upload several audio files in one request and queue a transcription job for each.
+    /// A bad file is reported in its own result instead of failing the whole batch.
+    pub async fn upload_batch(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        payload: web::Payload,
+    ) -> AppResult<HttpResponse> {
+        let start_time = Instant::now();
+        let user_id = extract_user_id(&req)?;
+        let max_files = app_state.config.max_batch_files;
+        let max_batch_size = app_state.config.max_batch_size;
+
+        log::info!("Starting batch transcription request for user: {}", user_id);
+
+        let mut payload = Self::limited_multipart(&req, payload, max_batch_size)?;
+
+        // Each audio part is either received onto disk or rejected with its own error
+        let mut uploads: Vec<(String, AppResult<FileUpload>)> = Vec::new();
+        let mut fields = UploadFormFields::default();
+        let mut batch_size = 0usize;
+
+        let form_result: AppResult<()> = async {
+            while let Some(mut field) = payload
+                .try_next()
+                .await
+                .map_err(|e| Self::multipart_error("Failed to read multipart data", e))?
+            {
+                let field_name = field
+                    .content_disposition()
+                    .get_name()
+                    .unwrap_or_default()
+                    .to_string();
+
+                match field_name.as_str() {
+                    "audio_file" => {
+                        let filename = field
+                            .content_disposition()
+                            .get_filename()
+                            .unwrap_or_default()
+                            .to_string();
+
+                        if uploads.len() >= max_files {
+                            return Err(AppError::ValidationError(format!(
+                                "A batch may contain at most {} files",
+                                max_files
+                            )));
+                        }
+
+                        let remaining = max_batch_size.saturating_sub(batch_size);
+                        let received = if filename.is_empty() {
+                            Err(AppError::BadRequest("Filename is required".to_string()))
+                        } else if remaining == 0 {
+                            Err(AppError::PayloadTooLarge(format!(
+                                "Batch exceeds maximum total size of {} bytes",
+                                max_batch_size
+                            )))
+                        } else {
+                            Self::receive_audio_file(
+                                &mut field,
+                                &filename,
+                                &app_state.config,
+                                app_state.config.max_file_size.min(remaining),
+                            )
+                            .await
+                        };
+
+                        if let Ok(upload) = &received {
+                            batch_size += upload.size;
+                        }
+                        uploads.push((filename, received));
+                    }
+                    name => fields.read_field(name, &mut field).await?,
+                }
+            }
+
+            Ok(())
+        }
+        .await;
+
+        // Options apply to every file, so a bad option still fails the whole batch
+        let validated = form_result.and_then(|_| {
+            if uploads.is_empty() {
+                return Err(AppError::BadRequest("No audio file provided".to_string()));
             }
+            fields.to_options(&app_state.config)
+        });
+
+        let options = match validated {
+            Ok(options) => options,
             Err(e) => {
-                log::warn!("Failed to get audio duration: {}", e);
-                None
+                for (_, received) in &uploads {
+                    if let Ok(upload) = received {
+                        tokio::fs::remove_file(&upload.path).await.ok();
+                    }
+                }
+                return Err(e);
             }
         };
 
-        // Remove temporary file (transcription service will create its own)
-        tokio::fs::remove_file(&temp_file_path).await.ok();
+        let mut results = Vec::with_capacity(uploads.len());
+        for (filename, received) in uploads {
+            let queued = match received {
+                // Each file counts against the same per-user job and rate limits as a
+                // single upload
+                Ok(upload) => match Self::acquire_upload_slot(&app_state, user_id) {
+                    Ok((slot, permit)) => Self::queue_upload(
+                        &app_state,
+                        &req,
+                        user_id,
+                        &upload,
+                        &options,
+                        Some(slot),
+                        permit,
+                    )
+                    .await
+                    .map(|(transcript, _)| transcript),
+                    Err(e) => {
+                        tokio::fs::remove_file(&upload.path).await.ok();
+                        Err(e)
+                    }
+                },
+                Err(e) => Err(e),
+            };
 
-        // Transcribe audio using the enhanced service
-        log::info!("Starting transcription for file: {}", file_upload.filename);
-        
-        let transcription_start = Instant::now();
-        let transcription = TranscriptionService::transcribe_audio(
-            app_state.whisper_ctx.clone(),
-            file_upload.clone(),
-            &app_state.config.temp_dir,
-        )
-        .await
-        .map_err(|e| {
-            log::error!("Transcription failed for file {}: {}", file_upload.filename, e);
-            e
-        })?;
+            results.push(match queued {
+                Ok(transcript) => json!({
+                    "filename": filename,
+                    "transcript_id": transcript.id,
+                    "status": transcript.status,
+                }),
+                Err(e) => {
+                    log::warn!("Batch upload of {} failed: {}", filename, e);
+                    json!({
+                        "filename": filename,
+                        "status": "rejected",
+                        "status_code": e.error_response().status().as_u16(),
+                        "error": e.to_string(),
+                    })
+                }
+            });
+        }
 
-        let transcription_duration = transcription_start.elapsed();
-        
         log::info!(
-            "Transcription completed in {:.2}s - Result length: {} characters",
-            transcription_duration.as_secs_f64(),
-            transcription.len()
+            "Batch of {} files processed in {:.2}s for user: {}",
+            results.len(),
+            start_time.elapsed().as_secs_f64(),
+            user_id
         );
 
-        // Log transcription preview for debugging
-        if !transcription.is_empty() {
-            let preview = transcription.chars().take(100).collect::<String>();
-            log::info!("Transcription preview: {}", preview);
-        } else {
-            log::warn!("Empty transcription result for file: {}", file_upload.filename);
+        Ok(HttpResponse::Accepted().json(json!({
+            "results": results,
+            "queue": JobService::queue_status(&app_state, None),
+        })))
+    }
+
+    /// OpenAI-compatible `POST /v1/audio/transcriptions`. The upload is queued like any other
+    /// and the response is sent once the job finishes, in the requested `response_format`.
+    /// A `model` named in `WHISPER_MODELS` selects that model; unknown names such as
+    /// `whisper-1` fall back to the default model.
+    pub async fn openai_transcription(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        payload: web::Payload,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+
+        let mut payload = Self::limited_multipart(&req, payload, app_state.config.max_file_size)?;
+
+        let mut file_upload: Option<FileUpload> = None;
+        let mut fields = UploadFormFields::default();
+        let mut response_format: Option<String> = None;
+
+        let form_result: AppResult<()> = async {
+            while let Some(mut field) = payload
+                .try_next()
+                .await
+                .map_err(|e| Self::multipart_error("Failed to read multipart data", e))?
+            {
+                let field_name = field
+                    .content_disposition()
+                    .get_name()
+                    .unwrap_or_default()
+                    .to_string();
+
+                match field_name.as_str() {
+                    "file" if file_upload.is_none() => {
+                        let filename = field
+                            .content_disposition()
+                            .get_filename()
+                            .ok_or_else(|| AppError::BadRequest("Filename is required".to_string()))?
+                            .to_string();
+
+                        file_upload = Some(
+                            Self::receive_audio_file(
+                                &mut field,
+                                &filename,
+                                &app_state.config,
+                                app_state.config.max_file_size,
+                            )
+                            .await?,
+                        );
+                    }
+                    "response_format" => {
+                        response_format = Some(Self::read_text_field(&mut field).await?);
+                    }
+                    // OpenAI model names like `whisper-1` fall back to the default model
+                    "model" => {
+                        let model = Self::read_text_field(&mut field).await?;
+                        if app_state.config.whisper_models.contains_key(model.trim()) {
+                            fields.model = Some(model);
+                        }
+                    }
+                    // `language`, `prompt` and `temperature` work as on our own upload endpoint
+                    name => fields.read_field(name, &mut field).await?,
+                }
+            }
+
+            Ok(())
         }
+        .await;
 
-        // Save transcription to database
-        let transcript = TranscriptionService::save_transcription(
-            &app_state.db,
+        let validated = form_result.and_then(|_| {
+            let response_format = Self::openai_response_format(response_format.as_deref())?;
+            let options = fields.to_options(&app_state.config)?;
+            let file_upload = file_upload
+                .clone()
+                .ok_or_else(|| AppError::BadRequest("No audio file provided".to_string()))?;
+            Ok((file_upload, options, response_format))
+        });
+
+        let (file_upload, options, response_format) = match validated {
+            Ok(validated) => validated,
+            Err(e) => {
+                if let Some(upload) = &file_upload {
+                    tokio::fs::remove_file(&upload.path).await.ok();
+                }
+                return Err(e);
+            }
+        };
+
+        let (slot, permit) = match Self::acquire_upload_slot(&app_state, user_id) {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                tokio::fs::remove_file(&file_upload.path).await.ok();
+                return Err(e);
+            }
+        };
+        let (transcript, _) = Self::queue_upload(
+            &app_state,
+            &req,
             user_id,
-            &original_filename, // Use original filename for display
-            &transcription,
-            file_upload.size as i64,
-            duration_seconds,
+            &file_upload,
+            &options,
+            Some(slot),
+            permit,
         )
         .await?;
 
-        let total_duration = start_time.elapsed();
+        // A client that disconnects only stops waiting; the job still completes in the background
+        if let Some(mut receiver) = JobService::subscribe(&app_state, transcript.id) {
+            loop {
+                match receiver.recv().await {
+                    Ok(ProgressEvent::Done(_)) | Err(RecvError::Closed) => break,
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                }
+            }
+        }
+
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, transcript.id, user_id).await?;
+        match transcript.status.as_str() {
+            transcript_status::COMPLETED => {}
+            transcript_status::FAILED => {
+                return Err(AppError::WhisperError(
+                    transcript
+                        .error_message
+                        .unwrap_or_else(|| "Transcription failed".to_string()),
+                ));
+            }
+            status => {
+                return Err(AppError::InternalError(format!(
+                    "Transcription {} did not finish (status is '{}')",
+                    transcript.id, status
+                )));
+            }
+        }
+
         log::info!(
-            "Complete transcription workflow finished in {:.2}s for file: {}",
-            total_duration.as_secs_f64(),
-            original_filename
+            "OpenAI-compatible transcription {} answered as {}",
+            transcript.id,
+            response_format
         );
 
-        // Return enhanced response with processing metadata
-        let response = json!({
-            "transcript": TranscriptResponse::from(transcript),
-            "processing_time_seconds": total_duration.as_secs_f64(),
-            "transcription_time_seconds": transcription_duration.as_secs_f64(),
-            "audio_duration_seconds": duration_seconds,
-            "file_size_bytes": file_upload.size,
-            "status": "completed"
-        });
+        match response_format {
+            "json" => {
+                return Ok(HttpResponse::Ok().json(json!({ "text": transcript.transcription })));
+            }
+            "text" => {
+                return Ok(HttpResponse::Ok()
+                    .content_type("text/plain; charset=utf-8")
+                    .body(transcript.transcription));
+            }
+            _ => {}
+        }
+
+        let segments = TranscriptionService::get_transcript_segments(
+            &app_state.db,
+            transcript.id,
+            user_id,
+            None,
+        )
+        .await?;
 
-        Ok(HttpResponse::Created().json(response))
+        Ok(match response_format {
+            "srt" => HttpResponse::Ok()
+                .content_type("text/plain; charset=utf-8")
+                .body(export::to_srt(&segments)),
+            "vtt" => HttpResponse::Ok()
+                .content_type("text/plain; charset=utf-8")
+                .body(export::to_vtt(&segments)),
+            _ => HttpResponse::Ok().json(export::to_openai_verbose_json(&transcript, &segments)),
+        })
     }
 
     // /// Alternative endpoint for direct file transcription (useful for testing)
@@ -226,6 +748,155 @@ impl TranscriptionController {
     //     Ok(HttpResponse::Ok().json(TranscriptResponse::from(transcript)))
     // }
 
+    /// Start a resumable upload; the file is then sent in parts and finalized
+    pub async fn initiate_upload(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        request: web::Json<InitiateUploadRequest>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        validation::validate_request(&*request)?;
+
+        let filename = file::sanitize_filename(&request.filename)?;
+        if !file::is_supported_audio_format(&filename, &app_state.config.supported_formats) {
+            return Err(AppError::ValidationError(format!(
+                "Unsupported audio format. Supported formats: {}",
+                app_state.config.supported_formats.join(", ")
+            )));
+        }
+        file::validate_file_size(request.size as usize, app_state.config.max_file_size)?;
+
+        // Parts are written into place, so the file exists from the start
+        let path = format!(
+            "{}/{}",
+            app_state.config.temp_dir,
+            file::generate_unique_filename(&filename)
+        );
+        tokio::fs::File::create(&path)
+            .await
+            .map_err(|e| AppError::FileError(format!("Failed to create temporary file: {}", e)))?;
+
+        let upload =
+            match UploadService::create(&app_state.db, user_id, &filename, &path, request.size).await {
+                Ok(upload) => upload,
+                Err(e) => {
+                    tokio::fs::remove_file(&path).await.ok();
+                    return Err(e);
+                }
+            };
+
+        Ok(HttpResponse::Created()
+            .insert_header((header::LOCATION, format!("/api/v1/uploads/{}", upload.id)))
+            .json(Self::upload_status(&upload, &app_state.config)))
+    }
+
+    /// Report how much of a resumable upload was received, so a client knows where to resume
+    pub async fn get_upload(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let upload = UploadService::get(&app_state.db, path.into_inner(), user_id).await?;
+
+        Ok(HttpResponse::Ok().json(Self::upload_status(&upload, &app_state.config)))
+    }
+
+    /// Append the byte range in `Content-Range` to a resumable upload
+    /// Ranges must continue exactly where the received data ends. If the connection drops
+    /// mid-part, the bytes that did arrive are kept and the client resumes after them.
+    pub async fn append_upload(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        payload: web::Payload,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let upload_id = path.into_inner();
+        let (start, end, total) = Self::content_range(&req)?;
+
+        let _lock = app_state.upload_locks.try_lock(upload_id)?;
+        let upload = UploadService::get(&app_state.db, upload_id, user_id).await?;
+        let received_bytes =
+            Self::write_range(&app_state.db, &upload, (start, end, total), payload).await?;
+
+        let upload = Upload {
+            received_bytes,
+            ..upload
+        };
+        Ok(HttpResponse::Ok().json(Self::upload_status(&upload, &app_state.config)))
+    }
+
+    /// Queue the transcription of a completely received resumable upload
+    /// The optional JSON body takes the same options as the multipart upload's form fields.
+    pub async fn finalize_upload(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        body: Bytes,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let upload_id = path.into_inner();
+
+        let _lock = app_state.upload_locks.try_lock(upload_id)?;
+        let upload = UploadService::get(&app_state.db, upload_id, user_id).await?;
+        if upload.received_bytes != upload.total_size {
+            return Err(AppError::Conflict(format!(
+                "Upload is incomplete: received {} of {} bytes",
+                upload.received_bytes, upload.total_size
+            )));
+        }
+
+        let fields: UploadFormFields = if body.is_empty() {
+            UploadFormFields::default()
+        } else {
+            serde_json::from_slice(&body)
+                .map_err(|e| AppError::BadRequest(format!("Invalid upload options: {}", e)))?
+        };
+        let options = fields.to_options(&app_state.config)?;
+
+        let (format, sha256) = Self::inspect_upload(&upload.path).await?;
+        let Some(format) = format else {
+            UploadService::delete(&app_state.db, upload_id).await?;
+            tokio::fs::remove_file(&upload.path).await.ok();
+            return Err(AppError::ValidationError(
+                "Uploaded file does not look like a supported audio file".to_string(),
+            ));
+        };
+        let file_upload = FileUpload {
+            filename: upload.filename.clone(),
+            content_type: format.content_type().to_string(),
+            size: upload.total_size as usize,
+            path: upload.path.clone(),
+            sha256,
+        };
+
+        // Over the limits, the upload is kept so finalizing can be retried later
+        let (slot, permit) = Self::acquire_upload_slot(&app_state, user_id)?;
+
+        // From here the file belongs to the job (or is removed if the job is rejected)
+        UploadService::delete(&app_state.db, upload_id).await?;
+        let (transcript, duration_seconds) = Self::queue_upload(
+            &app_state,
+            &req,
+            user_id,
+            &file_upload,
+            &options,
+            Some(slot),
+            permit,
+        )
+        .await?;
+
+        log::info!("Resumable upload {} queued as job {}", upload_id, transcript.id);
+
+        Ok(HttpResponse::Accepted().json(Self::queued_response(
+            &app_state,
+            transcript,
+            duration_seconds,
+            file_upload.size,
+        )))
+    }
+
     /// Get user's transcripts with enhanced pagination and filtering
     pub async fn get_transcripts(
         app_state: web::Data<AppState>,
@@ -234,26 +905,35 @@ impl TranscriptionController {
     ) -> AppResult<HttpResponse> {
         let user_id = extract_user_id(&req)?;
 
-        let page = query.page.unwrap_or(1).max(1);
-        let limit = query.limit.unwrap_or(10).min(100).max(1); // Max 100, min 1
-
-        log::debug!("Fetching transcripts for user {} - page: {}, limit: {}", user_id, page, limit);
+        let (page, limit) = query.page_and_limit(&app_state.config)?;
+        let filter = query.transcript_filter()?;
 
-        let (transcripts, total) =
-            TranscriptionService::get_user_transcripts(&app_state.db, user_id, page, limit).await?;
+        log::debug!(
+            "Fetching transcripts for user {} - page: {}, limit: {}, filter: {:?}",
+            user_id,
+            page,
+            limit,
+            filter
+        );
 
-        let total_pages = (total + limit - 1) / limit; // Ceiling division
+        let (transcripts, total) = TranscriptionService::get_user_transcripts(
+            &app_state.db,
+            user_id,
+            page,
+            limit,
+            &filter,
+        )
+        .await?;
 
-        let response = PaginatedResponse {
-            data: transcripts
+        let response = PaginatedResponse::new(
+            transcripts
                 .into_iter()
-                .map(TranscriptResponse::from)
+                .map(|transcript| TranscriptResponse::new(transcript, app_state.config.reading_wpm))
                 .collect(),
             page,
             limit,
             total,
-            total_pages,
-        };
+        );
 
         log::debug!("Returning {} transcripts (total: {})", response.data.len(), total);
 
@@ -272,48 +952,726 @@ impl TranscriptionController {
         log::debug!("Fetching transcript {} for user {}", transcript_id, user_id);
 
         let transcript =
-            TranscriptionService::get_transcript_by_id(&app_state.db, transcript_id, user_id)
-                .await?;
+            TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
 
-        Ok(HttpResponse::Ok().json(TranscriptResponse::from(transcript)))
+        Ok(HttpResponse::Ok().json(TranscriptResponse::new(transcript, app_state.config.reading_wpm)))
     }
 
-    /// Delete transcript by ID with confirmation
-    pub async fn delete_transcript(
+    /// Diff a transcript's current text against the original Whisper output
+    /// The runs of unchanged, inserted and deleted text are paginated with `page`/`limit`.
+    pub async fn get_transcript_diff(
         app_state: web::Data<AppState>,
         req: HttpRequest,
         path: web::Path<Uuid>,
+        pagination: web::Query<PaginationQuery>,
+        query: web::Query<DiffQuery>,
     ) -> AppResult<HttpResponse> {
         let user_id = extract_user_id(&req)?;
         let transcript_id = path.into_inner();
 
-        log::info!("Deleting transcript {} for user {}", transcript_id, user_id);
+        let granularity = match query.granularity.as_deref() {
+            None => diff::DiffGranularity::Word,
+            Some(value) => diff::DiffGranularity::parse(value).ok_or_else(|| {
+                AppError::BadRequest(format!(
+                    "Unsupported granularity '{}'. Supported values: word, line",
+                    value
+                ))
+            })?,
+        };
+        let (page, limit) = pagination.page_and_limit(&app_state.config)?;
 
-        TranscriptionService::delete_transcript(&app_state.db, transcript_id, user_id).await?;
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+        let original = transcript.original_transcription.ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Only completed transcripts have an original to compare with (status is '{}')",
+                transcript.status
+            ))
+        })?;
+        let current = transcript.transcription;
+
+        // Large transcripts take a while to diff, keep it off the async workers
+        let result = web::block(move || diff::diff(&original, &current, granularity))
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to compute diff: {}", e)))?;
+
+        let total = result.chunks.len() as i64;
+        let chunks = diff::page_of(result.chunks, page, limit);
 
         Ok(HttpResponse::Ok().json(json!({
-            "message": "Transcript deleted successfully",
             "transcript_id": transcript_id,
-            "deleted_at": chrono::Utc::now()
+            "granularity": granularity,
+            "edited": result.stats.inserted > 0 || result.stats.deleted > 0,
+            "stats": result.stats,
+            "changes": PaginatedResponse::new(chunks, page, limit, total),
         })))
     }
 
-    /// Health check endpoint for transcription service
-    // pub async fn health_check(
-    //     app_state: web::Data<AppState>,
-    // ) -> AppResult<HttpResponse> {
-    //     // Check if temp directory is accessible
-    //     let temp_dir_exists = tokio::fs::try_exists(&app_state.config.temp_dir)
-    //         .await
-    //         .unwrap_or(false);
+    /// Update (correct) a transcript's text or filename
+    pub async fn update_transcript(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        request: web::Json<UpdateTranscriptRequest>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
 
-    //     // Check if FFmpeg is available
-    //     let ffmpeg_available = tokio::process::Command::new("ffmpeg")
-    //         .arg("-version")
-    //         .output()
-    //         .await
-    //         .map(|output| output.status.success())
-    //         .unwrap_or(false);
+        validation::validate_request(&*request)?;
+
+        if request.transcription.is_none() && request.filename.is_none() {
+            return Err(AppError::BadRequest(
+                "At least one of transcription or filename must be provided".to_string(),
+            ));
+        }
+
+        let filename = request
+            .filename
+            .as_deref()
+            .map(file::sanitize_filename)
+            .transpose()?;
+
+        log::info!("Updating transcript {} for user {}", transcript_id, user_id);
+
+        TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+        let transcript = TranscriptionService::update_transcript(
+            &app_state.db,
+            transcript_id,
+            user_id,
+            request.transcription.as_deref(),
+            filename.as_deref(),
+        )
+        .await?;
+
+        Ok(HttpResponse::Ok().json(TranscriptResponse::new(transcript, app_state.config.reading_wpm)))
+    }
+
+    /// Add tags to a transcript
+    pub async fn add_tags(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        request: web::Json<TagsRequest>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+
+        validation::validate_request(&*request)?;
+        let tags = validation::validate_tags(&request.tags)?;
+
+        let transcript =
+            TranscriptionService::add_tags(&app_state.db, transcript_id, user_id, &tags).await?;
+
+        Ok(HttpResponse::Ok().json(TranscriptResponse::new(transcript, app_state.config.reading_wpm)))
+    }
+
+    /// Remove a tag from a transcript
+    pub async fn remove_tag(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<(Uuid, String)>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let (transcript_id, tag) = path.into_inner();
+        let tag = validation::validate_tag(&tag)?;
+
+        let transcript =
+            TranscriptionService::remove_tags(&app_state.db, transcript_id, user_id, &[tag]).await?;
+
+        Ok(HttpResponse::Ok().json(TranscriptResponse::new(transcript, app_state.config.reading_wpm)))
+    }
+
+    /// Get the current user's transcription usage for this month
+    pub async fn get_usage(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+
+        let (period_start, period_end) = billing_period(chrono::Utc::now());
+        let used_seconds =
+            TranscriptionService::get_usage_seconds(&app_state.db, user_id, period_start).await?;
+        let minutes_used = used_seconds / 60.0;
+        let quota_minutes = app_state.config.monthly_minutes_quota;
+
+        Ok(HttpResponse::Ok().json(UsageResponse {
+            period_start,
+            period_end,
+            minutes_used,
+            quota_minutes,
+            minutes_remaining: quota_minutes.map(|quota| (quota - minutes_used).max(0.0)),
+            queue: JobService::queue_status(&app_state, None),
+        }))
+    }
+
+    /// Get totals over the current user's transcripts
+    pub async fn get_stats(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+
+        let stats = TranscriptionService::get_user_stats(&app_state.db, user_id).await?;
+
+        Ok(HttpResponse::Ok().json(UserStatsResponse::from(stats)))
+    }
+
+    /// Re-run a failed transcription from the stored original audio
+    pub async fn retry_transcript(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+        let max_attempts = app_state.config.max_transcription_attempts;
+
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+
+        if transcript.status != transcript_status::FAILED {
+            return Err(AppError::BadRequest(format!(
+                "Only failed transcripts can be retried (status is '{}')",
+                transcript.status
+            )));
+        }
+        if transcript.attempt_count >= max_attempts {
+            return Err(AppError::BadRequest(format!(
+                "Retry limit reached after {} attempts",
+                transcript.attempt_count
+            )));
+        }
+        let storage_key = transcript.storage_key.ok_or_else(|| {
+            AppError::BadRequest("Original audio was not retained, please upload it again".to_string())
+        })?;
+        let permit = app_state.job_queue.try_reserve()?;
+
+        // The job consumes a local copy, just like a fresh upload
+        let audio_path = format!(
+            "{}/{}",
+            app_state.config.temp_dir,
+            file::generate_unique_filename(&storage_key)
+        );
+        if let Err(e) = app_state.storage.get_to_file(&storage_key, &audio_path).await {
+            tokio::fs::remove_file(&audio_path).await.ok();
+            return Err(e);
+        }
+
+        // Conditional update so concurrent retries can't both enqueue the job
+        let transcript = match TranscriptionService::reset_for_retry(
+            &app_state.db,
+            transcript_id,
+            user_id,
+            &audio_path,
+            max_attempts,
+        )
+        .await
+        {
+            Ok(Some(transcript)) => transcript,
+            Ok(None) => {
+                tokio::fs::remove_file(&audio_path).await.ok();
+                return Err(AppError::BadRequest(
+                    "Transcript is no longer in a retryable state".to_string(),
+                ));
+            }
+            Err(e) => {
+                tokio::fs::remove_file(&audio_path).await.ok();
+                return Err(e);
+            }
+        };
+
+        JobService::enqueue(&app_state, transcript.id, transcript.duration_seconds, None, permit);
+
+        log::info!(
+            "Transcription job {} re-queued (attempt {} of {})",
+            transcript.id,
+            transcript.attempt_count,
+            max_attempts
+        );
+
+        Ok(HttpResponse::Accepted().json(TranscriptResponse::new(transcript, app_state.config.reading_wpm)))
+    }
+
+    /// Stream the original uploaded audio of a transcript
+    pub async fn get_transcript_audio(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+        let storage_key = transcript
+            .storage_key
+            .ok_or_else(|| AppError::NotFound("Original audio is not stored".to_string()))?;
+
+        let reader = app_state.storage.get(&storage_key).await?;
+        let body = stream::unfold(reader, |mut reader| async move {
+            let mut buffer = vec![0u8; 64 * 1024];
+            match reader.read(&mut buffer).await {
+                Ok(0) => None,
+                Ok(read) => {
+                    buffer.truncate(read);
+                    Some((Ok::<_, std::io::Error>(Bytes::from(buffer)), reader))
+                }
+                Err(e) => Some((Err(e), reader)),
+            }
+        });
+
+        // Audio is already compressed
+        Ok(HttpResponse::Ok()
+            .content_type(Self::guess_content_type(&storage_key))
+            .insert_header((header::CONTENT_ENCODING, "identity"))
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                Self::attachment_disposition(
+                    &transcript.filename,
+                    storage_key.rsplit('.').next().unwrap_or("bin"),
+                ),
+            ))
+            .streaming(body))
+    }
+
+    /// Get timestamped segments of a transcript
+    pub async fn get_transcript_segments(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<SegmentsQuery>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+
+        log::debug!("Fetching segments of transcript {} for user {}", transcript_id, user_id);
+
+        if let Some(max_confidence) = query.max_confidence
+            && !(0.0..=1.0).contains(&max_confidence)
+        {
+            return Err(AppError::ValidationError(
+                "max_confidence must be between 0 and 1".to_string(),
+            ));
+        }
+
+        let words = match query.granularity.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("segment") => false,
+            Some("word") => true,
+            Some(other) => {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported granularity '{}'. Supported values: segment, word",
+                    other
+                )));
+            }
+        };
+
+        if words {
+            let transcript =
+                TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+            if !transcript.word_timestamps {
+                return Err(AppError::BadRequest(
+                    "Word timestamps were not requested when this transcript was uploaded".to_string(),
+                ));
+            }
+        }
+
+        let segments = TranscriptionService::get_transcript_segments(
+            &app_state.db,
+            transcript_id,
+            user_id,
+            query.max_confidence,
+        )
+        .await?;
+
+        if words {
+            return Ok(HttpResponse::Ok().json(json!({
+                "transcript_id": transcript_id,
+                "words": segments
+                    .into_iter()
+                    .flat_map(|segment| segment.words)
+                    .collect::<Vec<_>>()
+            })));
+        }
+
+        Ok(HttpResponse::Ok().json(json!({
+            "transcript_id": transcript_id,
+            "segments": segments
+                .into_iter()
+                .map(TranscriptSegmentResponse::from)
+                .collect::<Vec<_>>()
+        })))
+    }
+
+    /// Stream transcription progress as Server-Sent Events
+    pub async fn transcript_progress(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+
+        // Ensure the transcript belongs to the user before subscribing
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+
+        let sse_response = || {
+            let mut response = HttpResponse::Ok();
+            // Compression would hold events back until the encoder's buffer fills
+            response
+                .content_type("text/event-stream")
+                .insert_header((header::CACHE_CONTROL, "no-cache"))
+                .insert_header((header::CONTENT_ENCODING, "identity"));
+            response
+        };
+
+        let receiver = match JobService::subscribe(&app_state, transcript_id) {
+            Some(receiver) => receiver,
+            None => {
+                // The job is no longer running, so report its final status straight away
+                let transcript =
+                    TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+
+                return Ok(sse_response().body(Self::sse_event(
+                    "done",
+                    json!({ "status": transcript.status }),
+                )));
+            }
+        };
+
+        let events = stream::unfold(Some(receiver), |receiver| async move {
+            let mut receiver = receiver?;
+            loop {
+                match receiver.recv().await {
+                    Ok(ProgressEvent::Progress(progress)) => {
+                        let event = Self::sse_event("progress", json!({ "progress": progress }));
+                        return Some((Ok::<_, AppError>(Bytes::from(event)), Some(receiver)));
+                    }
+                    Ok(ProgressEvent::Segment(segment)) => {
+                        let event = Self::sse_event(
+                            "segment",
+                            json!(TranscriptSegmentResponse::from(segment)),
+                        );
+                        return Some((Ok(Bytes::from(event)), Some(receiver)));
+                    }
+                    Ok(ProgressEvent::Done(status)) => {
+                        let event = Self::sse_event("done", json!({ "status": status }));
+                        return Some((Ok(Bytes::from(event)), None));
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        // A job still waiting for a worker starts with its place in the queue
+        let queued = (transcript.status == transcript_status::PENDING).then(|| {
+            let audio_seconds = transcript.duration_seconds.unwrap_or(0.0);
+            let queue = JobService::queue_status(&app_state, Some(audio_seconds));
+            Ok::<_, AppError>(Bytes::from(Self::sse_event("queue", json!(queue))))
+        });
+        let events = stream::iter(queued).chain(events);
+
+        Ok(sse_response().streaming(events))
+    }
+
+    /// Export transcript segments as an SRT or WebVTT subtitle file
+    pub async fn export_transcript(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<ExportQuery>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+        let format = query.format.to_lowercase();
+
+        let content_type = match format.as_str() {
+            "srt" => "application/x-subrip",
+            "vtt" => "text/vtt",
+            _ => {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported export format '{}'. Supported formats: srt, vtt",
+                    query.format
+                )));
+            }
+        };
+
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+        let segments = TranscriptionService::get_transcript_segments(
+            &app_state.db,
+            transcript_id,
+            user_id,
+            None,
+        )
+        .await?;
+
+        let body = match format.as_str() {
+            "srt" => export::to_srt(&segments),
+            _ => export::to_vtt(&segments),
+        };
+
+        log::debug!("Exporting transcript {} as {}", transcript_id, format);
+
+        Ok(HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                Self::attachment_disposition(&transcript.filename, &format),
+            ))
+            .body(body))
+    }
+
+    /// Download the transcript as a plain text or JSON attachment
+    pub async fn download_transcript(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<ExportQuery>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+        let format = query.format.to_lowercase();
+
+        let content_type = match format.as_str() {
+            "txt" => "text/plain; charset=utf-8",
+            "json" => "application/json",
+            _ => {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported download format '{}'. Supported formats: txt, json",
+                    query.format
+                )));
+            }
+        };
+
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+
+        let body = match format.as_str() {
+            "txt" => export::to_txt(&transcript),
+            _ => {
+                let segments = TranscriptionService::get_transcript_segments(
+                    &app_state.db,
+                    transcript_id,
+                    user_id,
+                    None,
+                )
+                .await?;
+                serde_json::to_string_pretty(&export::to_json(&transcript, &segments, app_state.config.reading_wpm))
+                    .map_err(|e| AppError::InternalError(format!("Failed to serialize transcript: {}", e)))?
+            }
+        };
+
+        log::debug!("Downloading transcript {} as {}", transcript_id, format);
+
+        Ok(HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                Self::attachment_disposition(&transcript.filename, &format),
+            ))
+            .body(body))
+    }
+
+    /// Create a signed, time-limited link that serves the transcript without authentication
+    pub async fn share_transcript(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        request: web::Json<ShareTranscriptRequest>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+
+        validation::validate_request(&*request)?;
+        let format = request.format.as_deref().unwrap_or("txt").to_lowercase();
+        if !SHARE_FORMATS.contains(&format.as_str()) {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported share format '{}'. Supported formats: {}",
+                format,
+                SHARE_FORMATS.join(", ")
+            )));
+        }
+
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+        if transcript.status != transcript_status::COMPLETED {
+            return Err(AppError::BadRequest(format!(
+                "Only completed transcripts can be shared (status is '{}')",
+                transcript.status
+            )));
+        }
+
+        let expires_at = chrono::Utc::now()
+            + chrono::Duration::hours(
+                request
+                    .expires_in_hours
+                    .unwrap_or(app_state.config.share_link_ttl_hours),
+            );
+        let token =
+            ShareService::create_token(&app_state.db, user_id, transcript_id, &format, expires_at)
+                .await?;
+
+        // Built from configuration, since the Host header is whatever the client sent
+        let url = app_state
+            .config
+            .public_url
+            .as_deref()
+            .map(|origin| format!("{}/api/v1/shared/{}", origin, token));
+
+        log::info!("Share link created for transcript {} until {}", transcript_id, expires_at);
+
+        Ok(HttpResponse::Created().json(json!({
+            "url": url,
+            "token": token,
+            "format": format,
+            "expires_at": expires_at
+        })))
+    }
+
+    /// Serve a shared transcript, read-only, to anyone holding a valid share token
+    pub async fn get_shared_transcript(
+        app_state: web::Data<AppState>,
+        path: web::Path<String>,
+    ) -> AppResult<HttpResponse> {
+        let shared = ShareService::verify_token(&app_state.db, &path.into_inner()).await?;
+
+        let transcript =
+            TranscriptionService::load_owned(&app_state.db, shared.transcript_id, shared.user_id)
+                .await?;
+
+        let (content_type, body) = match shared.format.as_str() {
+            "txt" => ("text/plain; charset=utf-8", export::to_txt(&transcript)),
+            format => {
+                let segments = TranscriptionService::get_transcript_segments(
+                    &app_state.db,
+                    shared.transcript_id,
+                    shared.user_id,
+                    None,
+                )
+                .await?;
+
+                match format {
+                    "srt" => ("application/x-subrip", export::to_srt(&segments)),
+                    _ => (
+                        "application/json",
+                        serde_json::to_string_pretty(&export::to_json(
+                            &transcript,
+                            &segments,
+                            app_state.config.reading_wpm,
+                        ))
+                        .map_err(|e| {
+                            AppError::InternalError(format!("Failed to serialize transcript: {}", e))
+                        })?,
+                    ),
+                }
+            }
+        };
+
+        log::debug!("Serving shared transcript {} as {}", shared.transcript_id, shared.format);
+
+        Ok(HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header((header::CACHE_CONTROL, "private, no-store"))
+            .body(body))
+    }
+
+    /// Revoke every share link of the current user
+    pub async fn revoke_shares(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+
+        ShareService::revoke_all(&app_state.db, user_id).await?;
+
+        Ok(HttpResponse::NoContent().finish())
+    }
+
+    /// Delete transcript by ID with confirmation
+    pub async fn delete_transcript(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+
+        log::info!("Deleting transcript {} for user {}", transcript_id, user_id);
+
+        TranscriptionService::load_owned(&app_state.db, transcript_id, user_id).await?;
+        TranscriptionService::delete_transcript(&app_state.db, transcript_id, user_id).await?;
+
+        Ok(HttpResponse::Ok().json(json!({
+            "message": "Transcript deleted successfully",
+            "transcript_id": transcript_id,
+            "deleted_at": chrono::Utc::now(),
+            "restorable_for_days": RESTORE_WINDOW_DAYS
+        })))
+    }
+
+    /// Delete several transcripts at once; they stay restorable like single deletes
+    pub async fn bulk_delete_transcripts(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        request: web::Json<BulkDeleteRequest>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+
+        validation::validate_request(&*request)?;
+
+        let mut ids = request.into_inner().ids;
+        ids.sort_unstable();
+        ids.dedup();
+
+        log::info!("Deleting {} transcripts for user {}", ids.len(), user_id);
+
+        let deleted = TranscriptionService::delete_transcripts(&app_state.db, &ids, user_id).await?;
+        let not_found: Vec<Uuid> = ids.into_iter().filter(|id| !deleted.contains(id)).collect();
+
+        Ok(HttpResponse::Ok().json(json!({
+            "deleted": deleted,
+            "not_found": not_found,
+            "deleted_at": chrono::Utc::now(),
+            "restorable_for_days": RESTORE_WINDOW_DAYS
+        })))
+    }
+
+    /// Restore a recently deleted transcript
+    pub async fn restore_transcript(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        let transcript_id = path.into_inner();
+
+        log::info!("Restoring transcript {} for user {}", transcript_id, user_id);
+
+        let transcript =
+            TranscriptionService::restore_transcript(&app_state.db, transcript_id, user_id)
+                .await?;
+
+        Ok(HttpResponse::Ok().json(TranscriptResponse::new(transcript, app_state.config.reading_wpm)))
+    }
+
+    // Health check endpoint for transcription service
+    // pub async fn health_check(
+    //     app_state: web::Data<AppState>,
+    // ) -> AppResult<HttpResponse> {
+    //     // Check if temp directory is accessible
+    //     let temp_dir_exists = tokio::fs::try_exists(&app_state.config.temp_dir)
+    //         .await
+    //         .unwrap_or(false);
+
+    //     // Check if FFmpeg is available
+    //     let ffmpeg_available = tokio::process::Command::new("ffmpeg")
+    //         .arg("-version")
+    //         .output()
+    //         .await
+    //         .map(|output| output.status.success())
+    //         .unwrap_or(false);
 
     //     let status = if temp_dir_exists && ffmpeg_available {
     //         "healthy"
@@ -330,23 +1688,580 @@ impl TranscriptionController {
     //     })))
     // }
 
-    /// Helper function to check supported audio formats (expanded list)
-    fn is_supported_audio_format(filename: &str) -> bool {
-        let supported_extensions = [
-            "wav", "mp3", "m4a", "flac", "ogg", "aac", "wma", 
-            "aiff", "au", "webm", "opus", "3gp", "amr"
-        ];
+    /// Helper function to build an attachment Content-Disposition from the original filename
+    fn attachment_disposition(filename: &str, extension: &str) -> String {
+        let stem = std::path::Path::new(filename)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().replace(['"', '\\'], "_"))
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or_else(|| "transcript".to_string());
+
+        format!("attachment; filename=\"{}.{}\"", stem, extension)
+    }
+
+    /// Helper function to format a Server-Sent Event
+    fn sse_event(event: &str, data: serde_json::Value) -> String {
+        format!("event: {}\ndata: {}\n\n", event, data)
+    }
+
+    /// Helper function to check an uploaded audio part and stream it into the temp directory
+    async fn receive_audio_file(
+        field: &mut Field,
+        filename: &str,
+        config: &Config,
+        max_size: usize,
+    ) -> AppResult<FileUpload> {
+        // The client's name is only kept, cleaned, for display; the temp path is generated
+        let filename = file::sanitize_filename(filename)?;
+        log::info!("Processing uploaded file: {}", filename);
+
+        // Validate file format (now supports more formats thanks to FFmpeg)
+        if !file::is_supported_audio_format(&filename, &config.supported_formats) {
+            return Err(AppError::ValidationError(format!(
+                "Unsupported audio format. Supported formats: {}",
+                config.supported_formats.join(", ")
+            )));
+        }
+
+        // Keep the upload on disk until the background job has transcribed it
+        let path = format!("{}/{}", config.temp_dir, file::generate_unique_filename(&filename));
+        let (size, format, sha256) = Self::stream_field_to_file(field, &path, max_size).await?;
+
+        // The sniffed container wins over the client-supplied content type
+        let content_type = format.content_type().to_string();
+
+        log::info!("File uploaded successfully: {} bytes ({:?})", size, format);
+
+        Ok(FileUpload {
+            filename,
+            content_type,
+            size,
+            path,
+            sha256,
+        })
+    }
+
+    /// Helper function to check limits for a received upload, create its transcript and
+    /// queue the background job. The temp file is removed if the upload is rejected.
+    async fn queue_upload(
+        app_state: &web::Data<AppState>,
+        req: &HttpRequest,
+        user_id: Uuid,
+        file_upload: &FileUpload,
+        options: &UploadOptions,
+        slot: Option<JobSlot>,
+        permit: QueuePermit,
+    ) -> AppResult<(Transcript, Option<f64>)> {
+        let original_filename = file_upload.filename.clone();
+        let audio_path = file_upload.path.clone();
         
-        if let Some(extension) = filename.split('.').last() {
-            supported_extensions.contains(&extension.to_lowercase().as_str())
-        } else {
-            false
+        log::info!(
+            "Processing transcription for file: {} ({}, {} bytes)",
+            file_upload.filename,
+            file_upload.content_type,
+            file_upload.size
+        );
+
+        // Refuse files without a decodable audio stream up front with a 400
+        if let Err(e) =
+            TranscriptionService::ensure_audio_stream(&app_state.ffmpeg, &audio_path).await
+        {
+            tokio::fs::remove_file(&audio_path).await.ok();
+            return Err(e);
+        }
+
+        // Get audio duration before transcription
+        let duration_seconds = match TranscriptionService::get_audio_duration(
+            &app_state.ffmpeg,
+            &audio_path,
+        )
+        .await
+        {
+            Ok(duration) => {
+                log::info!("Audio duration: {:.2} seconds", duration);
+                Some(duration)
+            }
+            Err(e) => {
+                log::warn!("Failed to get audio duration: {}", e);
+                None
+            }
+        };
+
+        // Reject overly long recordings before spending CPU on conversion/transcription.
+        // Recordings FFprobe can't time are checked by the job once decoded
+        if let Some(duration) = duration_seconds
+            && let Err(e) =
+                file::validate_audio_duration(duration, app_state.config.max_audio_duration_seconds)
+        {
+            tokio::fs::remove_file(&audio_path).await.ok();
+            return Err(e);
+        }
+
+        // Enforce the monthly quota. The job charges the decoded length, but a recording FFprobe
+        // can't time could run past what is left, so it is refused while a quota applies
+        if let Some(quota_minutes) = app_state.config.monthly_minutes_quota {
+            let Some(duration_seconds) = duration_seconds else {
+                tokio::fs::remove_file(&audio_path).await.ok();
+                return Err(AppError::ValidationError(
+                    "Could not determine the audio duration, which is required to check the monthly quota"
+                        .to_string(),
+                ));
+            };
+
+            let (period_start, _) = billing_period(chrono::Utc::now());
+            let used_seconds =
+                match TranscriptionService::get_usage_seconds(&app_state.db, user_id, period_start)
+                    .await
+                {
+                    Ok(used_seconds) => used_seconds,
+                    Err(e) => {
+                        tokio::fs::remove_file(&audio_path).await.ok();
+                        return Err(e);
+                    }
+                };
+
+            let remaining_minutes = (quota_minutes - used_seconds / 60.0).max(0.0);
+            let requested_minutes = duration_seconds / 60.0;
+
+            if remaining_minutes <= 0.0 || requested_minutes > remaining_minutes {
+                tokio::fs::remove_file(&audio_path).await.ok();
+                return Err(AppError::QuotaExceeded(remaining_minutes));
+            }
+        }
+
+        // Create the pending transcript and hand it to a background job
+        let job = NewTranscriptionJob {
+            filename: original_filename.clone(), // Use original filename for display
+            file_size: file_upload.size as i64,
+            duration_seconds,
+            language: options.language.clone(),
+            audio_path: audio_path.clone(),
+            diarize: options.diarize,
+            trim_silence: options.trim_silence,
+            initial_prompt: options.initial_prompt.clone(),
+            text_format: options.text_format.to_string(),
+            word_timestamps: options.word_timestamps,
+            model_name: options.model.clone(),
+            capitalize_sentences: options.capitalize_sentences,
+            numbers_to_digits: options.numbers_to_digits,
+            temperature: options.temperature,
+            temperature_inc: options.temperature_inc,
+            audio_filters: options.audio_filters.clone(),
+            source_ip: Some(client_ip(req)),
+            user_agent: req
+                .headers()
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_CHARS).collect()),
+        };
+
+        let transcript = match TranscriptionService::create_transcription_job(
+            &app_state.db,
+            user_id,
+            &job,
+        )
+        .await
+        {
+            Ok(transcript) => transcript,
+            Err(e) => {
+                tokio::fs::remove_file(&audio_path).await.ok();
+                return Err(e);
+            }
+        };
+
+        // Keep the original so it can be listened to again; transcription doesn't depend on it
+        let storage_key = format!(
+            "audio/{}.{}",
+            transcript.id,
+            original_filename.rsplit('.').next().unwrap_or("bin").to_lowercase()
+        );
+        match app_state.storage.put(&storage_key, &audio_path).await {
+            Ok(()) => {
+                // The job row already exists, so losing the key only loses the original
+                if let Err(e) =
+                    TranscriptionService::set_storage_key(&app_state.db, transcript.id, &storage_key)
+                        .await
+                {
+                    log::warn!("Failed to record original audio for {}: {}", transcript.id, e);
+                    app_state.storage.delete(&storage_key).await.ok();
+                }
+            }
+            Err(e) => log::warn!("Failed to store original audio for {}: {}", transcript.id, e),
+        }
+
+        JobService::enqueue(app_state, transcript.id, duration_seconds, slot, permit);
+
+        Ok((transcript, duration_seconds))
+    }
+
+    /// Helper function to enforce the per-user upload rate and concurrent job limits and
+    /// reserve a place in the transcription queue
+    fn acquire_upload_slot(
+        app_state: &AppState,
+        user_id: Uuid,
+    ) -> AppResult<(JobSlot, QueuePermit)> {
+        // Claim the slot and queue place first so a rejected upload doesn't use up the
+        // minute's allowance
+        let slot = app_state
+            .job_slots
+            .try_acquire(user_id, app_state.config.max_concurrent_jobs_per_user)?;
+        let permit = app_state.job_queue.try_reserve()?;
+        check_rate_limit(
+            &app_state.upload_rate_limits,
+            &user_id.to_string(),
+            app_state.config.upload_rate_limit_per_minute,
+        )?;
+        Ok((slot, permit))
+    }
+
+    /// Helper function to build the `202 Accepted` body of a queued upload
+    fn queued_response(
+        app_state: &AppState,
+        transcript: Transcript,
+        duration_seconds: Option<f64>,
+        file_size: usize,
+    ) -> serde_json::Value {
+        json!({
+            "transcript_id": transcript.id,
+            "status": transcript.status.clone(),
+            "transcript": TranscriptResponse::new(transcript, app_state.config.reading_wpm),
+            "audio_duration_seconds": duration_seconds,
+            "file_size_bytes": file_size,
+            "queue": JobService::queue_status(app_state, Some(duration_seconds.unwrap_or(0.0))),
+        })
+    }
+
+    /// Helper function to describe a resumable upload's progress
+    /// Uploads expire once nothing is received for `TEMP_FILE_MAX_AGE_SECONDS`
+    fn upload_status(upload: &Upload, config: &Config) -> serde_json::Value {
+        let expires_at = upload.updated_at
+            + chrono::Duration::seconds(config.temp_file_max_age_seconds as i64);
+
+        json!({
+            "upload_id": upload.id,
+            "filename": upload.filename,
+            "size": upload.total_size,
+            "offset": upload.received_bytes,
+            "complete": upload.received_bytes == upload.total_size,
+            "expires_at": expires_at,
+        })
+    }
+
+    /// Helper function to write the `(start, end, total)` range of a locked upload from the body,
+    /// returning the new received offset
+    async fn write_range(
+        db: &PgPool,
+        upload: &Upload,
+        (start, end, total): (i64, i64, i64),
+        mut payload: web::Payload,
+    ) -> AppResult<i64> {
+        if total != upload.total_size {
+            return Err(AppError::BadRequest(format!(
+                "Content-Range total must be the upload size of {} bytes",
+                upload.total_size
+            )));
+        }
+        if start != upload.received_bytes {
+            return Err(AppError::Conflict(format!(
+                "Expected a range starting at byte {}",
+                upload.received_bytes
+            )));
+        }
+
+        let mut output = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(&upload.path)
+            .await
+            .map_err(|_| AppError::NotFound("Upload data is no longer available".to_string()))?;
+        output.set_len(start as u64).await?;
+        output.seek(std::io::SeekFrom::Start(start as u64)).await?;
+
+        let length = (end - start + 1) as usize;
+        let mut written = 0usize;
+        let mut overflowed = false;
+        let received: AppResult<()> = async {
+            while let Some(chunk) = payload.try_next().await.map_err(|e| {
+                AppError::BadRequest(format!("Failed to read upload data: {}", e))
+            })? {
+                if written + chunk.len() > length {
+                    overflowed = true;
+                    return Err(AppError::BadRequest(format!(
+                        "Request body is longer than the {} bytes in Content-Range",
+                        length
+                    )));
+                }
+                output.write_all(&chunk).await.map_err(|e| {
+                    AppError::FileError(format!("Failed to write temporary file: {}", e))
+                })?;
+                written += chunk.len();
+            }
+
+            if written < length {
+                return Err(AppError::BadRequest(format!(
+                    "Received {} of the {} bytes in Content-Range",
+                    written, length
+                )));
+            }
+            Ok(())
+        }
+        .await;
+
+        // Keep whatever arrived intact so the client can resume after it, unless the body
+        // didn't match its Content-Range
+        let kept = if overflowed { 0 } else { written };
+        output.set_len(start as u64 + kept as u64).await?;
+        output.flush().await.map_err(|e| {
+            AppError::FileError(format!("Failed to write temporary file: {}", e))
+        })?;
+        drop(output);
+
+        let received_bytes = start + kept as i64;
+        if kept > 0 {
+            UploadService::advance(db, upload.id, start, received_bytes).await?;
+        }
+        received?;
+
+        Ok(received_bytes)
+    }
+
+    /// Helper function to parse a `Content-Range: bytes <start>-<end>/<total>` header
+    fn content_range(req: &HttpRequest) -> AppResult<(i64, i64, i64)> {
+        let invalid = || {
+            AppError::BadRequest(
+                "Content-Range must look like 'bytes <start>-<end>/<total>'".to_string(),
+            )
+        };
+
+        let value = req
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .ok_or_else(|| AppError::BadRequest("Content-Range header is required".to_string()))?
+            .to_str()
+            .map_err(|_| invalid())?;
+        let (range, total) = value
+            .trim()
+            .strip_prefix("bytes ")
+            .and_then(|range| range.split_once('/'))
+            .ok_or_else(invalid)?;
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+
+        let parse = |value: &str| value.trim().parse::<i64>().ok().filter(|value| *value >= 0);
+        let (start, end, total) = match (parse(start), parse(end), parse(total)) {
+            (Some(start), Some(end), Some(total)) if start <= end && end < total => (start, end, total),
+            _ => return Err(invalid()),
+        };
+
+        Ok((start, end, total))
+    }
+
+    /// Helper function to sniff the container of an assembled upload and hash its content
+    async fn inspect_upload(path: &str) -> AppResult<(Option<file::AudioFormat>, String)> {
+        let mut input = tokio::fs::File::open(path)
+            .await
+            .map_err(|_| AppError::NotFound("Upload data is no longer available".to_string()))?;
+
+        let mut hasher = Sha256::new();
+        let mut header = Vec::with_capacity(file::SNIFF_LEN);
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = input.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            if header.len() < file::SNIFF_LEN {
+                let needed = (file::SNIFF_LEN - header.len()).min(read);
+                header.extend_from_slice(&buffer[..needed]);
+            }
+            hasher.update(&buffer[..read]);
+        }
+
+        Ok((file::sniff_audio_format(&header), hex::encode(hasher.finalize())))
+    }
+
+    /// Helper function to validate the OpenAI `response_format` field, which defaults to `json`
+    fn openai_response_format(value: Option<&str>) -> AppResult<&'static str> {
+        let value = value.unwrap_or("json").to_lowercase();
+        OPENAI_RESPONSE_FORMATS
+            .iter()
+            .find(|format| **format == value)
+            .copied()
+            .ok_or_else(|| {
+                AppError::ValidationError(format!(
+                    "Unsupported response_format '{}'. Supported formats: {}",
+                    value,
+                    OPENAI_RESPONSE_FORMATS.join(", ")
+                ))
+            })
+    }
+
+    /// Helper function to read an optional `Idempotency-Key` header
+    fn idempotency_key(req: &HttpRequest) -> AppResult<Option<String>> {
+        let Some(value) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+            return Ok(None);
+        };
+
+        let key = value
+            .to_str()
+            .map(str::trim)
+            .map_err(|_| AppError::BadRequest("Idempotency-Key must be printable ASCII".to_string()))?;
+        if key.is_empty() || key.len() > 255 {
+            return Err(AppError::BadRequest(
+                "Idempotency-Key must be between 1 and 255 characters".to_string(),
+            ));
+        }
+
+        Ok(Some(key.to_string()))
+    }
+
+    /// Helper function to stream a file field to disk, enforcing the size limit as it writes.
+    /// Nothing is written unless the leading bytes identify a supported audio container.
+    async fn stream_field_to_file(
+        field: &mut Field,
+        path: &str,
+        max_size: usize,
+    ) -> AppResult<(usize, file::AudioFormat, String)> {
+        let mut header = Vec::with_capacity(file::SNIFF_LEN);
+        let mut size = 0usize;
+        while header.len() < file::SNIFF_LEN {
+            match field.try_next().await.map_err(|e| {
+                Self::multipart_error("Failed to read audio file chunk", e)
+            })? {
+                Some(chunk) => {
+                    size += chunk.len();
+                    file::validate_file_size(size, max_size)?;
+                    header.extend_from_slice(&chunk);
+                }
+                None => break,
+            }
+        }
+
+        let format = file::sniff_audio_format(&header).ok_or_else(|| {
+            AppError::ValidationError(
+                "Uploaded file does not look like a supported audio file".to_string(),
+            )
+        })?;
+
+        let mut output = tokio::fs::File::create(path)
+            .await
+            .map_err(|e| AppError::FileError(format!("Failed to create temporary file: {}", e)))?;
+
+        // Hash the content as it streams by, for matching retried requests
+        let mut hasher = Sha256::new();
+        hasher.update(&header);
+
+        let written: AppResult<()> = async {
+            output.write_all(&header).await.map_err(|e| {
+                AppError::FileError(format!("Failed to write temporary file: {}", e))
+            })?;
+
+            while let Some(chunk) = field.try_next().await.map_err(|e| {
+                Self::multipart_error("Failed to read audio file chunk", e)
+            })? {
+                size += chunk.len();
+                file::validate_file_size(size, max_size)?;
+                hasher.update(&chunk);
+
+                output.write_all(&chunk).await.map_err(|e| {
+                    AppError::FileError(format!("Failed to write temporary file: {}", e))
+                })?;
+            }
+
+            output.flush().await.map_err(|e| {
+                AppError::FileError(format!("Failed to write temporary file: {}", e))
+            })?;
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = written {
+            // Abort the upload and delete the partial file
+            drop(output);
+            tokio::fs::remove_file(path).await.ok();
+            return Err(e);
+        }
+
+        Ok((size, format, hex::encode(hasher.finalize())))
+    }
+
+    /// Helper function to read a small text form field
+    async fn read_text_field(field: &mut Field) -> AppResult<String> {
+        let mut value = Vec::new();
+        while let Some(chunk) = field
+            .try_next()
+            .await
+            .map_err(|e| Self::multipart_error("Failed to read form field", e))?
+        {
+            value.extend_from_slice(&chunk);
+            if value.len() > MAX_FORM_FIELD_BYTES {
+                return Err(AppError::PayloadTooLarge(format!(
+                    "Form fields are limited to {} bytes",
+                    MAX_FORM_FIELD_BYTES
+                )));
+            }
+        }
+
+        String::from_utf8(value)
+            .map(|v| v.trim().to_string())
+            .map_err(|_| AppError::BadRequest("Form field must be valid UTF-8".to_string()))
+    }
+
+    /// Helper function to map multipart errors, reporting an oversized body as 413
+    fn multipart_error(context: &str, error: MultipartError) -> AppError {
+        match error {
+            MultipartError::Payload(PayloadError::Overflow) => {
+                AppError::PayloadTooLarge("Request body is too large".to_string())
+            }
+            error => AppError::BadRequest(format!("{}: {}", context, error)),
+        }
+    }
+
+    /// Helper function to read a multipart body of at most `max_size` bytes plus overhead
+    /// The limit is enforced by counting bytes as they arrive, so chunked bodies without a
+    /// `Content-Length` are cut off with `413 Payload Too Large` as soon as they go over it
+    fn limited_multipart(
+        req: &HttpRequest,
+        payload: web::Payload,
+        max_size: usize,
+    ) -> AppResult<Multipart> {
+        Self::check_content_length(req, max_size)?;
+
+        let limit = max_size.saturating_add(MULTIPART_OVERHEAD_BYTES);
+        let mut received = 0usize;
+        let body = payload.map(move |chunk| {
+            let chunk = chunk?;
+            received += chunk.len();
+            if received > limit {
+                return Err(PayloadError::Overflow);
+            }
+            Ok(chunk)
+        });
+
+        Ok(Multipart::new(req.headers(), body))
+    }
+
+    /// Helper function to reject a request whose declared `Content-Length` already exceeds
+    /// `max_size` plus room for multipart headers and text fields, before reading any of it
+    fn check_content_length(req: &HttpRequest, max_size: usize) -> AppResult<()> {
+        let content_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+
+        match content_length {
+            Some(length) if length > max_size.saturating_add(MULTIPART_OVERHEAD_BYTES) => {
+                Err(AppError::PayloadTooLarge(format!(
+                    "Request body of {} bytes exceeds maximum allowed size of {} bytes",
+                    length, max_size
+                )))
+            }
+            _ => Ok(()),
         }
     }
 
     /// Helper function to guess content type from filename
     fn guess_content_type(filename: &str) -> String {
-        match filename.split('.').last().unwrap_or("").to_lowercase().as_str() {
+        match filename.rsplit('.').next().unwrap_or("").to_lowercase().as_str() {
             "mp3" => "audio/mpeg".to_string(),
             "wav" => "audio/wav".to_string(),
             "m4a" => "audio/mp4".to_string(),
@@ -360,4 +2275,229 @@ impl TranscriptionController {
             _ => "application/octet-stream".to_string(),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::FromRequest;
+    use actix_web::dev::Payload;
+    use actix_web::test::TestRequest;
+    use crate::services::UserService;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const BOUNDARY: &str = "test-boundary";
+    const CHUNK_BYTES: usize = 64 * 1024;
+
+    fn multipart_request() -> TestRequest {
+        TestRequest::post().insert_header((
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        ))
+    }
+
+    /// Chunked multipart body of one audio part `chunks` chunks long, counting the chunks pulled
+    fn chunked_body(chunks: usize, pulled: Arc<AtomicUsize>) -> Payload {
+        let part_header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"audio_file\"; \
+             filename=\"a.wav\"\r\nContent-Type: audio/wav\r\n\r\n",
+            BOUNDARY
+        );
+        let closing = format!("\r\n--{}--\r\n", BOUNDARY);
+        let body = stream::iter(0..chunks + 2).map(move |index| {
+            pulled.fetch_add(1, Ordering::SeqCst);
+            Ok(match index {
+                0 => Bytes::from(part_header.clone()),
+                index if index > chunks => Bytes::from(closing.clone()),
+                _ => Bytes::from(vec![0u8; CHUNK_BYTES]),
+            })
+        });
+        Payload::Stream {
+            payload: Box::pin(body),
+        }
+    }
+
+    /// Read every part to the end, returning the first error as the handlers report it
+    async fn drain(mut multipart: Multipart) -> AppResult<()> {
+        let read_error = |e| TranscriptionController::multipart_error("Failed to read", e);
+        while let Some(mut field) = multipart.try_next().await.map_err(read_error)? {
+            while field.try_next().await.map_err(read_error)?.is_some() {}
+        }
+        Ok(())
+    }
+
+    #[actix_web::test]
+    async fn chunked_body_over_limit_is_cut_off_with_413() {
+        let chunks = 2 * MULTIPART_OVERHEAD_BYTES / CHUNK_BYTES;
+        let pulled = Arc::new(AtomicUsize::new(0));
+        let (req, _) = multipart_request().to_http_parts();
+        assert!(req.headers().get(header::CONTENT_LENGTH).is_none());
+        let mut body = chunked_body(chunks, pulled.clone());
+        let payload = web::Payload::from_request(&req, &mut body).await.unwrap();
+
+        let multipart = TranscriptionController::limited_multipart(&req, payload, 1024).unwrap();
+        let error = drain(multipart).await.unwrap_err();
+
+        assert!(matches!(error, AppError::PayloadTooLarge(_)));
+        assert_eq!(error.error_response().status(), 413);
+        // Rejected as soon as the limit is passed, not after reading the whole body
+        assert!(pulled.load(Ordering::SeqCst) < chunks);
+    }
+
+    #[actix_web::test]
+    async fn chunked_body_within_limit_is_read() {
+        let (req, _) = multipart_request().to_http_parts();
+        let mut body = chunked_body(4, Arc::new(AtomicUsize::new(0)));
+        let payload = web::Payload::from_request(&req, &mut body).await.unwrap();
+
+        let multipart =
+            TranscriptionController::limited_multipart(&req, payload, 4 * CHUNK_BYTES).unwrap();
+
+        drain(multipart).await.unwrap();
+    }
+
+    fn content_range(value: &str) -> AppResult<(i64, i64, i64)> {
+        let req = TestRequest::put()
+            .insert_header((header::CONTENT_RANGE, value))
+            .to_http_request();
+        TranscriptionController::content_range(&req)
+    }
+
+    #[test]
+    fn content_range_is_parsed() {
+        assert_eq!(content_range("bytes 0-9/100").unwrap(), (0, 9, 100));
+        assert_eq!(content_range("bytes 99-99/100").unwrap(), (99, 99, 100));
+        assert!(TranscriptionController::content_range(&TestRequest::put().to_http_request()).is_err());
+    }
+
+    #[test]
+    fn malformed_content_range_is_rejected() {
+        for value in [
+            "0-9/100",
+            "bytes 0-9",
+            "bytes 0/100",
+            "bytes a-9/100",
+            "bytes -1-9/100",
+            "bytes 0-9/*",
+            "items 0-9/100",
+        ] {
+            assert!(
+                matches!(content_range(value), Err(AppError::BadRequest(_))),
+                "{} was accepted",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn content_range_must_be_ordered_and_within_total() {
+        assert!(content_range("bytes 5-4/100").is_err());
+        assert!(content_range("bytes 0-100/100").is_err());
+        assert!(content_range("bytes 0-0/0").is_err());
+    }
+
+    /// Resumable upload of `total_size` bytes backed by an empty temporary file
+    async fn create_upload(pool: &PgPool, total_size: i64) -> (Upload, file::TempFile) {
+        let params = argon2::Params::new(8, 1, 1, None).unwrap();
+        let user = UserService::register_user(pool, "upload@example.com", "Password 1", &params)
+            .await
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("upload-test-{}", Uuid::new_v4()));
+        let path = path.to_string_lossy().to_string();
+        tokio::fs::write(&path, b"").await.unwrap();
+
+        let upload = UploadService::create(pool, user.id, "a.wav", &path, total_size)
+            .await
+            .unwrap();
+        (upload, file::TempFile::new(path))
+    }
+
+    /// Send `parts` as the chunks of a request body for `range`, returning the new offset
+    async fn write(
+        pool: &PgPool,
+        upload: &Upload,
+        range: (i64, i64, i64),
+        parts: &[&'static [u8]],
+    ) -> AppResult<i64> {
+        let upload = UploadService::get(pool, upload.id, upload.user_id).await.unwrap();
+        let (req, _) = TestRequest::put().to_http_parts();
+        let chunks: Vec<Result<Bytes, actix_web::error::PayloadError>> =
+            parts.iter().map(|part| Ok(Bytes::from_static(part))).collect();
+        let mut body: Payload = Payload::Stream {
+            payload: Box::pin(stream::iter(chunks)),
+        };
+        let payload = web::Payload::from_request(&req, &mut body).await.unwrap();
+
+        TranscriptionController::write_range(pool, &upload, range, payload).await
+    }
+
+    async fn received(pool: &PgPool, upload: &Upload) -> (i64, Vec<u8>) {
+        let offset = UploadService::get(pool, upload.id, upload.user_id)
+            .await
+            .unwrap()
+            .received_bytes;
+        (offset, tokio::fs::read(&upload.path).await.unwrap())
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn ranges_are_appended_in_order(pool: PgPool) {
+        let (upload, _file) = create_upload(&pool, 10).await;
+
+        assert_eq!(write(&pool, &upload, (0, 4, 10), &[b"hel", b"lo"]).await.unwrap(), 5);
+        assert_eq!(write(&pool, &upload, (5, 9, 10), &[b"world"]).await.unwrap(), 10);
+
+        assert_eq!(received(&pool, &upload).await, (10, b"helloworld".to_vec()));
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn range_must_continue_at_the_received_offset(pool: PgPool) {
+        let (upload, _file) = create_upload(&pool, 10).await;
+        write(&pool, &upload, (0, 4, 10), &[b"hello"]).await.unwrap();
+
+        for range in [(6, 9, 10), (0, 4, 10), (4, 9, 10)] {
+            let error = write(&pool, &upload, range, &[b"world"]).await.unwrap_err();
+            assert!(matches!(error, AppError::Conflict(_)));
+            assert_eq!(error.error_response().status(), 409);
+        }
+        let error = write(&pool, &upload, (5, 9, 11), &[b"world"]).await.unwrap_err();
+        assert!(matches!(error, AppError::BadRequest(_)));
+
+        assert_eq!(received(&pool, &upload).await, (5, b"hello".to_vec()));
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn overlong_body_is_truncated_back_to_the_range_start(pool: PgPool) {
+        let (upload, _file) = create_upload(&pool, 10).await;
+        write(&pool, &upload, (0, 4, 10), &[b"hello"]).await.unwrap();
+
+        let result = write(&pool, &upload, (5, 9, 10), &[b"wor", b"ld!!"]).await;
+
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert_eq!(received(&pool, &upload).await, (5, b"hello".to_vec()));
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn short_body_keeps_the_bytes_that_arrived(pool: PgPool) {
+        let (upload, _file) = create_upload(&pool, 10).await;
+
+        let result = write(&pool, &upload, (0, 9, 10), &[b"hel", b"lo"]).await;
+
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert_eq!(received(&pool, &upload).await, (5, b"hello".to_vec()));
+        // The client resumes after what was kept
+        assert_eq!(write(&pool, &upload, (5, 9, 10), &[b"world"]).await.unwrap(), 10);
+        assert_eq!(received(&pool, &upload).await, (10, b"helloworld".to_vec()));
+    }
+
+    #[actix_web::test]
+    async fn declared_length_over_limit_is_rejected_up_front() {
+        let req = multipart_request()
+            .insert_header((header::CONTENT_LENGTH, MULTIPART_OVERHEAD_BYTES + 1025))
+            .to_http_request();
+
+        let result = TranscriptionController::check_content_length(&req, 1024);
+
+        assert!(matches!(result, Err(AppError::PayloadTooLarge(_))));
+        assert!(TranscriptionController::check_content_length(&req, 1025).is_ok());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::AppState;
+use crate::errors::{AppError, AppResult};
+use crate::middlewares::extract_user_id;
+use crate::models::*;
+use crate::services::{SIGNATURE_HEADER, WebhookService};
+use crate::utils::validation;
+use actix_web::{HttpRequest, HttpResponse, web};
+use serde_json::json;
+
+/// Webhook controller for job completion callbacks
+pub struct WebhookController;
+
+impl WebhookController {
+    /// Get the current user's webhook (the secret is only shown when it is generated)
+    pub async fn get_webhook(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+
+        let webhook = WebhookService::get_webhook(&app_state.db, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("No webhook registered".to_string()))?;
+
+        Ok(HttpResponse::Ok().json(json!({
+            "url": webhook.url,
+            "created_at": webhook.created_at,
+            "updated_at": webhook.updated_at
+        })))
+    }
+
+    /// Register or replace the current user's webhook
+    pub async fn put_webhook(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+        request: web::Json<WebhookRequest>,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+        validation::validate_request(&*request)?;
+
+        WebhookService::check_url(&request.url).await?;
+
+        let webhook = WebhookService::upsert_webhook(&app_state.db, user_id, &request.url).await?;
+
+        Ok(HttpResponse::Ok().json(json!({
+            "url": webhook.url,
+            "secret": webhook.secret,
+            "signature_header": SIGNATURE_HEADER,
+            "created_at": webhook.created_at,
+            "updated_at": webhook.updated_at
+        })))
+    }
+
+    /// Remove the current user's webhook
+    pub async fn delete_webhook(
+        app_state: web::Data<AppState>,
+        req: HttpRequest,
+    ) -> AppResult<HttpResponse> {
+        let user_id = extract_user_id(&req)?;
+
+        if !WebhookService::delete_webhook(&app_state.db, user_id).await? {
+            return Err(AppError::NotFound("No webhook registered".to_string()));
+        }
+
+        Ok(HttpResponse::Ok().json(json!({
+            "message": "Webhook removed successfully"
+        })))
+    }
+}
@@ -1,8 +1,25 @@
 // errors/mod.rs - Central error handling for the application
 use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
 use serde_json::json;
 use thiserror::Error;
 
+/// Validation messages for a single request field
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub messages: Vec<String>,
+}
+
+/// Join field errors into a human-readable summary
+fn format_field_errors(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.messages.join(", ")))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
 /// Main application error type
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -13,7 +30,7 @@ pub enum AppError {
     JwtError(#[from] jsonwebtoken::errors::Error),
 
     #[error("Password hashing error: {0}")]
-    ArgonError(#[from(std::error::Error)] argon2::password_hash::Error),
+    ArgonError(#[from] argon2::password_hash::Error),
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -27,12 +44,21 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    #[error("Validation error: {}", format_field_errors(.0))]
+    ValidationErrors(Vec<FieldError>),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
     #[error("Internal server error: {0}")]
     InternalError(String),
 
@@ -47,6 +73,24 @@ pub enum AppError {
 
     #[error("Forbidden access")]
     Forbidden,
+
+    #[error("Too many requests, retry after {0} seconds")]
+    RateLimited(u64),
+
+    #[error("Server is busy, retry after {0} seconds")]
+    ServiceUnavailable(u64),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
+    #[error("Monthly transcription quota exceeded, {0:.1} minutes remaining")]
+    QuotaExceeded(f64),
 }
 
 impl ResponseError for AppError {
@@ -59,7 +103,52 @@ impl ResponseError for AppError {
                 (actix_web::http::StatusCode::BAD_REQUEST, "Bad Request")
             }
             AppError::NotFound(_) => (actix_web::http::StatusCode::NOT_FOUND, "Not Found"),
+            AppError::Conflict(_) => (actix_web::http::StatusCode::CONFLICT, "Conflict"),
+            AppError::PayloadTooLarge(_) => {
+                (actix_web::http::StatusCode::PAYLOAD_TOO_LARGE, "Payload Too Large")
+            }
             AppError::Forbidden => (actix_web::http::StatusCode::FORBIDDEN, "Forbidden"),
+            AppError::NotImplemented(_) => {
+                (actix_web::http::StatusCode::NOT_IMPLEMENTED, "Not Implemented")
+            }
+            AppError::Timeout(_) => {
+                (actix_web::http::StatusCode::GATEWAY_TIMEOUT, "Gateway Timeout")
+            }
+            AppError::ValidationErrors(errors) => {
+                let fields: serde_json::Map<String, serde_json::Value> = errors
+                    .iter()
+                    .map(|error| (error.field.clone(), json!(error.messages)))
+                    .collect();
+
+                return HttpResponse::BadRequest().json(json!({
+                    "error": "Bad Request",
+                    "message": self.to_string(),
+                    "fields": fields
+                }));
+            }
+            AppError::RateLimited(retry_after) => {
+                return HttpResponse::TooManyRequests()
+                    .insert_header((actix_web::http::header::RETRY_AFTER, retry_after.to_string()))
+                    .json(json!({
+                        "error": "Too Many Requests",
+                        "message": self.to_string()
+                    }));
+            }
+            AppError::ServiceUnavailable(retry_after) => {
+                return HttpResponse::ServiceUnavailable()
+                    .insert_header((actix_web::http::header::RETRY_AFTER, retry_after.to_string()))
+                    .json(json!({
+                        "error": "Service Unavailable",
+                        "message": self.to_string()
+                    }));
+            }
+            AppError::QuotaExceeded(remaining_minutes) => {
+                return HttpResponse::TooManyRequests().json(json!({
+                    "error": "Too Many Requests",
+                    "message": self.to_string(),
+                    "remaining_minutes": remaining_minutes
+                }));
+            }
             _ => {
                 log::error!("Internal server error: {}", self);
                 (
@@ -0,0 +1,156 @@
+// events/mod.rs - Transcription events published to a message broker
+use crate::AppState;
+use crate::config::Config;
+use crate::errors::{AppError, AppResult};
+use crate::models::{Transcript, transcript_status};
+use crate::services::TranscriptionService;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Subject prefix of published events; the event type is appended, e.g. `ai_scribe.transcript.completed`
+const SUBJECT_PREFIX: &str = "ai_scribe";
+
+/// Outcome of a transcription job, as published to consumers
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptEvent {
+    TranscriptCompleted {
+        user_id: Uuid,
+        transcript_id: Uuid,
+        duration_seconds: Option<f64>,
+        word_count: i32,
+        occurred_at: DateTime<Utc>,
+    },
+    TranscriptFailed {
+        user_id: Uuid,
+        transcript_id: Uuid,
+        duration_seconds: Option<f64>,
+        error: Option<String>,
+        occurred_at: DateTime<Utc>,
+    },
+}
+
+impl TranscriptEvent {
+    /// Event for a transcript whose job just finished; `None` while it is still pending or running
+    pub fn from_transcript(transcript: &Transcript) -> Option<Self> {
+        match transcript.status.as_str() {
+            transcript_status::COMPLETED => Some(Self::TranscriptCompleted {
+                user_id: transcript.user_id,
+                transcript_id: transcript.id,
+                duration_seconds: transcript.duration_seconds,
+                word_count: transcript.word_count,
+                occurred_at: transcript.updated_at,
+            }),
+            transcript_status::FAILED => Some(Self::TranscriptFailed {
+                user_id: transcript.user_id,
+                transcript_id: transcript.id,
+                duration_seconds: transcript.duration_seconds,
+                error: transcript.error_message.clone(),
+                occurred_at: transcript.updated_at,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Broker subject the event is published on
+    pub fn subject(&self) -> String {
+        let name = match self {
+            Self::TranscriptCompleted { .. } => "transcript.completed",
+            Self::TranscriptFailed { .. } => "transcript.failed",
+        };
+        format!("{}.{}", SUBJECT_PREFIX, name)
+    }
+}
+
+/// Destination of transcription events
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    /// Publish a single event
+    async fn publish(&self, event: &TranscriptEvent) -> AppResult<()>;
+}
+
+/// Build the publisher for `EVENT_BROKER_URL`; events are dropped when it isn't set
+pub async fn build_publisher(config: &Config) -> AppResult<Arc<dyn EventPublisher>> {
+    match config.event_broker_url.as_deref() {
+        None => Ok(Arc::new(NoopPublisher)),
+        Some(url) if url.starts_with("nats://") || url.starts_with("tls://") => {
+            Ok(Arc::new(NatsPublisher::connect(url).await?))
+        }
+        Some(_) => Err(AppError::ConfigError(
+            "EVENT_BROKER_URL must be a nats:// or tls:// URL".to_string(),
+        )),
+    }
+}
+
+/// Publish the outcome of a finished job on a background task
+/// Publishing is best-effort: failures are logged and never affect the job or the request
+pub fn publish_job_finished(app_state: &AppState, transcript_id: Uuid) {
+    let app_state = app_state.clone();
+
+    tokio::spawn(
+        async move {
+            let published = async {
+                let transcript =
+                    TranscriptionService::get_transcript_for_job(&app_state.db, transcript_id)
+                        .await?;
+                match TranscriptEvent::from_transcript(&transcript) {
+                    Some(event) => app_state.events.publish(&event).await,
+                    None => Ok(()),
+                }
+            }
+            .await;
+
+            if let Err(e) = published {
+                log::warn!("Failed to publish event for job {}: {}", transcript_id, e);
+            }
+        }
+        .instrument(tracing::Span::current()),
+    );
+}
+
+/// Discards every event
+pub struct NoopPublisher;
+
+#[async_trait]
+impl EventPublisher for NoopPublisher {
+    async fn publish(&self, _event: &TranscriptEvent) -> AppResult<()> {
+        Ok(())
+    }
+}
+
+/// Publishes events as JSON messages to a NATS server
+pub struct NatsPublisher {
+    client: async_nats::Client,
+}
+
+impl NatsPublisher {
+    /// Connect in the background, so a broker that is down at startup doesn't stop the server
+    pub async fn connect(url: &str) -> AppResult<Self> {
+        let client = async_nats::ConnectOptions::new()
+            .retry_on_initial_connect()
+            .connect(url)
+            .await
+            .map_err(|e| AppError::ConfigError(format!("Invalid EVENT_BROKER_URL: {}", e)))?;
+
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for NatsPublisher {
+    async fn publish(&self, event: &TranscriptEvent) -> AppResult<()> {
+        let payload = serde_json::to_vec(event)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize event: {}", e)))?;
+
+        self.client
+            .publish(event.subject(), payload.into())
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to publish event: {}", e)))?;
+
+        Ok(())
+    }
+}
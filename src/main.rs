@@ -1,83 +1,323 @@
 use actix_cors::Cors;
-use actix_web::{App, HttpServer, middleware::Logger, web};
+use actix_web::middleware::{Compress, Condition};
+use actix_web::{App, HttpServer, web};
 use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
 use std::sync::Arc;
-use whisper_rs::{self, WhisperContextParameters};
 mod config;
 mod controllers;
 mod errors;
+mod events;
 mod middlewares;
 mod models;
+mod notifications;
 mod routes;
 mod services;
+mod storage;
 mod utils;
 
 use config::Config;
 use errors::AppError;
+use events::EventPublisher;
+use middlewares::{AccountStatusCache, ApiVersion, RateLimitStore, RequestTracing};
+use notifications::Notifier;
+use services::{
+    FfmpegLimiter, IdempotencyService, JobQueue, JobService, JobSlots, JobTracker,
+    ProcessingRate, ProgressChannels, TranscriptionService, UploadLocks, UploadService,
+    WebhookService, WhisperModels, WhisperStateLimiter,
+};
+use storage::Storage;
+use utils::jwt;
 
 /// Application state shared across all handlers
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
     pub config: Arc<Config>,
-    pub whisper_ctx: Arc<whisper_rs::WhisperContext>,
+    pub whisper_models: WhisperModels,
+    pub progress_channels: ProgressChannels,
+    pub rate_limits: RateLimitStore,
+    pub upload_rate_limits: RateLimitStore,
+    pub job_slots: JobSlots,
+    pub upload_locks: UploadLocks,
+    pub account_status: AccountStatusCache,
+    pub jobs: JobTracker,
+    pub job_queue: JobQueue,
+    pub ffmpeg: FfmpegLimiter,
+    pub whisper_states: WhisperStateLimiter,
+    pub processing_rate: ProcessingRate,
+    pub http_client: reqwest::Client,
+    pub storage: Arc<dyn Storage>,
+    pub events: Arc<dyn EventPublisher>,
+    pub notifier: Arc<dyn Notifier>,
+    pub jwt_keys: Arc<jwt::JwtKeys>,
+}
+
+/// Build the CORS layer from the configured origins
+fn build_cors(config: &Config) -> Cors {
+    if !config.allowed_origins.is_empty() {
+        // Explicit origins can receive the httpOnly refresh cookie
+        config
+            .allowed_origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+            .allow_any_method()
+            .allow_any_header()
+            .supports_credentials()
+            .max_age(3600)
+    } else if config.cors_permissive {
+        Cors::default()
+            .allow_any_origin()
+            .allow_any_method()
+            .allow_any_header()
+            .max_age(3600)
+    } else {
+        // Same-origin only
+        Cors::default()
+    }
+}
+
+/// Initialize logging from `LOG_LEVEL`/`LOG_FORMAT`; a set `RUST_LOG` takes precedence over the level
+fn init_logging(config: &Config) {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(config.log_filter()));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+
+    if config.log_format == "json" {
+        // Log lines carry the fields of their request/job spans
+        subscriber
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .init();
+    } else {
+        subscriber.init();
+    }
+}
+
+/// Longest pause between startup connection attempts
+const MAX_DB_CONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Connect to the database, retrying with exponential backoff up to `DB_CONNECT_ATTEMPTS` times
+async fn connect_database(config: &Config) -> Result<PgPool, AppError> {
+    let options = PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .min_connections(config.db_min_connections)
+        .acquire_timeout(std::time::Duration::from_secs(config.db_acquire_timeout_seconds))
+        .idle_timeout(std::time::Duration::from_secs(config.db_idle_timeout_seconds));
+    let mut backoff = std::time::Duration::from_secs(config.db_connect_retry_seconds);
+
+    let mut attempt = 1;
+    loop {
+        match options.clone().connect(&config.database_url).await {
+            Ok(db) => return Ok(db),
+            Err(e) if attempt < config.db_connect_attempts => {
+                log::warn!(
+                    "Database not reachable (attempt {} of {}): {}. Retrying in {}s",
+                    attempt,
+                    config.db_connect_attempts,
+                    e,
+                    backoff.as_secs()
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_DB_CONNECT_BACKOFF);
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(AppError::ConfigError(format!(
+                    "Could not connect to DATABASE_URL after {} attempts: {}",
+                    attempt, e
+                )));
+            }
+        }
+    }
+}
+
+/// Key of the Postgres advisory lock held while migrating ("ai_scrib" in ASCII)
+const MIGRATION_LOCK_KEY: i64 = 0x6169_5f73_6372_6962;
+
+/// Run pending migrations while holding an advisory lock, so instances starting at the same
+/// time migrate one after another instead of racing
+async fn run_migrations(db: &PgPool) -> Result<(), AppError> {
+    // Advisory locks belong to a session, so lock, migrate and unlock on one connection
+    let mut conn = db.acquire().await?;
+
+    let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .fetch_one(&mut *conn)
+        .await?;
+    if !locked {
+        log::info!("Waiting for another instance to finish running database migrations");
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(MIGRATION_LOCK_KEY)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let migrated = sqlx::migrate!("./src/migrations")
+        .run(&mut *conn)
+        .await
+        .map_err(|e| AppError::InternalError(format!("Database migration failed: {}", e)));
+
+    let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await;
+    if let Err(e) = unlocked {
+        // Closing the session releases the lock; don't hand it back to the pool still held
+        log::warn!("Failed to release the migration lock: {}", e);
+        drop(conn.detach());
+    }
+
+    migrated
 }
 
 #[actix_web::main]
 async fn main() -> Result<(), AppError> {
-    // Initialize logger
-    env_logger::init();
-
-    // Load configuration
+    // Load configuration (also reads .env, so RUST_LOG there is honoured below)
     let config = Arc::new(Config::from_env()?);
+    init_logging(&config);
     log::info!("Configuration loaded successfully");
 
-    // Connect to database
-    let db = PgPool::connect(&config.database_url).await?;
-    log::info!("Connected to PostgreSQL database");
+    // Connect to database, waiting for it to come up (e.g. when started alongside it)
+    let db = connect_database(&config).await?;
+    log::info!(
+        "Connected to PostgreSQL database (max connections: {}, min connections: {}, acquire timeout: {}s, idle timeout: {}s)",
+        config.db_max_connections,
+        config.db_min_connections,
+        config.db_acquire_timeout_seconds,
+        config.db_idle_timeout_seconds
+    );
 
-    // Run database migrations
-    sqlx::migrate!("./src/migrations").run(&db).await.unwrap();
+    // Run database migrations, one instance at a time
+    run_migrations(&db).await?;
     log::info!("Database migrations completed");
 
-    // Initialize Whisper model
-    log::info!("Loading Whisper model from: {}", config.whisper_model_path);
-    let whisper_ctx = Arc::new(
-        whisper_rs::WhisperContext::new_with_params(
-            &config.whisper_model_path,
-            WhisperContextParameters { use_gpu: false }, //I previously set this to true
-        )
-        .map_err(|e| AppError::WhisperError(format!("Failed to load Whisper model: {}", e)))?,
+    // Initialize Whisper models; with WHISPER_LAZY_LOAD only the default is loaded up front
+    let whisper_models = WhisperModels::load(&config).await?;
+    log::info!(
+        "{} of {} Whisper model(s) loaded, default {}",
+        whisper_models.loaded_count(),
+        config.whisper_models.len(),
+        whisper_models.default_model()
     );
-    log::info!("Whisper model loaded successfully");
+
+    // Bounded queue in front of the transcription workers
+    let (job_queue, job_receiver) = JobQueue::new(config.transcription_queue_size);
 
     // Create application state
     let app_state = AppState {
         db,
         config: config.clone(),
-        whisper_ctx,
+        whisper_models,
+        progress_channels: ProgressChannels::default(),
+        rate_limits: RateLimitStore::default(),
+        upload_rate_limits: RateLimitStore::default(),
+        job_slots: JobSlots::default(),
+        upload_locks: UploadLocks::default(),
+        account_status: AccountStatusCache::default(),
+        jobs: JobTracker::new(),
+        job_queue,
+        processing_rate: ProcessingRate::default(),
+        ffmpeg: FfmpegLimiter::new(config.max_ffmpeg_concurrency),
+        whisper_states: WhisperStateLimiter::new(
+            config
+                .whisper_max_states
+                .unwrap_or(config.transcription_workers.max(config.whisper_parallel_chunks)),
+        ),
+        http_client: WebhookService::build_client()?,
+        storage: storage::build_storage(&config).await?,
+        events: events::build_publisher(&config).await?,
+        notifier: notifications::build_notifier(&config),
+        jwt_keys: Arc::new(jwt::JwtKeys::from_config(&config)?),
     };
+    log::info!("Storing original audio with the {} backend", config.storage_backend);
+    log::info!("Signing tokens with {}", config.jwt_algorithm);
+
+    JobService::start_workers(&app_state, job_receiver);
+
+    // Re-queue transcription jobs left unfinished by a previous run
+    let requeued = JobService::requeue_interrupted(&app_state).await?;
+    if requeued > 0 {
+        log::info!("Re-queued {} unfinished transcription jobs", requeued);
+    }
+
+    // Periodically purge soft-deleted transcripts past their restore window
+    let purge_db = app_state.db.clone();
+    let purge_storage = app_state.storage.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(24 * 60 * 60));
+        loop {
+            interval.tick().await;
+            if let Err(e) = TranscriptionService::purge_expired(&purge_db, purge_storage.as_ref()).await {
+                log::error!("Failed to purge deleted transcripts: {}", e);
+            }
+            if let Err(e) = IdempotencyService::purge_expired(&purge_db).await {
+                log::error!("Failed to purge expired idempotency keys: {}", e);
+            }
+        }
+    });
+
+    // Periodically delete orphaned temp files (uploads and WAVs left by crashes or timeouts)
+    // and resumable uploads abandoned for as long
+    let cleanup_db = app_state.db.clone();
+    let cleanup_config = config.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            cleanup_config.temp_cleanup_interval_seconds,
+        ));
+        let max_age = std::time::Duration::from_secs(cleanup_config.temp_file_max_age_seconds);
+        loop {
+            interval.tick().await;
+            match UploadService::expire_stale(&cleanup_db, max_age).await {
+                Ok(0) => {}
+                Ok(expired) => log::info!("Expired {} abandoned resumable uploads", expired),
+                Err(e) => log::error!("Failed to expire resumable uploads: {}", e),
+            }
+            match TranscriptionService::cleanup_stale_temp_files(
+                &cleanup_db,
+                &cleanup_config.temp_dir,
+                max_age,
+            )
+            .await
+            {
+                Ok(0) => {}
+                Ok(removed) => log::info!("Reclaimed {} orphaned temporary files", removed),
+                Err(e) => log::error!("Failed to clean up temporary files: {}", e),
+            }
+        }
+    });
 
     let bind_address = format!("{}:{}", config.host, config.port);
     log::info!("Starting server at http://{}", bind_address);
 
-    // Start HTTP server
+    // Keep a handle to the state for draining jobs after the server stops
+    let shutdown_state = app_state.clone();
+
+    // Start HTTP server (stops gracefully on SIGINT/SIGTERM)
     HttpServer::new(move || {
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .max_age(3600);
+        let cors = build_cors(&config);
 
         App::new()
             .app_data(web::Data::new(app_state.clone()))
+            .wrap(ApiVersion) // Inside compression, so the envelope sees the plain body
             .wrap(cors)
-            .wrap(Logger::default())
+            // Streaming responses opt out by setting `Content-Encoding: identity`
+            .wrap(Condition::new(config.compression_enabled, Compress::default()))
+            .wrap(RequestTracing) // Outermost: correlation ID and access log for every request
             .configure(routes::configure_routes)
     })
     .bind(&bind_address)?
     .run()
     .await?;
 
+    log::info!("HTTP server stopped, draining transcription jobs");
+    JobService::drain(
+        &shutdown_state,
+        std::time::Duration::from_secs(shutdown_state.config.shutdown_timeout_seconds),
+    )
+    .await?;
+    log::info!("Shutdown complete");
+
     Ok(())
 }
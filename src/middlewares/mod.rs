@@ -1,18 +1,230 @@
 // middleware/mod.rs - JWT authentication middleware
 use crate::errors::{AppError, AppResult};
-use crate::models::Claims;
+use crate::models::{user_role, Claims};
+use crate::services::UserService;
 use crate::utils::jwt;
+use crate::utils::network::IpNetwork;
 use crate::AppState;
 use actix_web::{
+    body::{to_bytes, EitherBody, MessageBody},
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage,
+    http::header::{HeaderName, HeaderValue, CONTENT_DISPOSITION, CONTENT_TYPE},
+    Error, HttpMessage, HttpRequest,
 };
+use dashmap::DashMap;
 use futures_util::future::LocalBoxFuture;
+use tracing::Instrument;
 use std::{
+    collections::VecDeque,
     future::{ready, Ready},
+    net::{IpAddr, SocketAddr},
     rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
+/// Header carrying the request correlation ID
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Middleware that assigns each request a correlation ID, runs it inside a tracing span
+/// and logs its status and latency
+pub struct RequestTracing;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTracing
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RequestTracingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestTracingMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let started = Instant::now();
+
+        // Reuse the caller's ID so logs can be correlated across services
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| is_valid_request_id(id))
+            .map(str::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id,
+            method = %req.method(),
+            path = %req.path(),
+            user_id = tracing::field::Empty,
+        );
+        let http_req = req.request().clone();
+
+        Box::pin(
+            async move {
+                // Render errors here so their responses carry the ID too
+                let mut res = match service.call(req).await {
+                    Ok(res) => res.map_into_left_body(),
+                    Err(e) => ServiceResponse::new(http_req, e.error_response()).map_into_right_body(),
+                };
+
+                if let Ok(value) = HeaderValue::from_str(&request_id) {
+                    res.headers_mut()
+                        .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                }
+
+                tracing::info!(
+                    status = res.status().as_u16(),
+                    latency_ms = started.elapsed().as_millis() as u64,
+                    "request completed"
+                );
+                Ok(res)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Accept caller-supplied IDs only if they are short and printable
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 128 && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Header carrying the API version of every response
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// Version of the `/api` surface, reported in the header and the response envelope
+pub const API_VERSION: &str = "1";
+
+/// Middleware that sets `X-API-Version` on every response and, when `RESPONSE_ENVELOPE` is
+/// enabled, wraps successful JSON bodies under `/api/` as `{"data": ..., "meta": {...}}`
+/// Error bodies, streams and the OpenAI-compatible and health endpoints are left as they are.
+pub struct ApiVersion;
+
+impl<S, B> Transform<S, ServiceRequest> for ApiVersion
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = ApiVersionMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiVersionMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct ApiVersionMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiVersionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let envelope = req.path().starts_with("/api/")
+            && req
+                .app_data::<actix_web::web::Data<AppState>>()
+                .is_some_and(|state| state.config.response_envelope);
+        let http_req = req.request().clone();
+
+        Box::pin(async move {
+            // Errors raised by inner middleware are rendered here so they get the header too
+            let mut res = match service.call(req).await {
+                Ok(res) if envelope && is_json_success(&res) => wrap_in_envelope(res).await?,
+                Ok(res) => res.map_into_left_body(),
+                Err(e) => ServiceResponse::new(http_req, e.error_response()).map_into_right_body(),
+            };
+            res.headers_mut().insert(
+                HeaderName::from_static(API_VERSION_HEADER),
+                HeaderValue::from_static(API_VERSION),
+            );
+            Ok(res)
+        })
+    }
+}
+
+/// Whether a response is a successful JSON body that the envelope applies to
+/// Downloads (anything with `Content-Disposition`) are left as-is, even JSON exports.
+fn is_json_success<B>(res: &ServiceResponse<B>) -> bool {
+    res.status().is_success()
+        && !res.headers().contains_key(CONTENT_DISPOSITION)
+        && res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("application/json"))
+}
+
+/// Move a JSON response body under `data`, next to the `meta` block
+async fn wrap_in_envelope<B: MessageBody>(
+    res: ServiceResponse<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let (http_req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = to_bytes(body).await.map_err(|e| {
+        let e: Box<dyn std::error::Error> = e.into();
+        actix_web::error::ErrorInternalServerError(e.to_string())
+    })?;
+
+    // A body that isn't valid JSON after all is passed through untouched
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(data) => serde_json::json!({
+            "data": data,
+            "meta": { "api_version": API_VERSION },
+        })
+        .to_string()
+        .into(),
+        Err(_) => bytes,
+    };
+
+    let res = res.set_body(body).map_into_boxed_body();
+    Ok(ServiceResponse::new(http_req, res).map_into_right_body())
+}
+
 /// JWT Authentication middleware
 pub struct JwtAuth;
 
@@ -69,13 +281,29 @@ where
 
             // Extract and verify token
             let token = jwt::extract_token_from_header(auth_header)?;
-            let claims = jwt::verify_token(token, &app_state.config.jwt_secret)?;
+            let claims = jwt::verify_token(token, &app_state.jwt_keys)?;
 
             // Validate token type (should be access token for protected routes)
             if claims.token_type != "access" {
                 return Err(AppError::AuthError("Invalid token type".to_string()).into());
             }
 
+            // Still-valid tokens of disabled or deleted accounts are refused
+            let user_id: uuid::Uuid = claims
+                .sub
+                .parse()
+                .map_err(|_| AppError::AuthError("Invalid user ID in token".to_string()))?;
+            if !app_state
+                .account_status
+                .is_active(&app_state.db, user_id)
+                .await?
+            {
+                return Err(AppError::Forbidden.into());
+            }
+
+            // Tag the request's log lines with the authenticated user
+            tracing::Span::current().record("user_id", tracing::field::display(&claims.sub));
+
             // Add claims to request extensions for use in handlers
             req.extensions_mut().insert(claims);
 
@@ -86,6 +314,375 @@ where
     }
 }
 
+/// Middleware that only lets users with a verified email through
+/// Must be applied inside a scope protected by `JwtAuth`
+pub struct RequireVerifiedEmail;
+
+impl<S, B> Transform<S, ServiceRequest> for RequireVerifiedEmail
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequireVerifiedEmailMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireVerifiedEmailMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequireVerifiedEmailMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireVerifiedEmailMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            // Extract app state
+            let app_state = req
+                .app_data::<actix_web::web::Data<AppState>>()
+                .ok_or_else(|| AppError::InternalError("App state not found".to_string()))?
+                .clone();
+
+            let user_id: uuid::Uuid = req
+                .extensions()
+                .get::<Claims>()
+                .ok_or_else(|| AppError::AuthError("User claims not found in request".to_string()))?
+                .sub
+                .parse()
+                .map_err(|_| AppError::AuthError("Invalid user ID in token".to_string()))?;
+
+            if !UserService::is_email_verified(&app_state.db, user_id).await? {
+                return Err(AppError::Forbidden.into());
+            }
+
+            // Continue with the request
+            let res = service.call(req).await?;
+            Ok(res)
+        })
+    }
+}
+
+/// Middleware that only lets users holding a given role through
+/// Must be applied inside a scope protected by `JwtAuth`
+pub struct RequireRole {
+    role: &'static str,
+}
+
+impl RequireRole {
+    /// Require the given role; administrators satisfy every role
+    pub fn new(role: &'static str) -> Self {
+        Self { role }
+    }
+
+    /// Require the administrator role
+    pub fn admin() -> Self {
+        Self::new(user_role::ADMIN)
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequireRoleMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireRoleMiddleware {
+            service: Rc::new(service),
+            role: self.role,
+        }))
+    }
+}
+
+pub struct RequireRoleMiddleware<S> {
+    service: Rc<S>,
+    role: &'static str,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireRoleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let required_role = self.role;
+
+        Box::pin(async move {
+            let role = req
+                .extensions()
+                .get::<Claims>()
+                .map(|claims| claims.role.clone())
+                .ok_or_else(|| AppError::AuthError("User claims not found in request".to_string()))?;
+
+            if role != required_role && role != user_role::ADMIN {
+                return Err(AppError::Forbidden.into());
+            }
+
+            // Continue with the request
+            let res = service.call(req).await?;
+            Ok(res)
+        })
+    }
+}
+
+/// Request timestamps per rate limiting key (client IP or user ID), shared across workers
+pub type RateLimitStore = Arc<DashMap<String, VecDeque<Instant>>>;
+
+/// Length of the rate limiting sliding window
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Number of tracked clients above which idle entries are swept
+const RATE_LIMIT_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Per-IP sliding window rate limiting middleware
+pub struct RateLimit;
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            // Extract app state
+            let app_state = req
+                .app_data::<actix_web::web::Data<AppState>>()
+                .ok_or_else(|| AppError::InternalError("App state not found".to_string()))?;
+
+            let client_ip = client_ip(req.request());
+            check_rate_limit(
+                &app_state.rate_limits,
+                &client_ip,
+                app_state.config.rate_limit_per_minute,
+            )?;
+
+            // Continue with the request
+            let res = service.call(req).await?;
+            Ok(res)
+        })
+    }
+}
+
+/// How long an account's active/disabled state is trusted before it is checked again
+const ACCOUNT_STATUS_TTL: Duration = Duration::from_secs(30);
+
+/// Number of cached accounts above which stale entries are swept
+const ACCOUNT_STATUS_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Recently checked account states, so `JwtAuth` doesn't query the database on every request
+/// Changes made through this process are applied at once via `invalidate`; others within the TTL
+#[derive(Clone, Default)]
+pub struct AccountStatusCache {
+    entries: Arc<DashMap<uuid::Uuid, (bool, Instant)>>,
+}
+
+impl AccountStatusCache {
+    /// Whether the account exists and is not disabled
+    pub async fn is_active(&self, pool: &sqlx::PgPool, user_id: uuid::Uuid) -> AppResult<bool> {
+        let now = Instant::now();
+
+        if let Some(entry) = self.entries.get(&user_id) {
+            let (active, checked_at) = *entry;
+            if now.duration_since(checked_at) < ACCOUNT_STATUS_TTL {
+                return Ok(active);
+            }
+        }
+
+        if self.entries.len() > ACCOUNT_STATUS_SWEEP_THRESHOLD {
+            self.entries
+                .retain(|_, (_, checked_at)| now.duration_since(*checked_at) < ACCOUNT_STATUS_TTL);
+        }
+
+        let active = UserService::is_active(pool, user_id).await?;
+        self.entries.insert(user_id, (active, now));
+        Ok(active)
+    }
+
+    /// Forget a cached state after the account was disabled, enabled or deleted
+    pub fn invalidate(&self, user_id: uuid::Uuid) {
+        self.entries.remove(&user_id);
+    }
+}
+
+/// Resolve the client IP
+/// `Forwarded`/`X-Forwarded-For` are only honoured when the peer is one of `TRUSTED_PROXIES`,
+/// since anyone connecting directly could otherwise pick their own address.
+pub fn client_ip(req: &HttpRequest) -> String {
+    let trusted_proxies = req
+        .app_data::<actix_web::web::Data<AppState>>()
+        .map(|state| state.config.trusted_proxies.as_slice())
+        .unwrap_or_default();
+
+    resolve_client_ip(req.peer_addr().map(|peer| peer.ip()), &forwarded_for(req), trusted_proxies)
+}
+
+/// Addresses from `Forwarded` (or `X-Forwarded-For` without it), nearest client first
+fn forwarded_for(req: &HttpRequest) -> Vec<String> {
+    let headers = req.headers();
+    let values = |name: &str| -> Vec<String> {
+        headers
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|entry| entry.trim().to_string())
+            .collect()
+    };
+
+    let forwarded = values("forwarded");
+    if forwarded.is_empty() {
+        return values("x-forwarded-for");
+    }
+
+    forwarded
+        .iter()
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.trim().split_once('=')?;
+                key.trim().eq_ignore_ascii_case("for").then(|| value.trim().trim_matches('"').to_string())
+            })
+        })
+        .collect()
+}
+
+/// Parse a forwarded node such as `192.0.2.1`, `192.0.2.1:4711` or `[2001:db8::1]:4711`
+fn parse_forwarded_ip(node: &str) -> Option<IpAddr> {
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+
+    node.parse()
+        .ok()
+        .or_else(|| node.parse::<SocketAddr>().ok().map(|socket| socket.ip()))
+}
+
+/// Walk the forwarded chain back from the peer, stopping at the first hop that isn't a trusted proxy
+fn resolve_client_ip(peer: Option<IpAddr>, forwarded: &[String], trusted_proxies: &[IpNetwork]) -> String {
+    let is_trusted = |ip: IpAddr| trusted_proxies.iter().any(|proxy| proxy.contains(ip));
+
+    let Some(peer) = peer else {
+        return "unknown".to_string();
+    };
+    if !is_trusted(peer) {
+        return peer.to_string();
+    }
+
+    let mut client = peer.to_string();
+    for node in forwarded.iter().rev().filter(|node| !node.is_empty()) {
+        match parse_forwarded_ip(node) {
+            Some(ip) => {
+                client = ip.to_string();
+                if !is_trusted(ip) {
+                    break;
+                }
+            }
+            // Obfuscated identifiers such as `unknown` or `_hidden` are taken as given
+            None => return node.to_string(),
+        }
+    }
+
+    client
+}
+
+/// Record a request under a key and fail with `RateLimited` if the window is full
+pub fn check_rate_limit(store: &RateLimitStore, key: &str, limit: usize) -> AppResult<()> {
+    let now = Instant::now();
+
+    if store.len() > RATE_LIMIT_SWEEP_THRESHOLD {
+        store.retain(|_, requests| {
+            requests
+                .back()
+                .is_some_and(|last| now.duration_since(*last) < RATE_LIMIT_WINDOW)
+        });
+    }
+
+    let mut requests = store.entry(key.to_string()).or_default();
+    while requests
+        .front()
+        .is_some_and(|first| now.duration_since(*first) >= RATE_LIMIT_WINDOW)
+    {
+        requests.pop_front();
+    }
+
+    if requests.len() >= limit {
+        let retry_after = requests
+            .front()
+            .map(|first| RATE_LIMIT_WINDOW.saturating_sub(now.duration_since(*first)))
+            .unwrap_or(RATE_LIMIT_WINDOW);
+
+        log::warn!("Rate limit exceeded for: {}", key);
+        return Err(AppError::RateLimited(retry_after.as_secs().max(1)));
+    }
+
+    requests.push_back(now);
+    Ok(())
+}
+
 /// Extract user claims from request extensions
 /// This function should be called from protected route handlers
 pub fn extract_claims(req: &actix_web::HttpRequest) -> AppResult<Claims> {
@@ -102,4 +699,86 @@ pub fn extract_user_id(req: &actix_web::HttpRequest) -> AppResult<uuid::Uuid> {
         .sub
         .parse()
         .map_err(|_| AppError::AuthError("Invalid user ID in token".to_string()))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test::TestRequest, HttpResponse};
+
+    fn json_response(response: HttpResponse) -> ServiceResponse {
+        TestRequest::default().to_srv_response(response)
+    }
+
+    #[test]
+    fn envelope_applies_to_successful_json() {
+        assert!(is_json_success(&json_response(
+            HttpResponse::Ok().content_type("application/json").finish()
+        )));
+        assert!(!is_json_success(&json_response(
+            HttpResponse::NotFound().content_type("application/json").finish()
+        )));
+        assert!(!is_json_success(&json_response(HttpResponse::Ok().content_type("text/plain").finish())));
+    }
+
+    #[test]
+    fn envelope_skips_downloads() {
+        let response = HttpResponse::Ok()
+            .content_type("application/json")
+            .insert_header((CONTENT_DISPOSITION, r#"attachment; filename="transcript.json""#))
+            .finish();
+
+        assert!(!is_json_success(&json_response(response)));
+    }
+
+    fn proxies(values: &[&str]) -> Vec<IpNetwork> {
+        values.iter().map(|value| IpNetwork::parse(value).unwrap()).collect()
+    }
+
+    fn resolve(req: TestRequest, trusted_proxies: &[&str]) -> String {
+        let req = req.to_http_request();
+        resolve_client_ip(req.peer_addr().map(|peer| peer.ip()), &forwarded_for(&req), &proxies(trusted_proxies))
+    }
+
+    fn from_peer(peer: &str) -> TestRequest {
+        TestRequest::default().peer_addr(peer.parse().unwrap())
+    }
+
+    #[test]
+    fn forwarding_headers_are_ignored_from_untrusted_peers() {
+        let req = from_peer("203.0.113.9:5000")
+            .insert_header(("X-Forwarded-For", "198.51.100.1"))
+            .insert_header(("Forwarded", "for=198.51.100.2"));
+
+        assert_eq!(resolve(req, &[]), "203.0.113.9");
+    }
+
+    #[test]
+    fn trusted_proxy_forwards_the_client_address() {
+        let req = from_peer("10.0.0.2:5000").insert_header(("X-Forwarded-For", "198.51.100.1"));
+
+        assert_eq!(resolve(req, &["10.0.0.0/8"]), "198.51.100.1");
+    }
+
+    #[test]
+    fn spoofed_entries_left_of_an_untrusted_hop_are_skipped() {
+        let req = from_peer("10.0.0.2:5000")
+            .insert_header(("X-Forwarded-For", "1.2.3.4, 198.51.100.1, 10.0.0.3"));
+
+        assert_eq!(resolve(req, &["10.0.0.0/8"]), "198.51.100.1");
+    }
+
+    #[test]
+    fn forwarded_header_takes_precedence() {
+        let req = from_peer("10.0.0.2:5000")
+            .insert_header(("X-Forwarded-For", "198.51.100.1"))
+            .insert_header(("Forwarded", r#"for="[2001:db8::1]:4711";proto=https, for=10.0.0.3"#));
+
+        assert_eq!(resolve(req, &["10.0.0.0/8"]), "2001:db8::1");
+    }
+
+    #[test]
+    fn peer_is_used_without_forwarding_headers() {
+        assert_eq!(resolve(from_peer("10.0.0.2:5000"), &["10.0.0.0/8"]), "10.0.0.2");
+        assert_eq!(resolve(TestRequest::default(), &[]), "unknown");
+    }
+}
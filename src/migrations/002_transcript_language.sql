@@ -0,0 +1,2 @@
+-- Record the language used (or detected) for each transcription
+ALTER TABLE transcripts ADD COLUMN language VARCHAR(8);
@@ -0,0 +1,12 @@
+-- Timestamped segments produced by Whisper for each transcript
+CREATE TABLE transcript_segments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4 (),
+    transcript_id UUID NOT NULL REFERENCES transcripts (id) ON DELETE CASCADE,
+    segment_index INTEGER NOT NULL,
+    start_ms BIGINT NOT NULL,
+    end_ms BIGINT NOT NULL,
+    text TEXT NOT NULL,
+    UNIQUE (transcript_id, segment_index)
+);
+
+CREATE INDEX idx_transcript_segments_transcript_id ON transcript_segments (transcript_id, segment_index);
@@ -0,0 +1,11 @@
+-- Track background transcription job state
+ALTER TABLE transcripts ADD COLUMN status VARCHAR(20) NOT NULL DEFAULT 'completed';
+
+ALTER TABLE transcripts ADD COLUMN error_message TEXT;
+
+-- Uploaded audio kept on disk until the job finishes, so it can be re-queued after a restart
+ALTER TABLE transcripts ADD COLUMN audio_path TEXT;
+
+CREATE INDEX idx_transcripts_active_status ON transcripts (status)
+WHERE
+    status IN ('pending', 'processing');
@@ -0,0 +1,13 @@
+-- Single-use password reset tokens (only the SHA-256 hash is stored)
+CREATE TABLE password_resets (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4 (),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_password_resets_user_id ON password_resets (user_id);
+
+-- Refresh tokens issued before the last password reset are rejected
+ALTER TABLE users ADD COLUMN password_changed_at TIMESTAMP WITH TIME ZONE;
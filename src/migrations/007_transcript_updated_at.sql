@@ -0,0 +1,2 @@
+-- Track when a transcript was last edited
+ALTER TABLE transcripts ADD COLUMN updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
@@ -0,0 +1,6 @@
+-- Soft-delete transcripts so they can be restored for a limited time
+ALTER TABLE transcripts ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_transcripts_deleted_at ON transcripts (deleted_at)
+WHERE
+    deleted_at IS NOT NULL;
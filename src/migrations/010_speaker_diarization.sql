@@ -0,0 +1,4 @@
+-- Optional speaker diarization of transcripts
+ALTER TABLE transcripts ADD COLUMN diarize BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE transcript_segments ADD COLUMN speaker VARCHAR(64);
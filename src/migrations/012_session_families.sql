@@ -0,0 +1,8 @@
+-- Group rotated refresh tokens so a replayed token can revoke the whole chain
+ALTER TABLE sessions ADD COLUMN family_id VARCHAR(64);
+
+UPDATE sessions SET family_id = id;
+
+ALTER TABLE sessions ALTER COLUMN family_id SET NOT NULL;
+
+CREATE INDEX idx_sessions_family_id ON sessions (family_id);
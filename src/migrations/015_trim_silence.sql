@@ -0,0 +1,2 @@
+-- Optional removal of long silences before transcription
+ALTER TABLE transcripts ADD COLUMN trim_silence BOOLEAN NOT NULL DEFAULT FALSE;
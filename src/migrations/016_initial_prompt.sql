@@ -0,0 +1,2 @@
+-- Prompt used to prime Whisper with domain vocabulary
+ALTER TABLE transcripts ADD COLUMN initial_prompt TEXT;
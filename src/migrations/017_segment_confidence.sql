@@ -0,0 +1,2 @@
+-- Average token probability of each segment, used to flag parts for review
+ALTER TABLE transcript_segments ADD COLUMN confidence REAL;
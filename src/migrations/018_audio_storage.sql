@@ -0,0 +1,2 @@
+-- Key of the original upload in object storage
+ALTER TABLE transcripts ADD COLUMN storage_key TEXT;
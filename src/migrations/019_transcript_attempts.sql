@@ -0,0 +1,2 @@
+-- Number of times a transcription job has been run, capping manual retries
+ALTER TABLE transcripts ADD COLUMN attempt_count INTEGER NOT NULL DEFAULT 1;
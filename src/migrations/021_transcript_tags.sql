@@ -0,0 +1,4 @@
+-- Free-form labels for organizing transcripts
+ALTER TABLE transcripts ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_transcripts_tags ON transcripts USING GIN (tags);
@@ -0,0 +1,3 @@
+-- Whisper model that produced a transcript and the language it detected (NULL when one was requested)
+ALTER TABLE transcripts ADD COLUMN model_name TEXT;
+ALTER TABLE transcripts ADD COLUMN detected_language TEXT;
@@ -0,0 +1,2 @@
+-- How segment texts are joined into the full transcription
+ALTER TABLE transcripts ADD COLUMN text_format TEXT NOT NULL DEFAULT 'plain';
@@ -0,0 +1,2 @@
+-- Emails are compared case-insensitively, so `User@x.com` and `user@x.com` are one account
+CREATE UNIQUE INDEX idx_users_email_lower ON users (LOWER(email));
@@ -0,0 +1,2 @@
+-- Accounts disabled by an administrator can no longer sign in or use their tokens
+ALTER TABLE users ADD COLUMN disabled_at TIMESTAMPTZ;
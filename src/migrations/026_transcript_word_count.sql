@@ -0,0 +1,6 @@
+-- Word count stored at save time so listings can be sorted by it
+ALTER TABLE transcripts ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0;
+
+UPDATE transcripts
+SET word_count = array_length(regexp_split_to_array(btrim(transcription), '\s+'), 1)
+WHERE btrim(transcription) <> '';
@@ -0,0 +1,3 @@
+-- Opt-in word-level timestamps, stored per segment
+ALTER TABLE transcripts ADD COLUMN word_timestamps BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE transcript_segments ADD COLUMN words JSONB NOT NULL DEFAULT '[]';
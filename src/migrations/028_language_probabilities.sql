@@ -0,0 +1,2 @@
+-- Most likely spoken languages with Whisper's probabilities, for auto-detected transcripts
+ALTER TABLE transcripts ADD COLUMN language_probabilities JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,2 @@
+-- Per-user key signing transcript share links; replacing it revokes every link
+ALTER TABLE users ADD COLUMN share_secret TEXT;
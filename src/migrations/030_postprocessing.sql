@@ -0,0 +1,3 @@
+-- Optional post-processing passes applied to a transcript's text
+ALTER TABLE transcripts ADD COLUMN capitalize_sentences BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE transcripts ADD COLUMN numbers_to_digits BOOLEAN NOT NULL DEFAULT FALSE;
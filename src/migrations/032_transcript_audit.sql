@@ -0,0 +1,3 @@
+-- Where an upload came from, for abuse investigation (shown to administrators only)
+ALTER TABLE transcripts ADD COLUMN source_ip TEXT;
+ALTER TABLE transcripts ADD COLUMN user_agent TEXT;
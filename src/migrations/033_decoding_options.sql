@@ -0,0 +1,3 @@
+-- Whisper sampling temperature and fallback step each transcript was decoded with
+ALTER TABLE transcripts ADD COLUMN temperature REAL NOT NULL DEFAULT 0;
+ALTER TABLE transcripts ADD COLUMN temperature_inc REAL NOT NULL DEFAULT 0.2;
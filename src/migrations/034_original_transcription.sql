@@ -0,0 +1,5 @@
+-- Whisper's own output, kept unchanged when users correct the transcription
+ALTER TABLE transcripts ADD COLUMN original_transcription TEXT;
+
+-- Earlier corrections weren't tracked, so completed transcripts start from their current text
+UPDATE transcripts SET original_transcription = transcription WHERE status = 'completed';
@@ -0,0 +1,2 @@
+-- FFmpeg audio filters applied while converting each upload for Whisper
+ALTER TABLE transcripts ADD COLUMN audio_filters TEXT[] NOT NULL DEFAULT '{}';
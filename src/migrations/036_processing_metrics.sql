@@ -0,0 +1,4 @@
+-- How long each completed transcription took and with how many Whisper threads
+ALTER TABLE transcripts ADD COLUMN transcription_seconds DOUBLE PRECISION;
+ALTER TABLE transcripts ADD COLUMN processing_seconds DOUBLE PRECISION;
+ALTER TABLE transcripts ADD COLUMN whisper_threads INTEGER;
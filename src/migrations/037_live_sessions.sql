@@ -0,0 +1,9 @@
+-- Audio received by each live transcription stream, counted towards the monthly quota
+CREATE TABLE live_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4 (),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    duration_seconds DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_live_sessions_user_id ON live_sessions (user_id, created_at);
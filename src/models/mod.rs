@@ -1,9 +1,10 @@
 // models/mod.rs - Database models and structures
+use crate::services::MAX_SHARE_LINK_HOURS;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 /// User model representing a registered user in the system
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -11,8 +12,56 @@ pub struct User {
     pub id: Uuid,
     pub email: String,
     pub password_hash: String,
+    pub email_verified: bool,
+    pub role: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+/// Roles a user can hold
+pub mod user_role {
+    pub const USER: &str = "user";
+    pub const ADMIN: &str = "admin";
+}
+
+/// Callback URL notified when a user's transcription jobs finish
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Resumable upload being assembled in the temp directory
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Upload {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub filename: String,
+    pub path: String,
+    pub total_size: i64,
+    /// Bytes received so far; the next part must start here
+    pub received_bytes: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Login session backed by a single refresh token
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub family_id: String,
+    pub user_id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
 }
 
 /// Transcript model representing a transcription result
@@ -24,10 +73,115 @@ pub struct Transcript {
     pub transcription: String,
     pub file_size: i64,
     pub duration_seconds: Option<f64>,
+    pub language: Option<String>,
+    pub status: String,
+    pub error_message: Option<String>,
+    pub audio_path: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub diarize: bool,
+    pub trim_silence: bool,
+    pub initial_prompt: Option<String>,
+    pub storage_key: Option<String>,
+    pub attempt_count: i32,
+    pub tags: Vec<String>,
+    pub model_name: Option<String>,
+    pub detected_language: Option<String>,
+    pub text_format: String,
+    pub word_count: i32,
+    pub word_timestamps: bool,
+    pub capitalize_sentences: bool,
+    pub numbers_to_digits: bool,
+    pub temperature: f32,
+    pub temperature_inc: f32,
+    pub audio_filters: Vec<String>,
+    /// Transcription as Whisper produced it, before any corrections (unset until completed)
+    pub original_transcription: Option<String>,
+    /// Most likely languages when the language was auto-detected, most likely first
+    #[sqlx(json)]
+    pub language_probabilities: Vec<LanguageProbability>,
+    /// Processing metrics of the completed job; only shown to administrators
+    #[serde(skip_serializing)]
+    pub transcription_seconds: Option<f64>,
+    #[serde(skip_serializing)]
+    pub processing_seconds: Option<f64>,
+    #[serde(skip_serializing)]
+    pub whisper_threads: Option<i32>,
+    /// Client address and User-Agent of the upload; only shown to administrators
+    #[serde(skip_serializing)]
+    pub source_ip: Option<String>,
+    #[serde(skip_serializing)]
+    pub user_agent: Option<String>,
 }
 
-/// Request models for API endpoints
+/// Transcript job status values stored in `transcripts.status`
+pub mod transcript_status {
+    pub const PENDING: &str = "pending";
+    pub const PROCESSING: &str = "processing";
+    pub const COMPLETED: &str = "completed";
+    pub const FAILED: &str = "failed";
+}
+
+/// Ways segment texts are joined into `transcripts.transcription`
+pub mod text_format {
+    /// Segments joined by single spaces, with runs of whitespace collapsed
+    pub const PLAIN: &str = "plain";
+    /// One segment per line, with runs of whitespace collapsed
+    pub const LINES: &str = "lines";
+    /// Segments concatenated exactly as Whisper produced them, leading spaces included
+    pub const VERBATIM: &str = "verbatim";
+
+    /// Every accepted format
+    pub const ALL: &[&str] = &[PLAIN, LINES, VERBATIM];
+}
+
+/// FFmpeg audio filters an upload can apply before transcription, stored in
+/// `transcripts.audio_filters`
+pub mod audio_filter {
+    /// Cut rumble and hum below 80 Hz
+    pub const HIGHPASS: &str = "highpass";
+    /// Cut hiss above 7.5 kHz, beyond the speech range Whisper's 16 kHz input keeps anyway
+    pub const LOWPASS: &str = "lowpass";
+    /// Reduce steady background noise
+    pub const DENOISE: &str = "denoise";
+    /// Normalize loudness (EBU R128), which helps quiet recordings
+    pub const LOUDNORM: &str = "loudnorm";
+
+    /// Every accepted filter, in the order they are applied
+    pub const ALL: &[&str] = &[HIGHPASS, LOWPASS, DENOISE, LOUDNORM];
+}
+
+/// Timestamped segment of a transcription
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub speaker: Option<String>,
+    pub confidence: Option<f32>,
+    /// Word timings, only filled in for transcripts uploaded with `word_timestamps`
+    #[sqlx(json)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<WordTimestamp>,
+}
+
+/// A candidate spoken language and how likely Whisper found it (0-1)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageProbability {
+    pub language: String,
+    pub probability: f32,
+}
+
+/// Timestamped word within a segment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordTimestamp {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+}
+
+// Request models for API endpoints
 
 /// User registration request
 #[derive(Debug, Deserialize, Validate)]
@@ -49,13 +203,112 @@ pub struct LoginRequest {
     pub password: String,
 }
 
-/// Token refresh request
+/// Forgot password request
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+/// Request a new email verification token
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResendVerificationRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+/// Reset password request
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1, message = "Reset token is required"))]
+    pub token: String,
+
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub new_password: String,
+}
+
+/// Account deletion request (the password is re-confirmed)
+#[derive(Debug, Deserialize, Validate)]
+pub struct DeleteAccountRequest {
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+}
+
+/// Transcript update request (only provided fields are changed)
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateTranscriptRequest {
+    #[validate(length(min = 1, message = "Transcription cannot be empty"))]
+    pub transcription: Option<String>,
+
+    #[validate(length(min = 1, max = 255, message = "Filename must be between 1 and 255 characters"))]
+    pub filename: Option<String>,
+}
+
+/// Tags to add to a transcript
+#[derive(Debug, Deserialize, Validate)]
+pub struct TagsRequest {
+    #[validate(length(min = 1, message = "At least one tag is required"))]
+    pub tags: Vec<String>,
+}
+
+/// Transcripts to delete in one request
+#[derive(Debug, Deserialize, Validate)]
+pub struct BulkDeleteRequest {
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 ids are required"))]
+    pub ids: Vec<Uuid>,
+}
+
+/// Webhook registration request
+#[derive(Debug, Deserialize, Validate)]
+pub struct WebhookRequest {
+    #[validate(url(message = "Invalid webhook URL"))]
+    pub url: String,
+}
+
+/// Share link request; the format defaults to `txt` and the lifetime to `SHARE_LINK_TTL_HOURS`
+#[derive(Debug, Deserialize, Validate)]
+pub struct ShareTranscriptRequest {
+    pub format: Option<String>,
+
+    #[validate(custom = "validate_share_link_hours")]
+    pub expires_in_hours: Option<i64>,
+}
+
+fn validate_share_link_hours(hours: i64) -> Result<(), ValidationError> {
+    if (1..=MAX_SHARE_LINK_HOURS).contains(&hours) {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("range");
+    error.message = Some(
+        format!("expires_in_hours must be between 1 and {}", MAX_SHARE_LINK_HOURS).into(),
+    );
+    Err(error)
+}
+
+/// Resumable upload request, declaring the file's name and total size in bytes
+#[derive(Debug, Deserialize, Validate)]
+pub struct InitiateUploadRequest {
+    #[validate(length(min = 1, max = 255, message = "filename must be between 1 and 255 characters"))]
+    pub filename: String,
+
+    #[validate(range(min = 1, message = "size must be at least 1 byte"))]
+    pub size: i64,
+}
+
+/// Email verification query parameters
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    pub token: String,
+}
+
+// Token refresh request
 // #[derive(Debug, Deserialize)]
 // pub struct RefreshTokenRequest {
 //     pub refresh_token: String,
 // }
 
-/// Response models for API endpoints
+// Response models for API endpoints
 
 /// Authentication response containing tokens
 #[derive(Debug, Serialize)]
@@ -67,19 +320,117 @@ pub struct AuthResponse {
     pub user: UserResponse,
 }
 
+/// Registration response (tokens are only issued once the email is verified and the user logs in)
+#[derive(Debug, Serialize)]
+pub struct RegisterResponse {
+    pub message: String,
+    pub verification_pending: bool,
+    pub user: UserResponse,
+}
+
 /// User response (without sensitive data)
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
+    pub email_verified: bool,
+    pub role: String,
     pub created_at: DateTime<Utc>,
 }
 
+/// Transcription usage for the current billing period
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub minutes_used: f64,
+    pub quota_minutes: Option<f64>,
+    pub minutes_remaining: Option<f64>,
+    pub queue: QueueStatus,
+}
+
+/// Transcription queue depth and the estimated wait for a worker
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStatus {
+    /// Jobs waiting for a worker
+    pub queue_depth: usize,
+    /// Jobs being transcribed
+    pub running_jobs: usize,
+    /// Unknown until a job has completed since the server started
+    pub estimated_wait_seconds: Option<u64>,
+}
+
+/// Aggregate totals over a user's transcripts (one row from `get_user_stats`)
+#[derive(Debug, Clone, FromRow)]
+pub struct UserStats {
+    pub total_transcripts: i64,
+    pub total_audio_seconds: f64,
+    pub total_words: i64,
+    pub average_words: f64,
+    pub average_duration_seconds: f64,
+    pub pending: i64,
+    pub processing: i64,
+    pub completed: i64,
+    pub failed: i64,
+}
+
+/// Transcript counts by job status
+#[derive(Debug, Serialize)]
+pub struct StatusCounts {
+    pub pending: i64,
+    pub processing: i64,
+    pub completed: i64,
+    pub failed: i64,
+}
+
+/// Dashboard summary of a user's transcripts
+#[derive(Debug, Serialize)]
+pub struct UserStatsResponse {
+    pub total_transcripts: i64,
+    pub total_audio_minutes: f64,
+    pub total_words: i64,
+    pub average_words_per_transcript: f64,
+    pub average_duration_seconds: f64,
+    pub by_status: StatusCounts,
+}
+
+impl From<UserStats> for UserStatsResponse {
+    fn from(stats: UserStats) -> Self {
+        Self {
+            total_transcripts: stats.total_transcripts,
+            total_audio_minutes: stats.total_audio_seconds / 60.0,
+            total_words: stats.total_words,
+            average_words_per_transcript: stats.average_words,
+            average_duration_seconds: stats.average_duration_seconds,
+            by_status: StatusCounts {
+                pending: stats.pending,
+                processing: stats.processing,
+                completed: stats.completed,
+                failed: stats.failed,
+            },
+        }
+    }
+}
+
+/// Active session response
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub current: bool,
+}
+
 impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         Self {
             id: user.id,
             email: user.email,
+            email_verified: user.email_verified,
+            role: user.role,
             created_at: user.created_at,
         }
     }
@@ -93,18 +444,112 @@ pub struct TranscriptResponse {
     pub transcription: String,
     pub file_size: i64,
     pub duration_seconds: Option<f64>,
+    pub language: Option<String>,
+    pub status: String,
+    pub error_message: Option<String>,
+    pub initial_prompt: Option<String>,
+    pub attempt_count: i32,
+    pub tags: Vec<String>,
+    pub model_name: Option<String>,
+    pub detected_language: Option<String>,
+    pub language_probabilities: Vec<LanguageProbability>,
+    pub text_format: String,
+    pub word_timestamps: bool,
+    pub capitalize_sentences: bool,
+    pub numbers_to_digits: bool,
+    pub temperature: f32,
+    pub temperature_inc: f32,
+    pub audio_filters: Vec<String>,
+    pub word_count: i32,
+    pub estimated_reading_time_seconds: i64,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
-impl From<Transcript> for TranscriptResponse {
-    fn from(transcript: Transcript) -> Self {
+impl TranscriptResponse {
+    /// Build the response, estimating reading time at `reading_wpm` words per minute
+    pub fn new(transcript: Transcript, reading_wpm: u32) -> Self {
+        let reading_seconds =
+            (transcript.word_count.max(0) as f64 * 60.0 / reading_wpm.max(1) as f64).ceil() as i64;
+
         Self {
             id: transcript.id,
             filename: transcript.filename,
             transcription: transcript.transcription,
             file_size: transcript.file_size,
             duration_seconds: transcript.duration_seconds,
+            language: transcript.language,
+            status: transcript.status,
+            error_message: transcript.error_message,
+            initial_prompt: transcript.initial_prompt,
+            attempt_count: transcript.attempt_count,
+            tags: transcript.tags,
+            model_name: transcript.model_name,
+            detected_language: transcript.detected_language,
+            language_probabilities: transcript.language_probabilities,
+            text_format: transcript.text_format,
+            word_timestamps: transcript.word_timestamps,
+            capitalize_sentences: transcript.capitalize_sentences,
+            numbers_to_digits: transcript.numbers_to_digits,
+            temperature: transcript.temperature,
+            temperature_inc: transcript.temperature_inc,
+            audio_filters: transcript.audio_filters,
+            word_count: transcript.word_count,
+            estimated_reading_time_seconds: reading_seconds,
             created_at: transcript.created_at,
+            updated_at: transcript.updated_at,
+        }
+    }
+}
+
+/// Transcript response for administrators, including the owning user and processing metrics
+#[derive(Debug, Serialize)]
+pub struct AdminTranscriptResponse {
+    pub user_id: Uuid,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Seconds Whisper spent decoding (unset until completed)
+    pub transcription_seconds: Option<f64>,
+    /// Seconds the job took in all, including conversion, model loading and diarization
+    pub processing_seconds: Option<f64>,
+    pub whisper_threads: Option<i32>,
+    #[serde(flatten)]
+    pub transcript: TranscriptResponse,
+}
+
+impl AdminTranscriptResponse {
+    /// Build the response, estimating reading time at `reading_wpm` words per minute
+    pub fn new(mut transcript: Transcript, reading_wpm: u32) -> Self {
+        Self {
+            user_id: transcript.user_id,
+            source_ip: transcript.source_ip.take(),
+            user_agent: transcript.user_agent.take(),
+            transcription_seconds: transcript.transcription_seconds,
+            processing_seconds: transcript.processing_seconds,
+            whisper_threads: transcript.whisper_threads,
+            transcript: TranscriptResponse::new(transcript, reading_wpm),
+        }
+    }
+}
+
+/// Transcript segment response
+#[derive(Debug, Serialize)]
+pub struct TranscriptSegmentResponse {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String,
+    pub speaker: Option<String>,
+    pub confidence: Option<f32>,
+}
+
+impl From<TranscriptSegment> for TranscriptSegmentResponse {
+    fn from(segment: TranscriptSegment) -> Self {
+        Self {
+            start_ms: segment.start_ms,
+            end_ms: segment.end_ms,
+            text: segment.text,
+            speaker: segment.speaker,
+            confidence: segment.confidence,
         }
     }
 }
@@ -119,6 +564,24 @@ pub struct PaginatedResponse<T> {
     pub total_pages: i64,
 }
 
+impl<T> PaginatedResponse<T> {
+    /// Build a page of results, deriving `total_pages` (a `limit` below 1 counts as 1)
+    pub fn new(data: Vec<T>, page: i64, limit: i64, total: i64) -> Self {
+        let per_page = limit.max(1);
+        let total = total.max(0);
+        // Ceiling division without the overflow of `total + per_page - 1`
+        let total_pages = total / per_page + i64::from(total % per_page != 0);
+
+        Self {
+            data,
+            page,
+            limit,
+            total,
+            total_pages,
+        }
+    }
+}
+
 /// JWT Claims structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -127,13 +590,173 @@ pub struct Claims {
     pub iat: i64, // Issued at
     pub exp: i64, // Expiration time
     pub token_type: String, // "access" or "refresh"
+    #[serde(default)]
+    pub role: String, // User role at the time the token was issued
+    pub jti: String, // Unique token ID (used for revocation)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>, // Issuer, when JWT_ISSUER is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>, // Audience, when JWT_AUDIENCE is set
+}
+
+/// Parameters for a new background transcription job
+#[derive(Debug, Clone)]
+pub struct NewTranscriptionJob {
+    pub filename: String,
+    pub file_size: i64,
+    pub duration_seconds: Option<f64>,
+    pub language: Option<String>,
+    pub audio_path: String,
+    pub diarize: bool,
+    pub trim_silence: bool,
+    pub initial_prompt: Option<String>,
+    pub text_format: String,
+    pub word_timestamps: bool,
+    pub model_name: String,
+    pub capitalize_sentences: bool,
+    pub numbers_to_digits: bool,
+    pub temperature: f32,
+    pub temperature_inc: f32,
+    pub audio_filters: Vec<String>,
+    pub source_ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Column a transcript listing can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranscriptSort {
+    #[default]
+    CreatedAt,
+    Duration,
+    Filename,
+    WordCount,
+}
+
+impl TranscriptSort {
+    /// Parse the `sort` query parameter
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "created_at" => Some(Self::CreatedAt),
+            "duration" => Some(Self::Duration),
+            "filename" => Some(Self::Filename),
+            "word_count" => Some(Self::WordCount),
+            _ => None,
+        }
+    }
+
+    /// Column to order by (never built from user input)
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::Duration => "duration_seconds",
+            Self::Filename => "filename",
+            Self::WordCount => "word_count",
+        }
+    }
+}
+
+/// Optional filters and ordering for a user's transcript listing
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    pub tag: Option<String>,
+    pub sort: TranscriptSort,
+    pub ascending: bool,
+}
+
+/// Result of running Whisper over an audio file
+#[derive(Debug, Clone)]
+pub struct TranscriptionResult {
+    pub text: String,
+    pub language: Option<String>,
+    /// ISO code Whisper detected, when no language was requested
+    pub detected_language: Option<String>,
+    /// Top language candidates when auto-detecting, most likely first
+    pub language_probabilities: Vec<LanguageProbability>,
+    pub segments: Vec<TranscriptSegment>,
+    /// Length of the decoded audio in seconds, before any silence is trimmed
+    pub duration_seconds: f64,
+    /// Seconds spent in Whisper, language detection included
+    pub transcription_seconds: f64,
+    /// Seconds spent on the whole job; `transcribe_audio` counts conversion and decoding, and
+    /// the job adds what it does around it
+    pub processing_seconds: f64,
+    /// Whisper threads in use across the chunks decoded in parallel
+    pub whisper_threads: usize,
 }
 
-/// File upload metadata
+/// File upload metadata (the audio itself is streamed to `path` on disk)
 #[derive(Debug, Clone)]
 pub struct FileUpload {
     pub filename: String,
     pub content_type: String,
     pub size: usize,
-    pub data: Vec<u8>,
-}
\ No newline at end of file
+    pub path: String,
+    /// Hex SHA-256 of the file content
+    pub sha256: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_pages(limit: i64, total: i64) -> i64 {
+        PaginatedResponse::<()>::new(Vec::new(), 1, limit, total).total_pages
+    }
+
+    #[test]
+    fn no_results_have_no_pages() {
+        assert_eq!(total_pages(20, 0), 0);
+    }
+
+    #[test]
+    fn exact_multiple_fills_last_page() {
+        assert_eq!(total_pages(20, 40), 2);
+        assert_eq!(total_pages(20, 20), 1);
+    }
+
+    #[test]
+    fn remainder_adds_a_page() {
+        assert_eq!(total_pages(20, 41), 3);
+        assert_eq!(total_pages(20, 1), 1);
+    }
+
+    #[test]
+    fn huge_total_does_not_overflow() {
+        assert_eq!(total_pages(1, i64::MAX), i64::MAX);
+        assert_eq!(total_pages(2, i64::MAX), i64::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn limit_below_one_counts_as_one() {
+        let page = PaginatedResponse::<()>::new(Vec::new(), 1, 0, 3);
+
+        assert_eq!(page.total_pages, 3);
+        assert_eq!(page.limit, 0);
+    }
+
+    #[test]
+    fn share_link_lifetime_is_capped() {
+        let request = |hours| ShareTranscriptRequest {
+            format: None,
+            expires_in_hours: hours,
+        };
+
+        assert!(request(None).validate().is_ok());
+        assert!(request(Some(1)).validate().is_ok());
+        assert!(request(Some(MAX_SHARE_LINK_HOURS)).validate().is_ok());
+        assert!(request(Some(0)).validate().is_err());
+        assert!(request(Some(MAX_SHARE_LINK_HOURS + 1)).validate().is_err());
+    }
+
+    #[test]
+    fn negative_total_is_clamped() {
+        let page = PaginatedResponse::<()>::new(Vec::new(), 1, 20, -5);
+
+        assert_eq!(page.total, 0);
+        assert_eq!(page.total_pages, 0);
+    }
+}
@@ -0,0 +1,78 @@
+// notifications/mod.rs - Account messages (verification and password reset links) sent to users
+use crate::config::Config;
+use crate::errors::AppResult;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Message carrying a single-use account token for a user
+#[derive(Debug, Clone, Copy)]
+pub enum AccountNotice<'a> {
+    EmailVerification { token: &'a str },
+    PasswordReset { token: &'a str },
+}
+
+impl AccountNotice<'_> {
+    /// What the token is for, e.g. for a subject line
+    pub fn purpose(&self) -> &'static str {
+        match self {
+            Self::EmailVerification { .. } => "email verification",
+            Self::PasswordReset { .. } => "password reset",
+        }
+    }
+
+    /// The raw token; only ever handed to the user, never logged outside `LogNotifier`
+    pub fn token(&self) -> &str {
+        match self {
+            Self::EmailVerification { token } | Self::PasswordReset { token } => token,
+        }
+    }
+}
+
+/// Delivery of account notices to users
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Whether notices reach users at all; flows that only work through them are refused otherwise
+    fn is_enabled(&self) -> bool;
+
+    /// Deliver a notice to `email`
+    async fn send(&self, email: &str, notice: AccountNotice<'_>) -> AppResult<()>;
+}
+
+/// Build the notifier; nothing is delivered unless `DEV_LOG_ACCOUNT_EMAILS` is set
+pub fn build_notifier(config: &Config) -> Arc<dyn Notifier> {
+    if config.dev_log_account_emails {
+        log::warn!("DEV_LOG_ACCOUNT_EMAILS is set: account tokens are written to the log. Never use this in production");
+        Arc::new(LogNotifier)
+    } else {
+        Arc::new(DisabledNotifier)
+    }
+}
+
+/// Delivers nothing
+pub struct DisabledNotifier;
+
+#[async_trait]
+impl Notifier for DisabledNotifier {
+    fn is_enabled(&self) -> bool {
+        false
+    }
+
+    async fn send(&self, _email: &str, _notice: AccountNotice<'_>) -> AppResult<()> {
+        Ok(())
+    }
+}
+
+/// Development only: writes notices, token included, to the log instead of sending them
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    async fn send(&self, email: &str, notice: AccountNotice<'_>) -> AppResult<()> {
+        log::info!("[dev] {} token for {}: {}", notice.purpose(), email, notice.token());
+        Ok(())
+    }
+}
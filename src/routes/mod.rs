@@ -1,5 +1,8 @@
-use crate::controllers::{AuthController, HealthController, TranscriptionController};
-use crate::middlewares::JwtAuth;
+use crate::controllers::{
+    AdminController, AuthController, HealthController, StreamController, TranscriptionController,
+    WebhookController,
+};
+use crate::middlewares::{JwtAuth, RateLimit, RequireRole, RequireVerifiedEmail};
 use actix_web::{web, HttpResponse};
 
 /// Configure all application routes
@@ -7,6 +10,17 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg
         // Health check route (no authentication required)
         .route("/health", web::get().to(HealthController::health))
+        .route("/health/live", web::get().to(HealthController::live))
+        .route("/health/ready", web::get().to(HealthController::ready))
+        .route("/.well-known/jwks.json", web::get().to(AuthController::jwks))
+        
+        // OpenAI-compatible transcription API, so existing OpenAI SDK clients can point here
+        .service(
+            web::scope("/v1")
+                .wrap(RequireVerifiedEmail)
+                .wrap(JwtAuth)
+                .route("/audio/transcriptions", web::post().to(TranscriptionController::openai_transcription))
+        )
         
         // API v1 routes
         .service(
@@ -14,10 +28,26 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
                 // Authentication routes (no JWT required)
                 .service(
                     web::scope("/auth")
+                        .wrap(RateLimit) // Throttle auth requests per client IP
                         .route("/register", web::post().to(AuthController::register))
                         .route("/login", web::post().to(AuthController::login))
                         .route("/refresh", web::post().to(AuthController::refresh))
+                        .route("/logout", web::post().to(AuthController::logout))
+                        .route("/verify", web::get().to(AuthController::verify_email))
+                        .route("/resend-verification", web::post().to(AuthController::resend_verification))
+                        .route("/forgot-password", web::post().to(AuthController::forgot_password))
+                        .route("/reset-password", web::post().to(AuthController::reset_password))
+                        .service(
+                            web::scope("/sessions")
+                                .wrap(JwtAuth)
+                                .route("", web::get().to(AuthController::list_sessions))
+                                .route("/{jti}", web::delete().to(AuthController::revoke_session))
+                        )
                 )
+                // Shared transcripts (the signed token in the path is the only credential)
+                .route("/shared/{token}", web::get().to(TranscriptionController::get_shared_transcript))
+                // Live transcription (authenticates the token itself; browsers can't send headers)
+                .route("/transcribe/stream", web::get().to(StreamController::transcribe_stream))
                 // Protected routes (JWT required)
                 .service(
                     web::scope("")
@@ -25,14 +55,55 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
                         
                         // User profile routes
                         .route("/me", web::get().to(AuthController::me))
+                        .route("/me", web::delete().to(AuthController::delete_account))
+                        .route("/me/usage", web::get().to(TranscriptionController::get_usage))
+                        .route("/me/stats", web::get().to(TranscriptionController::get_stats))
+                        .route("/me/webhook", web::get().to(WebhookController::get_webhook))
+                        .route("/me/webhook", web::put().to(WebhookController::put_webhook))
+                        .route("/me/webhook", web::delete().to(WebhookController::delete_webhook))
+                        .route("/me/shares", web::delete().to(TranscriptionController::revoke_shares))
                         
+                        // Administration routes
+                        .service(
+                            web::scope("/admin")
+                                .wrap(RequireRole::admin()) // Only administrators
+                                .route("/transcripts", web::get().to(AdminController::get_all_transcripts))
+                                .route("/users/{id}", web::delete().to(AdminController::delete_user))
+                                .route("/users/{id}/disable", web::post().to(AdminController::disable_user))
+                                .route("/users/{id}/enable", web::post().to(AdminController::enable_user))
+                        )
+
                         // Transcription routes
                         .service(
                             web::scope("/transcripts")
+                                .wrap(RequireVerifiedEmail) // Only verified users can transcribe
                                 .route("", web::post().to(TranscriptionController::upload_and_transcribe))
+                                .route("/batch", web::post().to(TranscriptionController::upload_batch))
+                                .route("/bulk-delete", web::post().to(TranscriptionController::bulk_delete_transcripts))
                                 .route("", web::get().to(TranscriptionController::get_transcripts))
                                 .route("/{id}", web::get().to(TranscriptionController::get_transcript))
+                                .route("/{id}", web::patch().to(TranscriptionController::update_transcript))
                                 .route("/{id}", web::delete().to(TranscriptionController::delete_transcript))
+                                .route("/{id}/restore", web::post().to(TranscriptionController::restore_transcript))
+                                .route("/{id}/retry", web::post().to(TranscriptionController::retry_transcript))
+                                .route("/{id}/progress", web::get().to(TranscriptionController::transcript_progress))
+                                .route("/{id}/segments", web::get().to(TranscriptionController::get_transcript_segments))
+                                .route("/{id}/diff", web::get().to(TranscriptionController::get_transcript_diff))
+                                .route("/{id}/audio", web::get().to(TranscriptionController::get_transcript_audio))
+                                .route("/{id}/export", web::get().to(TranscriptionController::export_transcript))
+                                .route("/{id}/download", web::get().to(TranscriptionController::download_transcript))
+                                .route("/{id}/share", web::post().to(TranscriptionController::share_transcript))
+                                .route("/{id}/tags", web::post().to(TranscriptionController::add_tags))
+                                .route("/{id}/tags/{tag}", web::delete().to(TranscriptionController::remove_tag))
+                        )
+                        // Resumable uploads, finalized into a transcription job
+                        .service(
+                            web::scope("/uploads")
+                                .wrap(RequireVerifiedEmail)
+                                .route("", web::post().to(TranscriptionController::initiate_upload))
+                                .route("/{id}", web::get().to(TranscriptionController::get_upload))
+                                .route("/{id}", web::patch().to(TranscriptionController::append_upload))
+                                .route("/{id}/finalize", web::post().to(TranscriptionController::finalize_upload))
                         )
                 )
         )
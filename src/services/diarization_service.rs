@@ -0,0 +1,64 @@
+use crate::errors::{AppError, AppResult};
+use crate::models::TranscriptSegment;
+use crate::utils::file;
+use serde::Deserialize;
+
+/// A single speaker turn reported by the diarization helper
+///
+/// The helper configured via `DIARIZATION_CMD` is invoked as `<cmd> <audio_path>` and must
+/// print a JSON array of turns to stdout, with times in seconds:
+///
+/// `[{"start": 0.0, "end": 4.2, "speaker": "SPEAKER_00"}, ...]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpeakerTurn {
+    pub start: f64,
+    pub end: f64,
+    pub speaker: String,
+}
+
+/// Diarization service that shells out to an external speaker segmentation helper
+pub struct DiarizationService;
+
+impl DiarizationService {
+    /// Run the diarization helper on an audio file
+    pub async fn diarize(command: &str, audio_path: &str) -> AppResult<Vec<SpeakerTurn>> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| AppError::ConfigError("DIARIZATION_CMD is empty".to_string()))?;
+
+        let output = tokio::process::Command::new(program)
+            .args(parts)
+            .arg(file::command_file_arg(audio_path))
+            .output()
+            .await
+            .map_err(|e| AppError::FileError(format!("Failed to run diarization helper: {}", e)))?;
+
+        if !output.status.success() {
+            let error_msg = String::from_utf8_lossy(&output.stderr);
+            return Err(AppError::FileError(format!("Diarization helper failed: {}", error_msg)));
+        }
+
+        let turns: Vec<SpeakerTurn> = serde_json::from_slice(&output.stdout).map_err(|e| {
+            AppError::FileError(format!("Invalid diarization helper output: {}", e))
+        })?;
+
+        log::info!("Diarization found {} speaker turns", turns.len());
+        Ok(turns)
+    }
+
+    /// Label each segment with the speaker whose turns overlap it the most
+    pub fn assign_speakers(segments: &mut [TranscriptSegment], turns: &[SpeakerTurn]) {
+        for segment in segments.iter_mut() {
+            let start = segment.start_ms as f64 / 1000.0;
+            let end = segment.end_ms as f64 / 1000.0;
+
+            segment.speaker = turns
+                .iter()
+                .map(|turn| (turn, end.min(turn.end) - start.max(turn.start)))
+                .filter(|(_, overlap)| *overlap > 0.0)
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(turn, _)| turn.speaker.clone());
+        }
+    }
+}
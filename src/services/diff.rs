@@ -0,0 +1,212 @@
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+use std::time::Duration;
+
+/// Longest time spent finding a minimal diff; past it the diff is still correct, just coarser
+const DIFF_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Unit a transcript diff is computed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffGranularity {
+    Word,
+    Line,
+}
+
+impl DiffGranularity {
+    /// Parse a granularity name, case-insensitively
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "word" => Some(Self::Word),
+            "line" => Some(Self::Line),
+            _ => None,
+        }
+    }
+}
+
+/// What happened to a run of text between the original and the current transcription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// Consecutive words or lines sharing the same operation
+#[derive(Debug, Serialize)]
+pub struct DiffChunk {
+    pub op: DiffOp,
+    /// Exact text of the run, whitespace and line breaks included
+    pub text: String,
+    /// Position of the run's first token in the original (unset for insertions); word diffs
+    /// count runs of whitespace as tokens too
+    pub original_index: Option<usize>,
+    /// Position of the run's first token in the current text (unset for deletions)
+    pub current_index: Option<usize>,
+}
+
+/// Words or lines inserted, deleted and left unchanged; whitespace isn't counted
+#[derive(Debug, Default, Serialize)]
+pub struct DiffStats {
+    pub inserted: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+}
+
+/// Diff between a transcript's original and current text
+#[derive(Debug)]
+pub struct TranscriptDiff {
+    pub chunks: Vec<DiffChunk>,
+    pub stats: DiffStats,
+}
+
+/// Diff the original transcription against the current one
+/// Concatenating the text of the equal and delete chunks gives back the original exactly,
+/// and the equal and insert chunks the current text. Word diffs keep whitespace in the chunks
+/// but only count words in the stats.
+pub fn diff(original: &str, current: &str, granularity: DiffGranularity) -> TranscriptDiff {
+    let mut config = TextDiff::configure();
+    config.timeout(DIFF_TIMEOUT);
+    let text_diff = match granularity {
+        DiffGranularity::Word => config.diff_words(original, current),
+        DiffGranularity::Line => config.diff_lines(original, current),
+    };
+
+    let mut chunks: Vec<DiffChunk> = Vec::new();
+    let mut stats = DiffStats::default();
+
+    for change in text_diff.iter_all_changes() {
+        let op = match change.tag() {
+            ChangeTag::Equal => DiffOp::Equal,
+            ChangeTag::Insert => DiffOp::Insert,
+            ChangeTag::Delete => DiffOp::Delete,
+        };
+        let value = change.value();
+
+        if !value.trim().is_empty() {
+            match op {
+                DiffOp::Equal => stats.unchanged += 1,
+                DiffOp::Insert => stats.inserted += 1,
+                DiffOp::Delete => stats.deleted += 1,
+            }
+        }
+
+        match chunks.last_mut() {
+            Some(chunk) if chunk.op == op => chunk.text.push_str(value),
+            _ => chunks.push(DiffChunk {
+                op,
+                text: value.to_string(),
+                original_index: change.old_index(),
+                current_index: change.new_index(),
+            }),
+        }
+    }
+
+    TranscriptDiff { chunks, stats }
+}
+
+/// Chunks on a 1-based page of `limit` chunks; pages past the end are empty
+pub fn page_of(chunks: Vec<DiffChunk>, page: i64, limit: i64) -> Vec<DiffChunk> {
+    let offset = (page - 1).saturating_mul(limit);
+    chunks
+        .into_iter()
+        .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+        .take(usize::try_from(limit).unwrap_or(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Concatenated text of the chunks with one of the given operations
+    fn rebuild(diff: &TranscriptDiff, ops: &[DiffOp]) -> String {
+        diff.chunks
+            .iter()
+            .filter(|chunk| ops.contains(&chunk.op))
+            .map(|chunk| chunk.text.as_str())
+            .collect()
+    }
+
+    fn assert_round_trips(original: &str, current: &str, granularity: DiffGranularity) {
+        let diff = diff(original, current, granularity);
+        assert_eq!(rebuild(&diff, &[DiffOp::Equal, DiffOp::Delete]), original);
+        assert_eq!(rebuild(&diff, &[DiffOp::Equal, DiffOp::Insert]), current);
+    }
+
+    #[test]
+    fn word_chunks_rebuild_both_texts() {
+        assert_round_trips(
+            "the quick brown fox  jumps over\nthe lazy dog",
+            "the quick red fox jumps\nover the  lazy dog.",
+            DiffGranularity::Word,
+        );
+        assert_round_trips("", "something new", DiffGranularity::Word);
+        assert_round_trips("all gone", "", DiffGranularity::Word);
+    }
+
+    #[test]
+    fn line_chunks_rebuild_both_texts() {
+        assert_round_trips(
+            "first line\nsecond line\nthird line\n",
+            "first line\nsecond line, edited\n\nthird line",
+            DiffGranularity::Line,
+        );
+        assert_round_trips("only line", "only line\nand another", DiffGranularity::Line);
+    }
+
+    #[test]
+    fn consecutive_changes_are_merged_into_one_chunk() {
+        let diff = diff("a b c", "a x y c", DiffGranularity::Word);
+        let ops: Vec<DiffOp> = diff.chunks.iter().map(|chunk| chunk.op).collect();
+
+        assert_eq!(ops, vec![DiffOp::Equal, DiffOp::Delete, DiffOp::Insert, DiffOp::Equal]);
+        assert_eq!(diff.chunks[2].text, "x y");
+        assert_eq!(diff.chunks[2].original_index, None);
+        assert_eq!(diff.chunks[1].current_index, None);
+    }
+
+    #[test]
+    fn stats_count_words_not_whitespace() {
+        let diff = diff("hello  world", "hello big   world", DiffGranularity::Word);
+
+        assert_eq!(diff.stats.inserted, 1);
+        assert_eq!(diff.stats.deleted, 0);
+        assert_eq!(diff.stats.unchanged, 2);
+    }
+
+    #[test]
+    fn whitespace_only_edits_change_no_stats() {
+        let words = diff("one two three", "one  two\nthree", DiffGranularity::Word);
+        assert_eq!((words.stats.inserted, words.stats.deleted), (0, 0));
+        assert_eq!(words.stats.unchanged, 3);
+        assert!(words.chunks.iter().any(|chunk| chunk.op != DiffOp::Equal));
+
+        let lines = diff("one\ntwo\n", "one\n\n   \ntwo\n", DiffGranularity::Line);
+        assert_eq!((lines.stats.inserted, lines.stats.deleted), (0, 0));
+        assert_eq!(lines.stats.unchanged, 2);
+    }
+
+    #[test]
+    fn pages_slice_the_chunks() {
+        let chunks = || diff("a b c d e", "a x c y e", DiffGranularity::Word).chunks;
+        let all: Vec<String> = chunks().into_iter().map(|chunk| chunk.text).collect();
+        let texts = |page: Vec<DiffChunk>| -> Vec<String> {
+            page.into_iter().map(|chunk| chunk.text).collect()
+        };
+
+        assert_eq!(texts(page_of(chunks(), 1, 3)), all[..3]);
+        assert_eq!(texts(page_of(chunks(), 2, 3)), all[3..6]);
+        assert_eq!(texts(page_of(chunks(), 1, 100)), all);
+        assert!(page_of(chunks(), 100, 3).is_empty());
+        assert!(page_of(chunks(), i64::MAX, i64::MAX).is_empty());
+    }
+
+    #[test]
+    fn granularity_names_are_case_insensitive() {
+        assert_eq!(DiffGranularity::parse("Word"), Some(DiffGranularity::Word));
+        assert_eq!(DiffGranularity::parse("LINE"), Some(DiffGranularity::Line));
+        assert_eq!(DiffGranularity::parse("char"), None);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::models::{Transcript, TranscriptResponse, TranscriptSegment, TranscriptSegmentResponse};
+use serde_json::json;
+
+/// Render segments as a SubRip (.srt) subtitle file
+pub fn to_srt(segments: &[TranscriptSegment]) -> String {
+    let mut output = String::new();
+
+    for (index, segment) in segments.iter().enumerate() {
+        output.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            format_timestamp(segment.start_ms, ','),
+            format_timestamp(segment.end_ms, ','),
+            segment.text.trim()
+        ));
+    }
+
+    output
+}
+
+/// Render segments as a WebVTT (.vtt) subtitle file
+pub fn to_vtt(segments: &[TranscriptSegment]) -> String {
+    let mut output = String::from("WEBVTT\n\n");
+
+    for segment in segments {
+        output.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(segment.start_ms, '.'),
+            format_timestamp(segment.end_ms, '.'),
+            segment.text.trim()
+        ));
+    }
+
+    output
+}
+
+/// Render the transcription as a plain text document
+pub fn to_txt(transcript: &Transcript) -> String {
+    format!("{}\n", transcript.transcription.trim())
+}
+
+/// Render the transcript and its segments as a structured JSON document
+pub fn to_json(
+    transcript: &Transcript,
+    segments: &[TranscriptSegment],
+    reading_wpm: u32,
+) -> serde_json::Value {
+    let segments: Vec<TranscriptSegmentResponse> = segments
+        .iter()
+        .cloned()
+        .map(TranscriptSegmentResponse::from)
+        .collect();
+
+    json!({
+        "transcript": TranscriptResponse::new(transcript.clone(), reading_wpm),
+        "segments": segments,
+    })
+}
+
+/// Render a finished transcript as OpenAI's `verbose_json` transcription response.
+/// Fields Whisper doesn't expose here (token IDs, compression ratio) are filled with neutral values.
+pub fn to_openai_verbose_json(
+    transcript: &Transcript,
+    segments: &[TranscriptSegment],
+) -> serde_json::Value {
+    let segments: Vec<serde_json::Value> = segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            // OpenAI reports the mean token log-probability; ours is the mean probability
+            let avg_logprob = segment
+                .confidence
+                .map_or(0.0, |confidence| confidence.max(f32::MIN_POSITIVE).ln());
+            json!({
+                "id": index,
+                "seek": 0,
+                "start": segment.start_ms as f64 / 1000.0,
+                "end": segment.end_ms as f64 / 1000.0,
+                "text": segment.text,
+                "tokens": [],
+                "temperature": 0.0,
+                "avg_logprob": avg_logprob,
+                "compression_ratio": 0.0,
+                "no_speech_prob": 0.0,
+            })
+        })
+        .collect();
+
+    json!({
+        "task": "transcribe",
+        "language": transcript.language.as_deref().unwrap_or("unknown"),
+        "duration": transcript.duration_seconds.unwrap_or(0.0),
+        "text": transcript.transcription,
+        "segments": segments,
+    })
+}
+
+/// Format milliseconds as `HH:MM:SS<sep>mmm` (SRT uses ',' and WebVTT uses '.')
+pub fn format_timestamp(ms: i64, separator: char) -> String {
+    let ms = ms.max(0);
+    let hours = ms / 3_600_000;
+    let minutes = (ms % 3_600_000) / 60_000;
+    let seconds = (ms % 60_000) / 1_000;
+    let millis = ms % 1_000;
+
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        hours, minutes, seconds, separator, millis
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_ms: i64, end_ms: i64, text: &str) -> TranscriptSegment {
+        TranscriptSegment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+            speaker: None,
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn formats_timestamp_fields() {
+        assert_eq!(format_timestamp(0, ','), "00:00:00,000");
+        assert_eq!(format_timestamp(61_001, ','), "00:01:01,001");
+        assert_eq!(format_timestamp(3_599_999, '.'), "00:59:59.999");
+    }
+
+    #[test]
+    fn rolls_over_into_hours() {
+        assert_eq!(format_timestamp(3_600_000, ','), "01:00:00,000");
+        assert_eq!(format_timestamp(36_000_000 + 62_500, '.'), "10:01:02.500");
+        // Hours aren't wrapped, even past a day
+        assert_eq!(format_timestamp(100 * 3_600_000, ','), "100:00:00,000");
+    }
+
+    #[test]
+    fn clamps_negative_timestamps() {
+        assert_eq!(format_timestamp(-5, ','), "00:00:00,000");
+    }
+
+    #[test]
+    fn srt_numbers_cues_and_uses_comma() {
+        let segments = [segment(0, 1_500, " Hello. "), segment(1_500, 3_600_250, "World.")];
+
+        assert_eq!(
+            to_srt(&segments),
+            "1\n00:00:00,000 --> 00:00:01,500\nHello.\n\n\
+             2\n00:00:01,500 --> 01:00:00,250\nWorld.\n\n"
+        );
+    }
+
+    #[test]
+    fn vtt_has_header_and_uses_dot() {
+        let segments = [segment(0, 1_500, " Hello. "), segment(1_500, 2_000, "World.")];
+
+        assert_eq!(
+            to_vtt(&segments),
+            "WEBVTT\n\n\
+             00:00:00.000 --> 00:00:01.500\nHello.\n\n\
+             00:00:01.500 --> 00:00:02.000\nWorld.\n\n"
+        );
+    }
+
+    #[test]
+    fn empty_segments_render_empty_files() {
+        assert_eq!(to_srt(&[]), "");
+        assert_eq!(to_vtt(&[]), "WEBVTT\n\n");
+    }
+}
@@ -0,0 +1,132 @@
+use crate::errors::{AppError, AppResult};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use std::time::Instant;
+use uuid::Uuid;
+
+/// How long a duplicate request waits for the original to finish before giving up
+const IN_PROGRESS_WAIT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Interval between checks while waiting for the original request
+const IN_PROGRESS_POLL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Outcome of claiming an idempotency key
+#[derive(Debug)]
+pub enum IdempotencyClaim {
+    /// This request owns the key and must `complete` or `release` it
+    Acquired,
+    /// The key was already used by an identical request; replay its response
+    Replay(serde_json::Value),
+}
+
+/// Idempotency key bookkeeping for retried uploads
+pub struct IdempotencyService;
+
+impl IdempotencyService {
+    /// Claim `key` for this request. A duplicate of a request still running waits for it,
+    /// so concurrent retries are serialized and only one job is created.
+    pub async fn claim(
+        pool: &PgPool,
+        user_id: Uuid,
+        key: &str,
+        request_hash: &str,
+        ttl_hours: i64,
+    ) -> AppResult<IdempotencyClaim> {
+        let started = Instant::now();
+
+        loop {
+            // Expired keys may be reused as if they had never been seen
+            sqlx::query("DELETE FROM idempotency_keys WHERE user_id = $1 AND key = $2 AND expires_at <= NOW()")
+                .bind(user_id)
+                .bind(key)
+                .execute(pool)
+                .await?;
+
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO idempotency_keys (user_id, key, request_hash, expires_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (user_id, key) DO NOTHING
+                "#,
+            )
+            .bind(user_id)
+            .bind(key)
+            .bind(request_hash)
+            .bind(Utc::now() + Duration::hours(ttl_hours))
+            .execute(pool)
+            .await?;
+
+            if inserted.rows_affected() == 1 {
+                return Ok(IdempotencyClaim::Acquired);
+            }
+
+            let existing: Option<(String, Option<serde_json::Value>)> = sqlx::query_as(
+                "SELECT request_hash, response FROM idempotency_keys WHERE user_id = $1 AND key = $2",
+            )
+            .bind(user_id)
+            .bind(key)
+            .fetch_optional(pool)
+            .await?;
+
+            match existing {
+                // Released or expired in the meantime; try to claim it again
+                None => continue,
+                Some((existing_hash, _)) if existing_hash != request_hash => {
+                    return Err(AppError::ValidationError(
+                        "Idempotency-Key was already used with a different request".to_string(),
+                    ));
+                }
+                Some((_, Some(response))) => return Ok(IdempotencyClaim::Replay(response)),
+                Some((_, None)) => {
+                    if started.elapsed() >= IN_PROGRESS_WAIT {
+                        return Err(AppError::Conflict(
+                            "A request with this Idempotency-Key is still being processed".to_string(),
+                        ));
+                    }
+                    tokio::time::sleep(IN_PROGRESS_POLL).await;
+                }
+            }
+        }
+    }
+
+    /// Record the response of the request that owns `key`
+    pub async fn complete(
+        pool: &PgPool,
+        user_id: Uuid,
+        key: &str,
+        transcript_id: Uuid,
+        response: &serde_json::Value,
+    ) -> AppResult<()> {
+        sqlx::query(
+            "UPDATE idempotency_keys SET transcript_id = $3, response = $4 WHERE user_id = $1 AND key = $2",
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(transcript_id)
+        .bind(response)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Give up a claimed key after a failed request so the client can retry with it
+    pub async fn release(pool: &PgPool, user_id: Uuid, key: &str) -> AppResult<()> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_id = $1 AND key = $2 AND response IS NULL")
+            .bind(user_id)
+            .bind(key)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete keys past their TTL
+    pub async fn purge_expired(pool: &PgPool) -> AppResult<u64> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= NOW()")
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
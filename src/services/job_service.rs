@@ -0,0 +1,637 @@
+use crate::AppState;
+use crate::events;
+use crate::errors::{AppError, AppResult};
+use crate::models::{QueueStatus, TranscriptSegment, transcript_status};
+use crate::services::postprocess::PostProcessOptions;
+use crate::services::{
+    DecodingOptions, DiarizationService, TranscribeOptions, TranscriptionService, WebhookService,
+};
+use dashmap::DashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore, broadcast, mpsc};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Progress update published while a transcription job runs
+#[derive(Debug, Clone)]
+pub enum ProgressEvent {
+    /// Whisper progress percentage (0-100)
+    Progress(i32),
+    /// A segment Whisper just finished, ahead of the complete transcript
+    Segment(TranscriptSegment),
+    /// Job finished with the given final status
+    Done(String),
+}
+
+/// Progress channels of running jobs, keyed by transcript ID
+pub type ProgressChannels = Arc<Mutex<HashMap<Uuid, broadcast::Sender<ProgressEvent>>>>;
+
+/// Tracks running transcription jobs so shutdown can wait for them to drain
+#[derive(Clone)]
+pub struct JobTracker {
+    active: Arc<Mutex<HashSet<Uuid>>>,
+    idle: Arc<Notify>,
+    // Set on shutdown so workers stop picking up queued jobs
+    stopping: Arc<AtomicBool>,
+}
+
+/// Removes a job from the tracker when it ends, even if it panics
+struct ActiveJob {
+    tracker: JobTracker,
+    transcript_id: Uuid,
+}
+
+impl Drop for ActiveJob {
+    fn drop(&mut self) {
+        if let Ok(mut active) = self.tracker.active.lock() {
+            active.remove(&self.transcript_id);
+            if active.is_empty() {
+                self.tracker.idle.notify_waiters();
+            }
+        }
+    }
+}
+
+impl Default for JobTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobTracker {
+    /// Create a tracker with no running jobs
+    pub fn new() -> Self {
+        Self {
+            active: Arc::new(Mutex::new(HashSet::new())),
+            idle: Arc::new(Notify::new()),
+            stopping: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Number of jobs currently running
+    pub fn active_count(&self) -> usize {
+        self.active.lock().map(|active| active.len()).unwrap_or(0)
+    }
+
+    /// Wait until no jobs are running, returning `false` if the timeout elapses first
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                // Register for the wakeup before checking to avoid missing it
+                let notified = self.idle.notified();
+                if self.active_count() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    /// Stop starting queued jobs; running ones continue
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether shutdown has begun
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Mark a job as running until the returned guard is dropped
+    fn track(&self, transcript_id: Uuid) -> ActiveJob {
+        if let Ok(mut active) = self.active.lock() {
+            active.insert(transcript_id);
+        }
+
+        ActiveJob {
+            tracker: self.clone(),
+            transcript_id,
+        }
+    }
+}
+
+/// Seconds clients are told to wait when the transcription queue is full
+const QUEUE_FULL_RETRY_AFTER_SECONDS: u64 = 30;
+
+/// Recent jobs the processing rate is averaged over
+const PROCESSING_RATE_WINDOW: usize = 20;
+
+/// Rolling average of processing time per second of audio over recently completed jobs
+#[derive(Clone, Default)]
+pub struct ProcessingRate {
+    // (processing seconds, audio seconds) of the latest jobs, oldest first
+    recent: Arc<Mutex<VecDeque<(f64, f64)>>>,
+}
+
+impl ProcessingRate {
+    /// Record a completed job; jobs of unknown length are ignored
+    pub fn record(&self, processing_seconds: f64, audio_seconds: f64) {
+        if audio_seconds <= 0.0 {
+            return;
+        }
+        if let Ok(mut recent) = self.recent.lock() {
+            recent.push_back((processing_seconds, audio_seconds));
+            if recent.len() > PROCESSING_RATE_WINDOW {
+                recent.pop_front();
+            }
+        }
+    }
+
+    /// Seconds of processing per second of audio, once any job has completed
+    pub fn seconds_per_audio_second(&self) -> Option<f64> {
+        let recent = self.recent.lock().ok()?;
+        let (processing, audio) = recent
+            .iter()
+            .fold((0.0, 0.0), |(processing, audio), job| (processing + job.0, audio + job.1));
+        (audio > 0.0).then(|| processing / audio)
+    }
+}
+
+/// Transcription job waiting in the queue for a worker
+pub struct QueuedJob {
+    transcript_id: Uuid,
+    audio_seconds: f64,
+    slot: Option<JobSlot>,
+    progress_tx: broadcast::Sender<ProgressEvent>,
+    // The span of the request that queued the job, so job logs carry its request ID
+    span: tracing::Span,
+}
+
+/// A reserved place in the transcription queue, claimed before any work is done for a job
+pub type QueuePermit = mpsc::OwnedPermit<QueuedJob>;
+
+/// Bounded queue feeding the transcription workers
+#[derive(Clone)]
+pub struct JobQueue {
+    sender: mpsc::Sender<QueuedJob>,
+    // Milliseconds of audio waiting in the queue and being transcribed, for wait estimates
+    waiting_audio_ms: Arc<AtomicU64>,
+    running_audio_ms: Arc<AtomicU64>,
+}
+
+impl JobQueue {
+    /// Create a queue holding at most `capacity` waiting jobs, and the receiver its workers share
+    pub fn new(capacity: usize) -> (Self, mpsc::Receiver<QueuedJob>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        let queue = Self {
+            sender,
+            waiting_audio_ms: Arc::new(AtomicU64::new(0)),
+            running_audio_ms: Arc::new(AtomicU64::new(0)),
+        };
+        (queue, receiver)
+    }
+
+    /// Reserve a place for a new job, failing with `ServiceUnavailable` when the queue is full
+    pub fn try_reserve(&self) -> AppResult<QueuePermit> {
+        self.sender.clone().try_reserve_owned().map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => {
+                log::warn!("Transcription queue is full ({} jobs waiting)", self.sender.max_capacity());
+                AppError::ServiceUnavailable(QUEUE_FULL_RETRY_AFTER_SECONDS)
+            }
+            mpsc::error::TrySendError::Closed(_) => {
+                AppError::InternalError("Transcription workers have stopped".to_string())
+            }
+        })
+    }
+
+    /// Wait for a place in the queue
+    async fn reserve(&self) -> AppResult<QueuePermit> {
+        self.sender
+            .clone()
+            .reserve_owned()
+            .await
+            .map_err(|_| AppError::InternalError("Transcription workers have stopped".to_string()))
+    }
+
+    /// Number of jobs waiting for a worker
+    pub fn waiting_count(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    /// Seconds of audio waiting for a worker
+    pub fn waiting_audio_seconds(&self) -> f64 {
+        self.waiting_audio_ms.load(Ordering::Relaxed) as f64 / 1000.0
+    }
+
+    /// Seconds of audio being transcribed right now
+    pub fn running_audio_seconds(&self) -> f64 {
+        self.running_audio_ms.load(Ordering::Relaxed) as f64 / 1000.0
+    }
+}
+
+/// Milliseconds of a job's audio, as counted in the queue totals
+fn audio_ms(audio_seconds: f64) -> u64 {
+    (audio_seconds.max(0.0) * 1000.0) as u64
+}
+
+/// Seconds a user is told to wait when all of their job slots are taken
+const JOB_SLOT_RETRY_AFTER_SECONDS: u64 = 30;
+
+/// Number of tracked users above which idle job slot entries are swept
+const JOB_SLOT_SWEEP_THRESHOLD: usize = 10_000;
+
+/// A user's claim on one of their job slots, released when dropped
+pub type JobSlot = OwnedSemaphorePermit;
+
+/// Per-user semaphores capping how many transcription jobs each user has in flight
+#[derive(Clone, Default)]
+pub struct JobSlots {
+    semaphores: Arc<DashMap<Uuid, Arc<Semaphore>>>,
+}
+
+impl JobSlots {
+    /// Claim a job slot for a user, failing with `RateLimited` if all of theirs are taken
+    pub fn try_acquire(&self, user_id: Uuid, max_jobs: usize) -> AppResult<JobSlot> {
+        if self.semaphores.len() > JOB_SLOT_SWEEP_THRESHOLD {
+            self.semaphores
+                .retain(|_, semaphore| semaphore.available_permits() < max_jobs);
+        }
+
+        let semaphore = self
+            .semaphores
+            .entry(user_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(max_jobs)))
+            .clone();
+
+        semaphore.try_acquire_owned().map_err(|_| {
+            log::warn!("Concurrent job limit reached for user: {}", user_id);
+            AppError::RateLimited(JOB_SLOT_RETRY_AFTER_SECONDS)
+        })
+    }
+}
+
+/// Background job service for running transcriptions outside the request
+pub struct JobService;
+
+impl JobService {
+    /// Queue a transcription job in a reserved place, holding `slot` (if any) until it finishes
+    pub fn enqueue(
+        app_state: &AppState,
+        transcript_id: Uuid,
+        audio_seconds: Option<f64>,
+        slot: Option<JobSlot>,
+        permit: QueuePermit,
+    ) {
+        // Register the progress channel up front so clients can subscribe while the job is pending
+        // Sized for segment events too; a lagging subscriber can fetch missed segments instead
+        let (progress_tx, _) = broadcast::channel(256);
+        if let Ok(mut channels) = app_state.progress_channels.lock() {
+            channels.insert(transcript_id, progress_tx.clone());
+        }
+
+        // Created inside the request span (if any), so job logs carry the request ID
+        let span = tracing::info_span!("transcription_job", transcript_id = %transcript_id);
+
+        let audio_seconds = audio_seconds.unwrap_or(0.0);
+        app_state
+            .job_queue
+            .waiting_audio_ms
+            .fetch_add(audio_ms(audio_seconds), Ordering::Relaxed);
+
+        permit.send(QueuedJob {
+            transcript_id,
+            audio_seconds,
+            slot,
+            progress_tx,
+            span,
+        });
+    }
+
+    /// Start `TRANSCRIPTION_WORKERS` workers, each running one queued job at a time
+    /// Whisper state borrows its model, so each job creates its own rather than keeping one per worker;
+    /// `WHISPER_MAX_STATES` caps how many exist at once.
+    pub fn start_workers(app_state: &AppState, receiver: mpsc::Receiver<QueuedJob>) {
+        let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+
+        for worker in 0..app_state.config.transcription_workers {
+            let app_state = app_state.clone();
+            let receiver = receiver.clone();
+
+            tokio::spawn(async move {
+                loop {
+                    let Some(job) = receiver.lock().await.recv().await else {
+                        break;
+                    };
+                    let queue = &app_state.job_queue;
+                    let job_audio_ms = audio_ms(job.audio_seconds);
+                    queue.waiting_audio_ms.fetch_sub(job_audio_ms, Ordering::Relaxed);
+
+                    // Jobs left in the queue are still pending and are re-queued on the next start
+                    if app_state.jobs.is_stopping() {
+                        break;
+                    }
+
+                    let transcript_id = job.transcript_id;
+                    let audio_seconds = job.audio_seconds;
+                    let _active = app_state.jobs.track(transcript_id);
+                    queue.running_audio_ms.fetch_add(job_audio_ms, Ordering::Relaxed);
+                    let started = Instant::now();
+
+                    // A panicking job must not take its worker down with it
+                    let span = job.span.clone();
+                    let run = tokio::spawn(Self::process(app_state.clone(), job).instrument(span));
+                    match run.await {
+                        Ok(true) => app_state
+                            .processing_rate
+                            .record(started.elapsed().as_secs_f64(), audio_seconds),
+                        Ok(false) => {}
+                        Err(e) => log::error!(
+                            "Transcription worker {} lost job {}: {}",
+                            worker,
+                            transcript_id,
+                            e
+                        ),
+                    }
+                    queue.running_audio_ms.fetch_sub(job_audio_ms, Ordering::Relaxed);
+                }
+            });
+        }
+
+        log::info!(
+            "Started {} transcription workers (queue size {})",
+            app_state.config.transcription_workers,
+            app_state.config.transcription_queue_size
+        );
+    }
+
+    /// Run a queued job and publish its outcome, returning whether it completed
+    async fn process(app_state: AppState, job: QueuedJob) -> bool {
+        let QueuedJob {
+            transcript_id,
+            slot,
+            progress_tx,
+            ..
+        } = job;
+
+        // Frees the user's slot whether the job completes or fails
+        let _slot = slot;
+
+        let status = match Self::run(&app_state, transcript_id, progress_tx.clone()).await {
+            Ok(()) => transcript_status::COMPLETED,
+            Err(e) => {
+                log::error!("Transcription job {} failed: {}", transcript_id, e);
+
+                if let Err(e) =
+                    TranscriptionService::mark_failed(&app_state.db, transcript_id, &e.to_string())
+                        .await
+                {
+                    log::error!("Failed to mark job {} as failed: {}", transcript_id, e);
+                }
+
+                transcript_status::FAILED
+            }
+        };
+
+        // Notify subscribers and drop the channel so the map doesn't grow unbounded
+        progress_tx.send(ProgressEvent::Done(status.to_string())).ok();
+        WebhookService::notify_job_finished(&app_state, transcript_id);
+        events::publish_job_finished(&app_state, transcript_id);
+        if let Ok(mut channels) = app_state.progress_channels.lock() {
+            channels.remove(&transcript_id);
+        }
+
+        status == transcript_status::COMPLETED
+    }
+
+    /// Current queue depth and how long a job can expect to wait for a worker
+    /// `queued_audio_seconds` is the length of a job already waiting, which is left out of the
+    /// wait; pass `None` for a job yet to be queued. Running jobs are assumed to be half done,
+    /// and the estimate is unknown until a job has completed since startup.
+    pub fn queue_status(app_state: &AppState, queued_audio_seconds: Option<f64>) -> QueueStatus {
+        let queue = &app_state.job_queue;
+        let workers = app_state.config.transcription_workers;
+        let queue_depth = queue.waiting_count();
+        let running_jobs = app_state.jobs.active_count();
+        let jobs_ahead = queue_depth.saturating_sub(usize::from(queued_audio_seconds.is_some()));
+
+        let estimated_wait_seconds = if jobs_ahead == 0 && running_jobs < workers {
+            Some(0)
+        } else {
+            app_state.processing_rate.seconds_per_audio_second().map(|rate| {
+                let waiting = queue.waiting_audio_seconds() - queued_audio_seconds.unwrap_or(0.0);
+                let audio_ahead = waiting.max(0.0) + queue.running_audio_seconds() / 2.0;
+                (audio_ahead * rate / workers as f64).ceil() as u64
+            })
+        };
+
+        QueueStatus {
+            queue_depth,
+            running_jobs,
+            estimated_wait_seconds,
+        }
+    }
+
+    /// Subscribe to progress updates of a queued or running job
+    pub fn subscribe(
+        app_state: &AppState,
+        transcript_id: Uuid,
+    ) -> Option<broadcast::Receiver<ProgressEvent>> {
+        app_state
+            .progress_channels
+            .lock()
+            .ok()?
+            .get(&transcript_id)
+            .map(|sender| sender.subscribe())
+    }
+
+    /// Re-queue jobs that were pending or interrupted mid-processing by a restart
+    pub async fn requeue_interrupted(app_state: &AppState) -> AppResult<usize> {
+        let pending = TranscriptionService::reset_interrupted_jobs(&app_state.db).await?;
+        let count = pending.len();
+
+        // The backlog may be larger than the queue, so wait for room without delaying startup
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            for transcript in pending {
+                match app_state.job_queue.reserve().await {
+                    Ok(permit) => Self::enqueue(
+                        &app_state,
+                        transcript.id,
+                        transcript.duration_seconds,
+                        None,
+                        permit,
+                    ),
+                    Err(e) => {
+                        log::error!("Failed to re-queue job {}: {}", transcript.id, e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(count)
+    }
+
+    /// Wait for running jobs to finish, then return any unfinished ones to the queue
+    pub async fn drain(app_state: &AppState, timeout: Duration) -> AppResult<()> {
+        app_state.jobs.stop();
+
+        let waiting = app_state.job_queue.waiting_count();
+        if waiting > 0 {
+            log::info!("{} queued transcription jobs stay pending until the next start", waiting);
+        }
+
+        let active = app_state.jobs.active_count();
+        if active > 0 {
+            log::info!("Waiting up to {}s for {} running transcription jobs", timeout.as_secs(), active);
+        }
+
+        if !app_state.jobs.wait_idle(timeout).await {
+            log::warn!(
+                "Shutdown timeout reached with {} transcription jobs still running",
+                app_state.jobs.active_count()
+            );
+        }
+
+        let requeued = TranscriptionService::reset_processing_jobs(&app_state.db).await?;
+        if requeued > 0 {
+            log::info!("Returned {} unfinished transcription jobs to pending", requeued);
+        }
+
+        TranscriptionService::cleanup_process_temp_files(&app_state.config.temp_dir).await;
+        Ok(())
+    }
+
+    /// Execute a single transcription job and record its outcome
+    async fn run(
+        app_state: &AppState,
+        transcript_id: Uuid,
+        progress_tx: broadcast::Sender<ProgressEvent>,
+    ) -> AppResult<()> {
+        let transcript =
+            TranscriptionService::get_transcript_for_job(&app_state.db, transcript_id).await?;
+
+        let audio_path = transcript.audio_path.ok_or_else(|| {
+            AppError::FileError("Uploaded audio is no longer available".to_string())
+        })?;
+
+        if !tokio::fs::try_exists(&audio_path).await.unwrap_or(false) {
+            return Err(AppError::FileError(
+                "Uploaded audio is no longer available".to_string(),
+            ));
+        }
+
+        TranscriptionService::mark_processing(&app_state.db, transcript_id).await?;
+        log::info!("Processing transcription job: {}", transcript_id);
+        let started = Instant::now();
+
+        // Lazily loaded models are loaded here, before the job's timeout starts
+        let (model_name, whisper_ctx) =
+            app_state.whisper_models.get(transcript.model_name.as_deref()).await?;
+
+        // Whisper hands finished segments to this task, which streams and persists them
+        // off the inference thread
+        let (segment_tx, mut segment_rx) = mpsc::unbounded_channel::<TranscriptSegment>();
+        let segment_db = app_state.db.clone();
+        let segment_progress_tx = progress_tx.clone();
+        let partial_segments = tokio::spawn(
+            async move {
+                let mut index = 0;
+                while let Some(segment) = segment_rx.recv().await {
+                    if let Err(e) = TranscriptionService::save_partial_segment(
+                        &segment_db,
+                        transcript_id,
+                        index,
+                        &segment,
+                    )
+                    .await
+                    {
+                        log::warn!("Failed to save partial segment of job {}: {}", transcript_id, e);
+                    }
+                    segment_progress_tx.send(ProgressEvent::Segment(segment)).ok();
+                    index += 1;
+                }
+            }
+            .instrument(tracing::Span::current()),
+        );
+
+        let timeout = Duration::from_secs(app_state.config.transcription_timeout_seconds);
+        let transcription = TranscriptionService::transcribe_audio(
+            whisper_ctx,
+            &audio_path,
+            &app_state.config,
+            &app_state.ffmpeg,
+            &app_state.whisper_states,
+            TranscribeOptions {
+                language: transcript.language.as_deref(),
+                trim_silence: transcript.trim_silence,
+                initial_prompt: transcript.initial_prompt.as_deref(),
+                text_format: &transcript.text_format,
+                word_timestamps: transcript.word_timestamps,
+                postprocess: PostProcessOptions {
+                    capitalize_sentences: transcript.capitalize_sentences,
+                    numbers_to_digits: transcript.numbers_to_digits,
+                },
+                decoding: DecodingOptions {
+                    temperature: transcript.temperature,
+                    temperature_inc: transcript.temperature_inc,
+                    ..DecodingOptions::from_config(&app_state.config)
+                },
+                audio_filters: &transcript.audio_filters,
+            },
+            Some(Box::new(move |progress| {
+                progress_tx.send(ProgressEvent::Progress(progress)).ok();
+            })),
+            Some(Arc::new(move |segment| {
+                segment_tx.send(segment).ok();
+            })),
+        );
+
+        let result = match tokio::time::timeout(timeout, transcription).await {
+            Ok(result) => {
+                // The callback is dropped with Whisper's params, so this ends once the last
+                // segment is saved and can't race the final write below
+                partial_segments.await.ok();
+                result
+            }
+            Err(_) => {
+                // Dropping the transcription stops Whisper at its next step; any segments it
+                // reports before then are discarded and the job fails here
+                partial_segments.abort();
+                Err(AppError::Timeout(format!(
+                    "Transcription did not finish within {} seconds",
+                    timeout.as_secs()
+                )))
+            }
+        };
+
+        // Speaker labels are best-effort: a missing or failing helper only loses the labels
+        let result = match result {
+            Ok(mut result) if transcript.diarize => {
+                match app_state.config.diarization_cmd.as_deref() {
+                    Some(command) => match DiarizationService::diarize(command, &audio_path).await {
+                        Ok(turns) => DiarizationService::assign_speakers(&mut result.segments, &turns),
+                        Err(e) => log::warn!("Diarization skipped for job {}: {}", transcript_id, e),
+                    },
+                    None => log::warn!(
+                        "Diarization requested for job {} but DIARIZATION_CMD is not set",
+                        transcript_id
+                    ),
+                }
+                Ok(result)
+            }
+            result => result,
+        };
+
+        // The uploaded audio is no longer needed once Whisper has run
+        tokio::fs::remove_file(&audio_path).await.ok();
+
+        let mut result = result?;
+        result.processing_seconds = started.elapsed().as_secs_f64();
+        TranscriptionService::complete_transcription(
+            &app_state.db,
+            transcript_id,
+            &result,
+            &model_name,
+        )
+        .await?;
+
+        log::info!("Transcription job completed: {}", transcript_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use crate::models::TranscriptSegment;
+use crate::services::{WHISPER_SAMPLE_RATE, samples_to_ms};
+
+/// Rolling buffer of live 16kHz mono audio awaiting transcription.
+/// Whisper re-runs over the pending audio every `step` samples to produce partial text;
+/// once the pending audio reaches `window` samples its segments are finalized and dropped.
+pub struct LiveBuffer {
+    pending: Vec<f32>,
+    /// Position of `pending[0]` in the whole stream, in samples
+    offset: usize,
+    /// Samples received since Whisper last ran
+    unprocessed: usize,
+    /// Byte of a 16-bit sample split across two messages
+    carry: Option<u8>,
+    step: usize,
+    window: usize,
+}
+
+impl LiveBuffer {
+    pub fn new(step_seconds: u64, window_seconds: u64) -> Self {
+        Self {
+            pending: Vec::new(),
+            offset: 0,
+            unprocessed: 0,
+            carry: None,
+            step: step_seconds as usize * WHISPER_SAMPLE_RATE,
+            window: window_seconds as usize * WHISPER_SAMPLE_RATE,
+        }
+    }
+
+    /// Append 16-bit little-endian PCM bytes
+    pub fn push_pcm(&mut self, bytes: &[u8]) {
+        let mut bytes = bytes;
+        if let Some(low) = self.carry.take() {
+            match bytes.split_first() {
+                Some((high, rest)) => {
+                    self.push_sample(i16::from_le_bytes([low, *high]));
+                    bytes = rest;
+                }
+                None => {
+                    self.carry = Some(low);
+                    return;
+                }
+            }
+        }
+
+        let mut samples = bytes.chunks_exact(2);
+        for sample in samples.by_ref() {
+            self.push_sample(i16::from_le_bytes([sample[0], sample[1]]));
+        }
+        self.carry = samples.remainder().first().copied();
+    }
+
+    fn push_sample(&mut self, sample: i16) {
+        self.pending.push(sample as f32 / 32768.0);
+        self.unprocessed += 1;
+    }
+
+    /// Whether enough new audio arrived to run Whisper again
+    pub fn is_due(&self) -> bool {
+        self.unprocessed >= self.step
+    }
+
+    /// Whether the pending audio is long enough to finalize
+    pub fn is_full(&self) -> bool {
+        self.pending.len() >= self.window
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Seconds of audio received over the whole stream
+    pub fn received_seconds(&self) -> f64 {
+        (self.offset + self.pending.len()) as f64 / WHISPER_SAMPLE_RATE as f64
+    }
+
+    /// Copy of the pending audio and its start time, padded to the one second Whisper needs
+    pub fn snapshot(&mut self) -> (Vec<f32>, i64) {
+        self.unprocessed = 0;
+
+        let mut samples = self.pending.clone();
+        if samples.len() < WHISPER_SAMPLE_RATE {
+            samples.resize(WHISPER_SAMPLE_RATE, 0.0);
+        }
+        (samples, samples_to_ms(self.offset))
+    }
+
+    /// Finalize segments transcribed from a `snapshot`, dropping their audio.
+    /// Unless `flush` is set, the last segment stays pending because its words may
+    /// continue in audio that hasn't arrived yet.
+    pub fn commit(
+        &mut self,
+        mut segments: Vec<TranscriptSegment>,
+        flush: bool,
+    ) -> Vec<TranscriptSegment> {
+        let stream_end_ms = samples_to_ms(self.offset + self.pending.len());
+        for segment in segments.iter_mut() {
+            // Padding may stretch the last segment past the audio actually received
+            segment.end_ms = segment.end_ms.min(stream_end_ms);
+        }
+
+        // Never keep more than two windows buffered, even if Whisper returns one long segment
+        let overflowing = self.pending.len() >= self.window * 2;
+        if !flush && !overflowing && segments.len() > 1
+            && let Some(last) = segments.last()
+        {
+            let cut_ms = last.start_ms - samples_to_ms(self.offset);
+            let cut = (cut_ms.max(0) as usize * WHISPER_SAMPLE_RATE / 1000).min(self.pending.len());
+            if cut > 0 {
+                segments.pop();
+                self.pending.drain(..cut);
+                self.offset += cut;
+                self.unprocessed = self.pending.len();
+                return segments;
+            }
+        }
+
+        self.offset += self.pending.len();
+        self.pending.clear();
+        self.unprocessed = 0;
+        segments
+    }
+}
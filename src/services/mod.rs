@@ -1,4 +1,23 @@
 mod user_service;
 mod transcription_service;
+mod job_service;
+mod diarization_service;
+mod webhook_service;
+mod idempotency_service;
+mod live_service;
+mod share_service;
+mod model_service;
+mod upload_service;
+pub mod diff;
+pub mod export;
+pub mod postprocess;
 pub use user_service::*;
 pub use transcription_service::*;
+pub use job_service::*;
+pub use diarization_service::*;
+pub use webhook_service::*;
+pub use idempotency_service::*;
+pub use live_service::*;
+pub use share_service::*;
+pub use model_service::*;
+pub use upload_service::*;
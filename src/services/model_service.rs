@@ -0,0 +1,157 @@
+use crate::config::Config;
+use crate::errors::{AppError, AppResult};
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use whisper_rs::{WhisperContext, WhisperContextParameters};
+
+/// Loaded Whisper models, least recently used first
+type LoadedModels = Vec<(String, Arc<WhisperContext>)>;
+
+/// The configured Whisper models, loaded at startup or on first use
+#[derive(Clone)]
+pub struct WhisperModels {
+    paths: Arc<BTreeMap<String, String>>,
+    default_model: Arc<str>,
+    use_gpu: bool,
+    lazy: bool,
+    max_loaded: Option<usize>,
+    loaded: Arc<Mutex<LoadedModels>>,
+    // One lock per model, so concurrent first uses load it once without blocking other models
+    load_locks: Arc<DashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl WhisperModels {
+    /// Load the default model, and every other model unless `WHISPER_LAZY_LOAD` is set
+    pub async fn load(config: &Config) -> AppResult<Self> {
+        let models = Self {
+            paths: Arc::new(config.whisper_models.clone()),
+            default_model: Arc::from(config.whisper_default_model.as_str()),
+            use_gpu: config.whisper_use_gpu,
+            lazy: config.whisper_lazy_load,
+            max_loaded: config.whisper_max_loaded_models,
+            loaded: Arc::new(Mutex::new(Vec::new())),
+            load_locks: Arc::new(DashMap::new()),
+        };
+
+        models.get(None).await?;
+        if !models.lazy {
+            for name in models.paths.keys() {
+                models.get(Some(name)).await?;
+            }
+        }
+
+        Ok(models)
+    }
+
+    /// Name of the model uploads use when they don't choose one
+    pub fn default_model(&self) -> &str {
+        &self.default_model
+    }
+
+    /// Number of models currently in memory
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.lock().map(|loaded| loaded.len()).unwrap_or(0)
+    }
+
+    /// Whether the default model is loaded, or its file is there to be loaded again
+    /// after eviction
+    pub async fn default_model_available(&self) -> bool {
+        let loaded = self
+            .loaded
+            .lock()
+            .map(|loaded| loaded.iter().any(|(name, _)| name == self.default_model()))
+            .unwrap_or(false);
+        if loaded {
+            return true;
+        }
+
+        match self.paths.get(self.default_model()) {
+            Some(path) => tokio::fs::try_exists(path).await.unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// The model with the given name, or the default model when it isn't configured
+    /// (e.g. a job queued before the model was removed from `WHISPER_MODELS`), loading it if needed
+    pub async fn get(&self, name: Option<&str>) -> AppResult<(String, Arc<WhisperContext>)> {
+        let name = name
+            .filter(|name| self.paths.contains_key(*name))
+            .unwrap_or(self.default_model())
+            .to_string();
+
+        if let Some(whisper_ctx) = self.touch(&name) {
+            return Ok((name, whisper_ctx));
+        }
+
+        let load_lock = self
+            .load_locks
+            .entry(name.clone())
+            .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
+            .clone();
+        let _loading = load_lock.lock().await;
+
+        // Another request may have loaded it while this one waited for the lock
+        if let Some(whisper_ctx) = self.touch(&name) {
+            return Ok((name, whisper_ctx));
+        }
+
+        let whisper_ctx = self.load_model(&name).await?;
+        self.insert(&name, whisper_ctx.clone());
+        Ok((name, whisper_ctx))
+    }
+
+    /// Loaded model by name, marking it as the most recently used
+    fn touch(&self, name: &str) -> Option<Arc<WhisperContext>> {
+        let mut loaded = self.loaded.lock().ok()?;
+        let position = loaded.iter().position(|(loaded_name, _)| loaded_name == name)?;
+        let entry = loaded.remove(position);
+        let whisper_ctx = entry.1.clone();
+        loaded.push(entry);
+        Some(whisper_ctx)
+    }
+
+    /// Read a model file on a blocking thread
+    async fn load_model(&self, name: &str) -> AppResult<Arc<WhisperContext>> {
+        let path = self.paths[name].clone();
+        let use_gpu = self.use_gpu;
+        log::info!("Loading Whisper model {} from: {}", name, path);
+
+        let started = Instant::now();
+        let whisper_ctx = tokio::task::spawn_blocking(move || {
+            WhisperContext::new_with_params(&path, WhisperContextParameters { use_gpu })
+        })
+        .await
+        .map_err(|e| AppError::WhisperError(format!("Model loading task failed: {}", e)))?
+        .map_err(|e| AppError::WhisperError(format!("Failed to load Whisper model {}: {}", name, e)))?;
+
+        log::info!(
+            "Whisper model {} loaded in {} ms (GPU mode: {})",
+            name,
+            started.elapsed().as_millis(),
+            if use_gpu { "enabled" } else { "disabled" }
+        );
+        Ok(Arc::new(whisper_ctx))
+    }
+
+    /// Add a freshly loaded model, evicting the least recently used ones over `WHISPER_MAX_LOADED_MODELS`
+    /// Jobs still holding an evicted model keep it alive until they finish
+    fn insert(&self, name: &str, whisper_ctx: Arc<WhisperContext>) {
+        let Ok(mut loaded) = self.loaded.lock() else {
+            return;
+        };
+        loaded.push((name.to_string(), whisper_ctx));
+
+        if let Some(max_loaded) = self.max_loaded {
+            while loaded.len() > max_loaded {
+                let (evicted, whisper_ctx) = loaded.remove(0);
+                log::info!(
+                    "Evicted Whisper model {} ({} in use by running jobs)",
+                    evicted,
+                    Arc::strong_count(&whisper_ctx) - 1
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use crate::models::TranscriptSegment;
+
+/// Optional clean-up passes over Whisper's segment texts
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostProcessOptions {
+    /// Start every sentence with a capital letter
+    pub capitalize_sentences: bool,
+    /// Write spoken English numbers as digits ("twenty five" becomes "25")
+    pub numbers_to_digits: bool,
+}
+
+/// Post-process segment texts in place; `language` is the transcript's language, if known
+/// Numbers are only converted for English (or unknown-language) transcripts. Segments are
+/// processed one at a time, so a number spoken across two segments is left as words.
+pub fn apply(segments: &mut [TranscriptSegment], options: PostProcessOptions, language: Option<&str>) {
+    let convert_numbers = options.numbers_to_digits && language.is_none_or(|language| language == "en");
+    let mut sentence_start = true;
+
+    for segment in segments.iter_mut() {
+        if convert_numbers {
+            segment.text = numbers_to_digits(&segment.text);
+        }
+        if options.capitalize_sentences {
+            segment.text = capitalize_sentences(&segment.text, sentence_start);
+        }
+        if !segment.text.trim().is_empty() {
+            sentence_start = ends_sentence(&segment.text);
+        }
+    }
+}
+
+/// Capitalize the first letter of each sentence; `sentence_start` says whether the text begins one
+/// Sentences end at `.`, `?` or `!` followed by whitespace, so decimals like "3.5" are left alone.
+/// Whitespace is kept exactly as it was.
+pub fn capitalize_sentences(text: &str, sentence_start: bool) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut capitalize_next = sentence_start;
+    let mut after_terminator = false;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if after_terminator {
+                capitalize_next = true;
+                after_terminator = false;
+            }
+            output.push(c);
+            continue;
+        }
+
+        if capitalize_next && c.is_alphabetic() {
+            output.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            if c.is_alphanumeric() {
+                capitalize_next = false;
+            }
+            output.push(c);
+        }
+
+        // Closing quotes and brackets don't end the sentence's final punctuation
+        if matches!(c, '.' | '?' | '!') {
+            after_terminator = true;
+        } else if !matches!(c, '"' | '\'' | ')' | ']' | '”' | '’') {
+            after_terminator = false;
+        }
+    }
+
+    output
+}
+
+/// Whether a text ends with sentence-final punctuation
+pub fn ends_sentence(text: &str) -> bool {
+    text.trim_end()
+        .trim_end_matches(['"', '\'', ')', ']', '”', '’'])
+        .ends_with(['.', '?', '!'])
+}
+
+/// Replace spoken English cardinal numbers with digits, e.g. "two hundred and five" with "205"
+/// A lone number word below ten ("one of them") is kept, as are ordinals, decimals and numbers
+/// interrupted by punctuation. Whitespace and trailing punctuation are kept as they were.
+pub fn numbers_to_digits(text: &str) -> String {
+    let tokens = tokenize(text);
+    let mut output = String::with_capacity(text.len());
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match parse_number(&tokens[index..]) {
+            Some((consumed, value)) => {
+                let last = strip_punctuation(tokens[index + consumed - 1].word).1;
+                output.push_str(tokens[index].space);
+                output.push_str(&value.to_string());
+                output.push_str(last);
+                index += consumed;
+            }
+            None => {
+                output.push_str(tokens[index].space);
+                output.push_str(tokens[index].word);
+                index += 1;
+            }
+        }
+    }
+
+    output.push_str(trailing_space(text));
+    output
+}
+
+/// A word and the whitespace before it
+struct Token<'a> {
+    space: &'a str,
+    word: &'a str,
+}
+
+/// Split text into words, keeping the whitespace in front of each
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+
+    loop {
+        let word_start = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+        if word_start == rest.len() {
+            break;
+        }
+        let word_end = rest[word_start..]
+            .find(char::is_whitespace)
+            .map_or(rest.len(), |end| word_start + end);
+
+        tokens.push(Token {
+            space: &rest[..word_start],
+            word: &rest[word_start..word_end],
+        });
+        rest = &rest[word_end..];
+    }
+
+    tokens
+}
+
+/// Whitespace after the last word
+fn trailing_space(text: &str) -> &str {
+    &text[text.trim_end().len()..]
+}
+
+/// Split a word into its text and trailing punctuation
+fn strip_punctuation(word: &str) -> (&str, &str) {
+    let core = word.trim_end_matches(|c: char| c.is_ascii_punctuation() && c != '-');
+    (core, &word[core.len()..])
+}
+
+/// Parse the longest number at the start of `tokens`, returning the tokens it spans and its value
+fn parse_number(tokens: &[Token<'_>]) -> Option<(usize, u64)> {
+    let mut parser = NumberParser::default();
+    let mut parsed: Option<(usize, u64)> = None;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let (core, punctuation) = strip_punctuation(token.word);
+
+        // "and" may join a hundred or a scale to the rest, as in "one hundred and five"
+        if core.eq_ignore_ascii_case("and") && punctuation.is_empty() && parser.accepts_and() {
+            continue;
+        }
+
+        let mut next = parser.clone();
+        let words: Option<Vec<NumberWord>> = core.split('-').map(NumberWord::parse).collect();
+        match words {
+            Some(words) if words.iter().all(|&word| next.push(word)) => parser = next,
+            _ => break,
+        }
+        parsed = Some((index + 1, parser.value()));
+
+        if !punctuation.is_empty() {
+            break;
+        }
+    }
+
+    // A lone small number usually reads better as a word
+    parsed.filter(|(consumed, value)| *consumed > 1 || *value >= 10 || parser.words > 1)
+}
+
+/// English number word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberWord {
+    Zero,
+    /// One to nine
+    Unit(u64),
+    /// Ten to nineteen
+    Teen(u64),
+    /// Twenty, thirty, ... ninety
+    Tens(u64),
+    Hundred,
+    /// Thousand, million or billion
+    Scale(u64),
+}
+
+impl NumberWord {
+    fn parse(word: &str) -> Option<Self> {
+        let word = word.to_lowercase();
+        let number = match word.as_str() {
+            "zero" => Self::Zero,
+            "one" => Self::Unit(1),
+            "two" => Self::Unit(2),
+            "three" => Self::Unit(3),
+            "four" => Self::Unit(4),
+            "five" => Self::Unit(5),
+            "six" => Self::Unit(6),
+            "seven" => Self::Unit(7),
+            "eight" => Self::Unit(8),
+            "nine" => Self::Unit(9),
+            "ten" => Self::Teen(10),
+            "eleven" => Self::Teen(11),
+            "twelve" => Self::Teen(12),
+            "thirteen" => Self::Teen(13),
+            "fourteen" => Self::Teen(14),
+            "fifteen" => Self::Teen(15),
+            "sixteen" => Self::Teen(16),
+            "seventeen" => Self::Teen(17),
+            "eighteen" => Self::Teen(18),
+            "nineteen" => Self::Teen(19),
+            "twenty" => Self::Tens(20),
+            "thirty" => Self::Tens(30),
+            "forty" => Self::Tens(40),
+            "fifty" => Self::Tens(50),
+            "sixty" => Self::Tens(60),
+            "seventy" => Self::Tens(70),
+            "eighty" => Self::Tens(80),
+            "ninety" => Self::Tens(90),
+            "hundred" => Self::Hundred,
+            "thousand" => Self::Scale(1_000),
+            "million" => Self::Scale(1_000_000),
+            "billion" => Self::Scale(1_000_000_000),
+            _ => return None,
+        };
+        Some(number)
+    }
+}
+
+/// Accumulates number words, rejecting sequences that aren't a single number ("one two")
+#[derive(Debug, Clone, Default)]
+struct NumberParser {
+    total: u64,
+    current: u64,
+    last: Option<NumberWord>,
+    last_scale: Option<u64>,
+    words: usize,
+}
+
+impl NumberParser {
+    /// Add the next word, or return false if it can't continue this number
+    fn push(&mut self, word: NumberWord) -> bool {
+        use NumberWord::*;
+
+        let allowed = match (word, self.last) {
+            (Zero, None) => true,
+            (Unit(_), None | Some(Tens(_)) | Some(Hundred) | Some(Scale(_))) => true,
+            (Teen(_) | Tens(_), None | Some(Hundred) | Some(Scale(_))) => true,
+            // "five hundred", "nineteen hundred", "twenty five hundred"
+            (Hundred, Some(Unit(_) | Teen(_) | Tens(_))) => self.current < 100,
+            (Scale(scale), Some(Unit(_) | Teen(_) | Tens(_) | Hundred)) => {
+                self.last_scale.is_none_or(|last_scale| scale < last_scale)
+            }
+            _ => false,
+        };
+        if !allowed {
+            return false;
+        }
+
+        match word {
+            Zero => {}
+            Unit(value) | Teen(value) | Tens(value) => self.current += value,
+            Hundred => self.current *= 100,
+            Scale(scale) => {
+                self.total += self.current * scale;
+                self.current = 0;
+                self.last_scale = Some(scale);
+            }
+        }
+        self.last = Some(word);
+        self.words += 1;
+        true
+    }
+
+    /// Whether "and" can follow, i.e. right after a hundred or a scale word
+    fn accepts_and(&self) -> bool {
+        matches!(self.last, Some(NumberWord::Hundred | NumberWord::Scale(_)))
+    }
+
+    fn value(&self) -> u64 {
+        self.total + self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str) -> TranscriptSegment {
+        TranscriptSegment {
+            start_ms: 0,
+            end_ms: 1000,
+            text: text.to_string(),
+            speaker: None,
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    fn texts(segments: &[TranscriptSegment]) -> Vec<&str> {
+        segments.iter().map(|segment| segment.text.as_str()).collect()
+    }
+
+    #[test]
+    fn compound_numbers_become_digits() {
+        assert_eq!(numbers_to_digits("twenty five"), "25");
+        assert_eq!(numbers_to_digits("two hundred and five"), "205");
+        assert_eq!(numbers_to_digits("about twenty-five people"), "about 25 people");
+    }
+
+    #[test]
+    fn hundreds_and_scales_combine() {
+        assert_eq!(numbers_to_digits("one thousand two hundred"), "1200");
+        assert_eq!(numbers_to_digits("twenty five hundred"), "2500");
+        assert_eq!(numbers_to_digits("five hundred thousand"), "500000");
+        assert_eq!(numbers_to_digits("two million three hundred thousand and one"), "2300001");
+    }
+
+    #[test]
+    fn lone_small_numbers_stay_words() {
+        assert_eq!(numbers_to_digits("one of them"), "one of them");
+        assert_eq!(numbers_to_digits("nine lives"), "nine lives");
+        assert_eq!(numbers_to_digits("ten minutes"), "10 minutes");
+        // Not a single number, so neither word is converted
+        assert_eq!(numbers_to_digits("one two"), "one two");
+    }
+
+    #[test]
+    fn trailing_punctuation_and_whitespace_are_kept() {
+        assert_eq!(numbers_to_digits("It took twenty five minutes."), "It took 25 minutes.");
+        assert_eq!(numbers_to_digits(" costs thirty five!"), " costs 35!");
+        assert_eq!(numbers_to_digits("  forty  two  "), "  42  ");
+    }
+
+    #[test]
+    fn and_without_a_following_number_is_left_in_place() {
+        assert_eq!(numbers_to_digits("one hundred and then some"), "100 and then some");
+        assert_eq!(numbers_to_digits("twenty and thirty"), "20 and 30");
+    }
+
+    #[test]
+    fn punctuation_splits_numbers() {
+        assert_eq!(numbers_to_digits("twenty, five"), "20, five");
+        assert_eq!(numbers_to_digits("thirty, forty"), "30, 40");
+    }
+
+    #[test]
+    fn sentences_are_capitalized() {
+        assert_eq!(
+            capitalize_sentences("hello there. how are you? fine!", true),
+            "Hello there. How are you? Fine!"
+        );
+        assert_eq!(capitalize_sentences("and then it stopped", false), "and then it stopped");
+        assert_eq!(capitalize_sentences("\"done.\" next", true), "\"Done.\" Next");
+    }
+
+    #[test]
+    fn decimals_do_not_end_sentences() {
+        assert_eq!(
+            capitalize_sentences("the rate is 3.5 percent. next year", true),
+            "The rate is 3.5 percent. Next year"
+        );
+    }
+
+    #[test]
+    fn sentence_state_carries_across_segments() {
+        let mut segments = vec![
+            segment(" hello there."),
+            segment(" how are you"),
+            segment(" "),
+            segment(" doing today? fine"),
+        ];
+        let options = PostProcessOptions {
+            capitalize_sentences: true,
+            numbers_to_digits: false,
+        };
+
+        apply(&mut segments, options, Some("en"));
+
+        assert_eq!(texts(&segments), vec![" Hello there.", " How are you", " ", " doing today? Fine"]);
+    }
+
+    #[test]
+    fn numbers_are_only_converted_for_english() {
+        let options = PostProcessOptions {
+            capitalize_sentences: false,
+            numbers_to_digits: true,
+        };
+
+        let mut english = vec![segment("twenty five")];
+        apply(&mut english, options, Some("en"));
+        let mut unknown = vec![segment("twenty five")];
+        apply(&mut unknown, options, None);
+        let mut german = vec![segment("twenty five")];
+        apply(&mut german, options, Some("de"));
+
+        assert_eq!(texts(&english), vec!["25"]);
+        assert_eq!(texts(&unknown), vec!["25"]);
+        assert_eq!(texts(&german), vec!["twenty five"]);
+    }
+}
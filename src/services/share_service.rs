@@ -0,0 +1,276 @@
+use crate::errors::{AppError, AppResult};
+use crate::utils::token;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Formats a transcript can be shared in
+pub const SHARE_FORMATS: &[&str] = &["txt", "srt", "json"];
+
+/// Longest a share link may stay valid (30 days)
+pub const MAX_SHARE_LINK_HOURS: i64 = 720;
+
+/// Transcript a verified share token grants read access to
+#[derive(Debug, Clone)]
+pub struct SharedTranscript {
+    pub transcript_id: Uuid,
+    pub user_id: Uuid,
+    pub format: String,
+}
+
+/// Share service for signed, time-limited transcript links
+///
+/// A token is `<transcript_id>.<expires_unix>.<format>.<signature>`, where the signature is a
+/// hex HMAC-SHA256 of the rest keyed by the owner's share secret
+pub struct ShareService;
+
+impl ShareService {
+    /// Mint a share token for a transcript, valid until `expires_at`
+    pub async fn create_token(
+        pool: &PgPool,
+        user_id: Uuid,
+        transcript_id: Uuid,
+        format: &str,
+        expires_at: DateTime<Utc>,
+    ) -> AppResult<String> {
+        // Generated on first use; COALESCE keeps the secret a concurrent request already set
+        let secret: String = sqlx::query_scalar(
+            "UPDATE users SET share_secret = COALESCE(share_secret, $2) WHERE id = $1 RETURNING share_secret",
+        )
+        .bind(user_id)
+        .bind(token::generate_secure_token())
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        let payload = format!("{}.{}.{}", transcript_id, expires_at.timestamp(), format);
+        let signature = hex::encode(Self::mac(&secret, &payload).finalize().into_bytes());
+
+        Ok(format!("{}.{}", payload, signature))
+    }
+
+    /// Check a share token's signature and expiry
+    /// Every failure is reported the same way, so tokens can't be probed
+    pub async fn verify_token(pool: &PgPool, share_token: &str) -> AppResult<SharedTranscript> {
+        let invalid = || AppError::NotFound("Share link is invalid or has expired".to_string());
+
+        let (payload, signature) = share_token.rsplit_once('.').ok_or_else(invalid)?;
+        let mut parts = payload.splitn(3, '.');
+        let (Some(transcript_id), Some(expires), Some(format)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+
+        let transcript_id = Uuid::parse_str(transcript_id).map_err(|_| invalid())?;
+        let expires: i64 = expires.parse().map_err(|_| invalid())?;
+        if expires <= Utc::now().timestamp() || !SHARE_FORMATS.contains(&format) {
+            return Err(invalid());
+        }
+
+        // Deleted transcripts and disabled accounts stop being shared
+        let (user_id, secret): (Uuid, Option<String>) = sqlx::query_as(
+            r#"
+            SELECT t.user_id, u.share_secret FROM transcripts t
+            JOIN users u ON u.id = t.user_id
+            WHERE t.id = $1 AND t.deleted_at IS NULL AND u.disabled_at IS NULL
+            "#,
+        )
+        .bind(transcript_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(invalid)?;
+
+        let secret = secret.ok_or_else(invalid)?;
+        let signature = hex::decode(signature).map_err(|_| invalid())?;
+        Self::mac(&secret, payload)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        Ok(SharedTranscript {
+            transcript_id,
+            user_id,
+            format: format.to_string(),
+        })
+    }
+
+    /// Revoke every share link of a user by discarding their share secret
+    pub async fn revoke_all(pool: &PgPool, user_id: Uuid) -> AppResult<()> {
+        sqlx::query("UPDATE users SET share_secret = NULL WHERE id = $1")
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+
+        log::info!("Share links revoked for user: {}", user_id);
+        Ok(())
+    }
+
+    fn mac(secret: &str, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{text_format, NewTranscriptionJob};
+    use crate::services::{TranscriptionService, UserService};
+    use chrono::Duration;
+
+    async fn create_user(pool: &PgPool, email: &str) -> Uuid {
+        let params = argon2::Params::new(8, 1, 1, None).unwrap();
+        UserService::register_user(pool, email, "Password 1", &params)
+            .await
+            .unwrap()
+            .id
+    }
+
+    async fn create_transcript(pool: &PgPool, user_id: Uuid) -> Uuid {
+        let job = NewTranscriptionJob {
+            filename: "shared.wav".to_string(),
+            file_size: 1024,
+            duration_seconds: Some(1.0),
+            language: None,
+            audio_path: "/tmp/shared.wav".to_string(),
+            diarize: false,
+            trim_silence: false,
+            initial_prompt: None,
+            text_format: text_format::PLAIN.to_string(),
+            word_timestamps: false,
+            model_name: "base".to_string(),
+            capitalize_sentences: false,
+            numbers_to_digits: false,
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            audio_filters: Vec::new(),
+            source_ip: None,
+            user_agent: None,
+        };
+        TranscriptionService::create_transcription_job(pool, user_id, &job)
+            .await
+            .unwrap()
+            .id
+    }
+
+    fn in_a_day() -> DateTime<Utc> {
+        Utc::now() + Duration::days(1)
+    }
+
+    async fn assert_rejected(pool: &PgPool, share_token: &str) {
+        let result = ShareService::verify_token(pool, share_token).await;
+        assert!(
+            matches!(result, Err(AppError::NotFound(_))),
+            "{} was accepted",
+            share_token
+        );
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn valid_token_grants_access(pool: PgPool) {
+        let user_id = create_user(&pool, "share@example.com").await;
+        let transcript_id = create_transcript(&pool, user_id).await;
+
+        let share_token =
+            ShareService::create_token(&pool, user_id, transcript_id, "srt", in_a_day())
+                .await
+                .unwrap();
+        let shared = ShareService::verify_token(&pool, &share_token).await.unwrap();
+
+        assert_eq!(shared.transcript_id, transcript_id);
+        assert_eq!(shared.user_id, user_id);
+        assert_eq!(shared.format, "srt");
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn tampered_tokens_are_rejected(pool: PgPool) {
+        let user_id = create_user(&pool, "tamper@example.com").await;
+        let transcript_id = create_transcript(&pool, user_id).await;
+        let other_transcript_id = create_transcript(&pool, user_id).await;
+        let expires_at = in_a_day();
+        let share_token =
+            ShareService::create_token(&pool, user_id, transcript_id, "txt", expires_at)
+                .await
+                .unwrap();
+        let (payload, signature) = share_token.rsplit_once('.').unwrap();
+
+        // A changed signature
+        let flipped = if signature.starts_with('0') { "1" } else { "0" };
+        assert_rejected(&pool, &format!("{}.{}{}", payload, flipped, &signature[1..])).await;
+        assert_rejected(&pool, &format!("{}.not-hex", payload)).await;
+        assert_rejected(&pool, payload).await;
+
+        // A changed format, expiry or transcript under the original signature
+        let timestamp = expires_at.timestamp();
+        for payload in [
+            format!("{}.{}.json", transcript_id, timestamp),
+            format!("{}.{}.txt", transcript_id, timestamp + 3600),
+            format!("{}.{}.txt", other_transcript_id, timestamp),
+        ] {
+            assert_rejected(&pool, &format!("{}.{}", payload, signature)).await;
+        }
+
+        // Malformed tokens
+        for share_token in ["", "...", "not-a-token", "a.b.c.d"] {
+            assert_rejected(&pool, share_token).await;
+        }
+        assert_rejected(&pool, &format!("{}.soon.txt.{}", transcript_id, signature)).await;
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn expired_token_is_rejected(pool: PgPool) {
+        let user_id = create_user(&pool, "expired@example.com").await;
+        let transcript_id = create_transcript(&pool, user_id).await;
+
+        let share_token = ShareService::create_token(
+            &pool,
+            user_id,
+            transcript_id,
+            "txt",
+            Utc::now() - Duration::seconds(1),
+        )
+        .await
+        .unwrap();
+
+        assert_rejected(&pool, &share_token).await;
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn revoking_invalidates_existing_links_only(pool: PgPool) {
+        let user_id = create_user(&pool, "revoke@example.com").await;
+        let transcript_id = create_transcript(&pool, user_id).await;
+        let revoked = ShareService::create_token(&pool, user_id, transcript_id, "txt", in_a_day())
+            .await
+            .unwrap();
+
+        ShareService::revoke_all(&pool, user_id).await.unwrap();
+        assert_rejected(&pool, &revoked).await;
+
+        let renewed = ShareService::create_token(&pool, user_id, transcript_id, "txt", in_a_day())
+            .await
+            .unwrap();
+        assert_ne!(renewed, revoked);
+        assert!(ShareService::verify_token(&pool, &renewed).await.is_ok());
+        assert_rejected(&pool, &revoked).await;
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn deleted_transcript_is_no_longer_shared(pool: PgPool) {
+        let user_id = create_user(&pool, "deleted@example.com").await;
+        let transcript_id = create_transcript(&pool, user_id).await;
+        let share_token =
+            ShareService::create_token(&pool, user_id, transcript_id, "txt", in_a_day())
+                .await
+                .unwrap();
+
+        TranscriptionService::delete_transcript(&pool, transcript_id, user_id)
+            .await
+            .unwrap();
+
+        assert_rejected(&pool, &share_token).await;
+    }
+}
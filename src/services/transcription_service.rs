@@ -1,144 +1,925 @@
+use crate::config::Config;
 use crate::errors::{AppError, AppResult};
-use crate::models::{FileUpload, Transcript};
-use chrono::Utc;
-use sqlx::PgPool;
-use std::sync::Arc;
+use crate::models::{
+    LanguageProbability, NewTranscriptionJob, Transcript, TranscriptFilter, TranscriptSegment,
+    TranscriptionResult, UserStats, WordTimestamp, audio_filter, text_format, transcript_status,
+};
+use crate::services::postprocess::{self, PostProcessOptions};
+use crate::storage::Storage;
+use crate::utils::file::{self, TempFile};
+use crate::utils::validation;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder, Transaction};
+use std::collections::HashSet;
+use std::os::raw::c_int;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, SemaphorePermit};
 use uuid::Uuid;
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext};
 
+/// Callback invoked with Whisper's 0-100 progress percentage
+pub type ProgressCallback = Box<dyn FnMut(i32) + Send>;
+
+/// Callback invoked with each segment as soon as Whisper finishes it
+/// Runs on the inference thread, so it must hand the segment off without blocking
+pub type SegmentCallback = Arc<dyn Fn(TranscriptSegment) + Send + Sync>;
+
+/// Most tokens an initial prompt may tokenize to; whisper.cpp keeps the last half-context of them
+const MAX_PROMPT_TOKENS: usize = 1024;
+
+/// Number of language candidates reported for auto-detected transcripts
+const LANGUAGE_CANDIDATES: usize = 5;
+
+/// Number of days a soft-deleted transcript can still be restored
+pub const RESTORE_WINDOW_DAYS: i32 = 30;
+
+/// Per-job options of `transcribe_audio`
+#[derive(Debug, Clone, Copy)]
+pub struct TranscribeOptions<'a> {
+    pub language: Option<&'a str>,
+    pub trim_silence: bool,
+    pub initial_prompt: Option<&'a str>,
+    /// One of the `text_format` values
+    pub text_format: &'a str,
+    /// Time each word as well; costs extra decoding time and memory
+    pub word_timestamps: bool,
+    /// Clean-up passes over the segment texts
+    pub postprocess: PostProcessOptions,
+    /// Temperature fallback and the thresholds that trigger it
+    pub decoding: DecodingOptions,
+    /// `audio_filter` names applied while converting the audio
+    pub audio_filters: &'a [String],
+}
+
+/// Whisper's temperature fallback: a segment that looks like silence or repetition is decoded
+/// again at a higher temperature, which gets greedy decoding unstuck on noisy audio
+#[derive(Debug, Clone, Copy)]
+pub struct DecodingOptions {
+    /// Sampling temperature of the first attempt; 0 always picks the most likely token
+    pub temperature: f32,
+    /// Step the temperature is raised by on each retry; 0 disables the fallback
+    pub temperature_inc: f32,
+    /// "No speech" probability above which a segment is treated as silence
+    pub no_speech_threshold: f32,
+    /// Token entropy below which a segment is considered repetitive. whisper.cpp checks this
+    /// instead of the compression ratio used by the reference implementation
+    pub entropy_threshold: f32,
+}
+
+impl DecodingOptions {
+    /// Options from `WHISPER_TEMPERATURE`, `WHISPER_TEMPERATURE_INC` and the thresholds
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            temperature: config.whisper_temperature,
+            temperature_inc: config.whisper_temperature_inc,
+            no_speech_threshold: config.whisper_no_speech_threshold,
+            entropy_threshold: config.whisper_entropy_threshold,
+        }
+    }
+}
+
+/// Bounds how many FFmpeg/FFprobe processes run at once, so a burst of uploads waits its
+/// turn instead of starving the machine
+#[derive(Debug, Clone)]
+pub struct FfmpegLimiter {
+    permits: Arc<Semaphore>,
+}
+
+impl FfmpegLimiter {
+    pub fn new(max_processes: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_processes)),
+        }
+    }
+
+    /// Wait for a free process slot, logging how long it took when others held them all
+    async fn acquire(&self, tool: &str) -> AppResult<SemaphorePermit<'_>> {
+        if let Ok(permit) = self.permits.try_acquire() {
+            return Ok(permit);
+        }
+
+        let started = Instant::now();
+        let permit = self.permits.acquire().await.map_err(|_| {
+            AppError::InternalError("FFmpeg process limiter is closed".to_string())
+        })?;
+        log::info!(
+            "Waited {:.2}s for a free process slot to run {}",
+            started.elapsed().as_secs_f64(),
+            tool
+        );
+        Ok(permit)
+    }
+}
+
+/// Bounds how many Whisper states exist at once across jobs, chunks and live streams.
+/// Every state carries its own decoding buffers, so without a cap memory would grow with
+/// `TRANSCRIPTION_WORKERS` × `WHISPER_PARALLEL_CHUNKS`
+#[derive(Debug, Clone)]
+pub struct WhisperStateLimiter {
+    permits: Arc<Semaphore>,
+}
+
+impl WhisperStateLimiter {
+    pub fn new(max_states: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_states)),
+        }
+    }
+
+    /// Wait for room for one more Whisper state, which must be dropped before the permit
+    async fn acquire(&self) -> AppResult<OwnedSemaphorePermit> {
+        self.permits.clone().acquire_owned().await.map_err(|_| {
+            AppError::InternalError("Whisper state limiter is closed".to_string())
+        })
+    }
+}
+
 /// Transcription service for handling audio transcription
 pub struct TranscriptionService;
 
 impl TranscriptionService {
-    /// Transcribe audio file using Whisper with automatic format conversion
+    /// Transcribe an audio file on disk using Whisper with automatic format conversion
+    /// The input file is owned by the caller and is not removed here
+    #[allow(clippy::too_many_arguments)]
     pub async fn transcribe_audio(
         whisper_ctx: Arc<WhisperContext>,
-        file_upload: FileUpload,
-        temp_dir: &str,
-    ) -> AppResult<String> {
-        // Save uploaded file to temporary location
-        let temp_file_path = format!("{}/{}", temp_dir, file_upload.filename);
-        tokio::fs::write(&temp_file_path, &file_upload.data).await?;
-
-        // Convert audio to WAV format suitable for Whisper
-        let wav_file_path = format!("{}/{}.wav", temp_dir, Uuid::new_v4());
-        Self::convert_to_wav(&temp_file_path, &wav_file_path).await?;
-
-        // Load audio data from the converted WAV file
-        let audio_data = Self::load_wav_audio_samples(&wav_file_path).await?;
-
-        // Set up Whisper parameters
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-        params.set_n_threads(4);
-        params.set_language(Some("en"));
+        input_path: &str,
+        config: &Config,
+        ffmpeg: &FfmpegLimiter,
+        whisper_states: &WhisperStateLimiter,
+        options: TranscribeOptions<'_>,
+        on_progress: Option<ProgressCallback>,
+        on_segment: Option<SegmentCallback>,
+    ) -> AppResult<TranscriptionResult> {
+        let started = Instant::now();
+
+        // Resolve the requested language (None means Whisper auto-detects)
+        let language = validation::validate_language(options.language)?;
+
+        let (mut audio_data, silences) =
+            Self::load_audio(input_path, config, ffmpeg, &options).await?;
+        // Charged against the quota in place of FFprobe's estimate, which some containers lack
+        let duration_seconds = audio_data.len() as f64 / WHISPER_SAMPLE_RATE as f64;
+        // The upload check relies on FFprobe, which can't time every file; this one can't be skipped
+        file::validate_audio_duration(duration_seconds, config.max_audio_duration_seconds)?;
+
+        // Cut long silences out, remembering where so timestamps can be mapped back
+        let silence_map = Arc::new(SilenceMap::trim(&mut audio_data, &silences));
+        if silence_map.removed_ms() > 0 {
+            log::info!(
+                "Trimmed {:.1}s of silence from {}",
+                silence_map.removed_ms() as f64 / 1000.0,
+                input_path
+            );
+        }
+
+        // Long recordings are split into overlapping chunks transcribed concurrently
+        let chunks = Self::plan_chunks(audio_data.len(), config);
+        let parallel_chunks = config.whisper_parallel_chunks.min(chunks.len()).max(1);
+        let threads_per_chunk = (config.whisper_threads / parallel_chunks).max(1);
+
+        // Perform transcription
+        log::info!("Starting transcription for file: {}", input_path);
+        log::info!(
+            "Audio data length: {} samples in {} chunk(s), {} in parallel",
+            audio_data.len(),
+            chunks.len(),
+            parallel_chunks
+        );
+
+        let audio_data = Arc::new(audio_data);
+        let whisper_started = Instant::now();
+
+        // Auto-detection also reports how sure Whisper was, to flag ambiguous audio for review
+        let language_probabilities = if language.is_none() {
+            let state_permit = whisper_states.acquire().await?;
+            let whisper_ctx = whisper_ctx.clone();
+            let audio_data = audio_data.clone();
+            let threads = config.whisper_threads;
+            let span = tracing::Span::current();
+            tokio::task::spawn_blocking(move || {
+                let _entered = span.enter();
+                let _state_permit = state_permit;
+                Self::detect_language_probabilities(&whisper_ctx, &audio_data, threads)
+            })
+            .await
+            .map_err(|e| AppError::WhisperError(format!("Language detection task failed: {}", e)))
+            .and_then(|result| result)
+            .unwrap_or_else(|e| {
+                log::warn!("Language detection failed for {}: {}", input_path, e);
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+
+        let semaphore = Arc::new(Semaphore::new(parallel_chunks));
+        let progress = Arc::new(Mutex::new(ChunkProgress {
+            per_chunk: vec![0; chunks.len()],
+            callback: on_progress,
+        }));
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut handles = Vec::with_capacity(chunks.len());
+        for (index, chunk) in chunks.iter().cloned().enumerate() {
+            let cancelled = cancelled.clone();
+            let whisper_ctx = whisper_ctx.clone();
+            let audio_data = audio_data.clone();
+            let semaphore = semaphore.clone();
+            let whisper_states = whisper_states.clone();
+            let progress = progress.clone();
+            let on_segment = on_segment.clone();
+            let silence_map = silence_map.clone();
+            let beam_size = config.whisper_beam_size;
+            let initial_prompt = options.initial_prompt.map(str::to_string);
+            let word_timestamps = options.word_timestamps;
+            let decoding = options.decoding;
+            let span = tracing::Span::current();
+
+            handles.push(tokio::spawn(async move {
+                let _permit = semaphore
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| AppError::WhisperError(format!("Chunk scheduler closed: {}", e)))?;
+                // Other jobs' chunks share the same cap, so a busy server runs fewer at once
+                let state_permit = whisper_states.acquire().await?;
+
+                tokio::task::spawn_blocking(move || {
+                    // Keep the job's span on the blocking thread so Whisper logs stay correlated
+                    let _entered = span.enter();
+                    let _state_permit = state_permit;
+                    let prompt_tokens = initial_prompt
+                        .as_deref()
+                        .map(|prompt| Self::prompt_tokens(&whisper_ctx, prompt))
+                        .transpose()?;
+                    let mut params = Self::whisper_params(
+                        beam_size,
+                        threads_per_chunk,
+                        language,
+                        prompt_tokens.as_deref(),
+                        word_timestamps,
+                        decoding,
+                    );
+                    params.set_progress_callback_safe(move |value| {
+                        if let Ok(mut progress) = progress.lock() {
+                            progress.report(index, value);
+                        }
+                    });
+
+                    // Partial segments go through the same overlap filter as the final stitch.
+                    // The handler lives in this closure, so it outlives Whisper's run below
+                    let offset_ms = samples_to_ms(chunk.start);
+                    let segment_chunk = chunk.clone();
+                    let mut segment_handler = on_segment.map(|on_segment| -> SegmentHandler {
+                        Box::new(move |t0, t1, text| {
+                            let mut segment = TranscriptSegment {
+                                start_ms: offset_ms + t0 * 10,
+                                end_ms: offset_ms + t1 * 10,
+                                text: text.trim().to_string(),
+                                speaker: None,
+                                confidence: None,
+                                words: Vec::new(),
+                            };
+                            if segment.text.is_empty() || !segment_chunk.keeps(&segment) {
+                                return;
+                            }
+                            silence_map.restore(&mut segment);
+                            on_segment(segment);
+                        })
+                    });
+                    if let Some(handler) = segment_handler.as_mut() {
+                        set_segment_handler(&mut params, handler);
+                    }
+                    set_abort_flag(&mut params, &cancelled);
+
+                    Self::run_whisper(
+                        &whisper_ctx,
+                        params,
+                        &audio_data[chunk.start..chunk.end],
+                        samples_to_ms(chunk.start),
+                        word_timestamps,
+                    )
+                })
+                .await
+                .map_err(|e| AppError::WhisperError(format!("Transcription task failed: {}", e)))?
+            }));
+        }
+        let _abort_pending = AbortOnDrop {
+            handles: handles.iter().map(|handle| handle.abort_handle()).collect(),
+            cancelled,
+        };
+
+        // Stitch chunks back together, keeping each overlapping segment from exactly one chunk
+        let mut segments = Vec::new();
+        let mut detected_languages = Vec::new();
+        for (chunk, handle) in chunks.iter().zip(handles) {
+            let (chunk_segments, chunk_language) = handle
+                .await
+                .map_err(|e| AppError::WhisperError(format!("Transcription task failed: {}", e)))??;
+
+            segments.extend(chunk_segments.into_iter().filter(|segment| chunk.keeps(segment)));
+            detected_languages.extend(chunk_language);
+        }
+        let transcription_seconds = whisper_started.elapsed().as_secs_f64();
+
+        for segment in segments.iter_mut() {
+            silence_map.restore(segment);
+        }
+
+        // Use the requested language, or the one Whisper detected in most chunks
+        let detected_language = match language {
+            Some(_) => None,
+            None => most_common(detected_languages),
+        };
+        let language = language.map(str::to_string).or_else(|| detected_language.clone());
+
+        postprocess::apply(&mut segments, options.postprocess, language.as_deref());
+
+        // Join while segments still carry Whisper's leading spaces, then store them trimmed
+        let transcription = join_segments(&segments, options.text_format);
+        for segment in segments.iter_mut() {
+            segment.text = segment.text.trim().to_string();
+        }
+
+        let result = TranscriptionResult {
+            text: transcription,
+            language,
+            detected_language,
+            language_probabilities,
+            segments,
+            duration_seconds,
+            transcription_seconds,
+            processing_seconds: started.elapsed().as_secs_f64(),
+            whisper_threads: threads_per_chunk * parallel_chunks,
+        };
+
+        log::info!(
+            "Transcription completed for file: {} - Length: {} characters, language: {}, took {:.2}s",
+            input_path,
+            result.text.len(),
+            result.language.as_deref().unwrap_or("unknown"),
+            result.transcription_seconds
+        );
+
+        if result.text.is_empty() {
+            log::warn!(
+                "Empty transcription result for file: {}",
+                input_path
+            );
+        }
+
+        Ok(result)
+    }
+
+    /// Split decoded audio into chunks; a single chunk when chunking is disabled or not needed
+    fn plan_chunks(total_samples: usize, config: &Config) -> Vec<AudioChunk> {
+        let single_chunk = vec![AudioChunk {
+            start: 0,
+            end: total_samples,
+            keep_from_ms: i64::MIN,
+            keep_until_ms: i64::MAX,
+        }];
+
+        let threshold = match config.whisper_chunk_threshold_seconds {
+            Some(threshold) => threshold as usize * WHISPER_SAMPLE_RATE,
+            None => return single_chunk,
+        };
+        let chunk_len = config.whisper_chunk_seconds as usize * WHISPER_SAMPLE_RATE;
+        if total_samples <= threshold || total_samples <= chunk_len {
+            return single_chunk;
+        }
+
+        // Each chunk is padded by half the overlap on both sides of its core region
+        let half_overlap = config.whisper_chunk_overlap_seconds as usize * WHISPER_SAMPLE_RATE / 2;
+        let chunk_count = total_samples.div_ceil(chunk_len);
+
+        (0..chunk_count)
+            .map(|index| {
+                let core_start = index * chunk_len;
+                let core_end = (core_start + chunk_len).min(total_samples);
+
+                AudioChunk {
+                    start: core_start.saturating_sub(half_overlap),
+                    end: (core_end + half_overlap).min(total_samples),
+                    keep_from_ms: if index == 0 { i64::MIN } else { samples_to_ms(core_start) },
+                    keep_until_ms: if index + 1 == chunk_count {
+                        i64::MAX
+                    } else {
+                        samples_to_ms(core_end)
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Tokenize an initial prompt for `whisper_params` (whisper-rs 0.10 only takes tokens)
+    fn prompt_tokens(whisper_ctx: &WhisperContext, prompt: &str) -> AppResult<Vec<c_int>> {
+        whisper_ctx.tokenize(prompt, MAX_PROMPT_TOKENS).map_err(|e| {
+            AppError::WhisperError(format!("Failed to tokenize the initial prompt: {}", e))
+        })
+    }
+
+    /// Build Whisper decoding parameters
+    /// `prompt_tokens` (see `prompt_tokens`) must outlive the returned parameters.
+    fn whisper_params<'b>(
+        beam_size: Option<usize>,
+        threads: usize,
+        language: Option<&'static str>,
+        prompt_tokens: Option<&'b [c_int]>,
+        token_timestamps: bool,
+        decoding: DecodingOptions,
+    ) -> FullParams<'static, 'b> {
+        let strategy = match beam_size {
+            Some(beam_size) => SamplingStrategy::BeamSearch {
+                beam_size: beam_size as i32,
+                patience: -1.0,
+            },
+            None => SamplingStrategy::Greedy { best_of: 1 },
+        };
+        let mut params = FullParams::new(strategy);
+        params.set_n_threads(threads as i32);
+        params.set_language(language);
         params.set_translate(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
+        params.set_token_timestamps(token_timestamps);
+        params.set_temperature(decoding.temperature);
+        params.set_temperature_inc(decoding.temperature_inc);
+        params.set_no_speech_thold(decoding.no_speech_threshold);
+        params.set_entropy_thold(decoding.entropy_threshold);
+        if let Some(prompt_tokens) = prompt_tokens {
+            params.set_tokens(prompt_tokens);
+        }
+        params
+    }
 
-        // Perform transcription
-        log::info!("Starting transcription for file: {}", file_upload.filename);
-        log::info!("Audio data length: {} samples", audio_data.len());
-
-        let whisper_ctx_clone = whisper_ctx.clone();
-        let transcription = tokio::task::spawn_blocking(move || -> AppResult<String> {
-            // Create state once and reuse it
-            let mut state = whisper_ctx_clone.create_state().map_err(|e| {
-                AppError::WhisperError(format!("Failed to create Whisper state: {}", e))
-            })?;
+    /// Transcribe 16kHz mono samples already in memory, e.g. audio received from a live stream
+    /// Segment timestamps are shifted by `offset_ms`
+    pub async fn transcribe_samples(
+        whisper_ctx: Arc<WhisperContext>,
+        samples: Vec<f32>,
+        offset_ms: i64,
+        config: &Config,
+        whisper_states: &WhisperStateLimiter,
+        language: Option<&'static str>,
+    ) -> AppResult<Vec<TranscriptSegment>> {
+        let beam_size = config.whisper_beam_size;
+        let threads = config.whisper_threads;
+        let decoding = DecodingOptions::from_config(config);
+        let span = tracing::Span::current();
+        let state_permit = whisper_states.acquire().await?;
+
+        let (mut segments, _) = tokio::task::spawn_blocking(move || {
+            let _entered = span.enter();
+            let _state_permit = state_permit;
+            let params = Self::whisper_params(beam_size, threads, language, None, false, decoding);
+            Self::run_whisper(&whisper_ctx, params, &samples, offset_ms, false)
+        })
+        .await
+        .map_err(|e| AppError::WhisperError(format!("Transcription task failed: {}", e)))??;
+
+        for segment in segments.iter_mut() {
+            segment.text = segment.text.trim().to_string();
+        }
+
+        Ok(segments)
+    }
+
+    /// Run Whisper over a slice of samples on a fresh state (blocking)
+    /// Callers hold a `WhisperStateLimiter` permit for as long as this runs.
+    /// Segment timestamps are shifted by `offset_ms` to be relative to the whole recording.
+    /// Segment texts are returned untrimmed, with the leading space Whisper puts before each word.
+    /// `word_timestamps` needs params built with token timestamps enabled.
+    fn run_whisper(
+        whisper_ctx: &WhisperContext,
+        params: FullParams<'_, '_>,
+        samples: &[f32],
+        offset_ms: i64,
+        word_timestamps: bool,
+    ) -> AppResult<(Vec<TranscriptSegment>, Option<String>)> {
+        // Each chunk needs its own state so chunks can run concurrently
+        let mut state = whisper_ctx.create_state().map_err(|e| {
+            AppError::WhisperError(format!("Failed to create Whisper state: {}", e))
+        })?;
+
+        // Run transcription
+        state.full(params, samples).map_err(|e| {
+            AppError::WhisperError(format!("Whisper transcription failed: {}", e))
+        })?;
+
+        // Get number of segments from the SAME state
+        let num_segments = state
+            .full_n_segments()
+            .map_err(|e| AppError::WhisperError(format!("Failed to get segments: {}", e)))?;
 
-            // Run transcription
-            state.full(params, &audio_data).map_err(|e| {
-                AppError::WhisperError(format!("Whisper transcription failed: {}", e))
+        log::info!("Transcription found {} segments", num_segments);
+
+        // Timestamps and other special tokens have IDs from end-of-text upwards
+        let token_eot = whisper_ctx.token_eot();
+
+        // Extract segment text and timestamps from the SAME state
+        let mut segments = Vec::with_capacity(num_segments.max(0) as usize);
+        for i in 0..num_segments {
+            let segment_text = state.full_get_segment_text(i).map_err(|e| {
+                AppError::WhisperError(format!("Failed to get segment text: {}", e))
             })?;
 
-            // Get number of segments from the SAME state
-            let num_segments = state
-                .full_n_segments()
-                .map_err(|e| AppError::WhisperError(format!("Failed to get segments: {}", e)))?;
+            // Whisper reports timestamps in centiseconds
+            let t0 = state.full_get_segment_t0(i).map_err(|e| {
+                AppError::WhisperError(format!("Failed to get segment start time: {}", e))
+            })?;
+            let t1 = state.full_get_segment_t1(i).map_err(|e| {
+                AppError::WhisperError(format!("Failed to get segment end time: {}", e))
+            })?;
 
-            log::info!("Transcription found {} segments", num_segments);
+            // Confidence is the average probability of the segment's text tokens
+            let num_tokens = state.full_n_tokens(i).map_err(|e| {
+                AppError::WhisperError(format!("Failed to get segment tokens: {}", e))
+            })?;
+            let mut probability_sum = 0.0;
+            let mut text_tokens = 0;
+            let mut words: Vec<WordTimestamp> = Vec::new();
+            for token in 0..num_tokens {
+                let token_id = state.full_get_token_id(i, token).map_err(|e| {
+                    AppError::WhisperError(format!("Failed to get token id: {}", e))
+                })?;
+                if token_id >= token_eot {
+                    continue;
+                }
 
-            // Extract transcription text from the SAME state
-            let mut transcription = String::new();
-            for i in 0..num_segments {
-                let segment_text = state.full_get_segment_text(i).map_err(|e| {
-                    AppError::WhisperError(format!("Failed to get segment text: {}", e))
+                probability_sum += state.full_get_token_prob(i, token).map_err(|e| {
+                    AppError::WhisperError(format!("Failed to get token probability: {}", e))
                 })?;
+                text_tokens += 1;
+
+                if word_timestamps {
+                    let data = state.full_get_token_data(i, token).map_err(|e| {
+                        AppError::WhisperError(format!("Failed to get token timestamps: {}", e))
+                    })?;
+                    // A token holding part of a multi-byte character isn't valid UTF-8 on its
+                    // own; its time still counts towards the word
+                    let text = state.full_get_token_text(i, token).unwrap_or_default();
+                    let end_ms = offset_ms + data.t1 * 10;
 
-                log::debug!("Segment {}: '{}'", i, segment_text);
-                transcription.push_str(&segment_text);
-                if i < num_segments - 1 {
-                    transcription.push(' ');
+                    // Tokens starting with a space begin a new word, the rest extend the last one
+                    match words.last_mut() {
+                        Some(word) if !text.starts_with(' ') => {
+                            word.text.push_str(&text);
+                            word.end_ms = word.end_ms.max(end_ms);
+                        }
+                        _ => words.push(WordTimestamp {
+                            start_ms: offset_ms + data.t0 * 10,
+                            end_ms,
+                            text,
+                        }),
+                    }
                 }
             }
+            for word in words.iter_mut() {
+                word.text = word.text.trim().to_string();
+            }
+            words.retain(|word| !word.text.is_empty());
+            let confidence = (text_tokens > 0).then(|| probability_sum / text_tokens as f32);
 
-            Ok(transcription.trim().to_string())
-        })
-        .await
-        .map_err(|e| AppError::WhisperError(format!("Transcription task failed: {}", e)))??;
+            log::debug!(
+                "Segment {} [{} - {}] (confidence {:?}): '{}'",
+                i,
+                t0,
+                t1,
+                confidence,
+                segment_text
+            );
 
-        // Clean up temporary files
-        tokio::fs::remove_file(&temp_file_path).await.ok();
-        tokio::fs::remove_file(&wav_file_path).await.ok();
+            segments.push(TranscriptSegment {
+                start_ms: offset_ms + t0 * 10,
+                end_ms: offset_ms + t1 * 10,
+                text: segment_text,
+                speaker: None,
+                confidence,
+                words,
+            });
+        }
 
-        log::info!(
-            "Transcription completed for file: {} - Length: {} characters",
-            file_upload.filename,
-            transcription.len()
-        );
+        let detected_language = state
+            .full_lang_id_from_state()
+            .ok()
+            .and_then(whisper_rs::get_lang_str)
+            .map(|code| code.to_string());
 
-        if transcription.is_empty() {
-            log::warn!(
-                "Empty transcription result for file: {}",
-                file_upload.filename
-            );
+        Ok((segments, detected_language))
+    }
+
+    /// Likeliest spoken languages in the first 30 seconds of audio, most likely first (blocking)
+    fn detect_language_probabilities(
+        whisper_ctx: &WhisperContext,
+        samples: &[f32],
+        threads: usize,
+    ) -> AppResult<Vec<LanguageProbability>> {
+        let mut state = whisper_ctx.create_state().map_err(|e| {
+            AppError::WhisperError(format!("Failed to create Whisper state: {}", e))
+        })?;
+
+        let window = &samples[..samples.len().min(30 * WHISPER_SAMPLE_RATE)];
+        state.pcm_to_mel(window, threads).map_err(|e| {
+            AppError::WhisperError(format!("Failed to compute spectrogram: {}", e))
+        })?;
+        let probabilities = state.lang_detect(0, threads).map_err(|e| {
+            AppError::WhisperError(format!("Failed to detect language: {}", e))
+        })?;
+
+        // Probabilities are indexed by Whisper's language ID
+        let mut candidates: Vec<LanguageProbability> = probabilities
+            .iter()
+            .enumerate()
+            .filter_map(|(id, probability)| {
+                whisper_rs::get_lang_str(id as i32).map(|code| LanguageProbability {
+                    language: code.to_string(),
+                    probability: *probability,
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+        candidates.truncate(LANGUAGE_CANDIDATES);
+
+        Ok(candidates)
+    }
+
+    /// Prefix of intermediate files created by this process, so they can be swept on shutdown
+    fn process_temp_prefix() -> String {
+        format!("ai-scribe-{}-", std::process::id())
+    }
+
+    /// Remove intermediate files this process left in the temp directory
+    pub async fn cleanup_process_temp_files(temp_dir: &str) {
+        let prefix = Self::process_temp_prefix();
+        let mut entries = match tokio::fs::read_dir(temp_dir).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Failed to read temp directory {}: {}", temp_dir, e);
+                return;
+            }
+        };
+
+        let mut removed = 0;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry.file_name().to_string_lossy().starts_with(&prefix)
+                && tokio::fs::remove_file(entry.path()).await.is_ok()
+            {
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            log::info!("Removed {} leftover temporary files", removed);
+        }
+    }
+
+    /// Delete files this service created in the temp directory that are older than `max_age`,
+    /// e.g. left behind by a crash. Audio still needed by a queued or running job is kept, and the
+    /// age threshold protects files of requests still in flight. Returns how many were removed.
+    pub async fn cleanup_stale_temp_files(
+        pool: &PgPool,
+        temp_dir: &str,
+        max_age: Duration,
+    ) -> AppResult<usize> {
+        // Queued jobs' uploads and resumable uploads still being assembled are in use
+        let in_use: HashSet<String> = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT audio_path FROM transcripts WHERE audio_path IS NOT NULL AND status IN ($1, $2)
+            UNION ALL
+            SELECT path FROM uploads
+            "#,
+        )
+        .bind(transcript_status::PENDING)
+        .bind(transcript_status::PROCESSING)
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .filter_map(|path| Path::new(path).file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect();
+
+        let now = SystemTime::now();
+        let mut entries = tokio::fs::read_dir(temp_dir).await?;
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !is_temp_file_name(&name) || in_use.contains(&name) {
+                continue;
+            }
+
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok());
+            if metadata.is_file()
+                && age.is_some_and(|age| age > max_age)
+                && tokio::fs::remove_file(entry.path()).await.is_ok()
+            {
+                removed += 1;
+            }
         }
 
-        Ok(transcription)
+        Ok(removed)
     }
 
     /// Convert audio file to WAV format using FFmpeg
-    async fn convert_to_wav(input_path: &str, output_path: &str) -> AppResult<()> {
-        let output = tokio::process::Command::new("ffmpeg")
-            .args([
-                "-i", input_path,        // Input file
-                "-ar", "16000",          // Sample rate 16kHz (whisper requirement)
-                "-ac", "1",              // Mono channel
-                "-c:a", "pcm_s16le",     // 16-bit PCM encoding
-                "-y",                    // Overwrite output file
-                output_path
-            ])
+    /// When `silence_detection` is set to `(threshold_db, min_duration_seconds)`, silences are
+    /// detected in the same pass and returned as `(start, end)` seconds
+    async fn convert_to_wav(
+        ffmpeg: &FfmpegLimiter,
+        input_path: &str,
+        output_path: &str,
+        audio_filters: &[String],
+        silence_detection: Option<(f64, f64)>,
+    ) -> AppResult<Vec<(f64, f64)>> {
+        let mut command = tokio::process::Command::new("ffmpeg");
+        // A timed-out job drops this future; don't leave FFmpeg writing the WAV behind it
+        command.kill_on_drop(true);
+        let input_arg = file::ffmpeg_file_arg(input_path);
+        let output_arg = file::ffmpeg_file_arg(output_path);
+        command.args([
+            "-i", input_arg.as_str(),   // Input file
+            "-ar", "16000",             // Sample rate 16kHz (whisper requirement)
+            "-ac", "1",                 // Mono channel
+            "-c:a", "pcm_s16le",        // 16-bit PCM encoding
+        ]);
+        let mut filters = audio_filters
+            .iter()
+            .map(|name| {
+                audio_filter_expression(name).map(str::to_string).ok_or_else(|| {
+                    AppError::ValidationError(format!("Unsupported audio filter '{}'", name))
+                })
+            })
+            .collect::<AppResult<Vec<String>>>()?;
+        if let Some((threshold_db, min_duration)) = silence_detection {
+            // silencedetect only reports silences on stderr; the audio itself is untouched.
+            // It runs last, so it sees the filtered audio
+            filters.push(format!("silencedetect=noise={}dB:d={}", threshold_db, min_duration));
+        }
+        if !filters.is_empty() {
+            command.arg("-af").arg(filters.join(","));
+        }
+        command.args([
+            "-y",                       // Overwrite output file
+            output_arg.as_str(),
+        ]);
+
+        Self::ensure_audio_stream(ffmpeg, input_path).await?;
+
+        let _permit = ffmpeg.acquire("FFmpeg").await?;
+        let output = command
             .output()
             .await
             .map_err(|e| AppError::FileError(format!("Failed to run FFmpeg: {}", e)))?;
 
         if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            return Err(AppError::FileError(format!("FFmpeg conversion failed: {}", error_msg)));
+            return Err(ffmpeg_failure("FFmpeg conversion", &output));
         }
 
         log::info!("Successfully converted {} to {}", input_path, output_path);
-        Ok(())
+
+        if silence_detection.is_none() {
+            return Ok(Vec::new());
+        }
+        Ok(parse_silences(&String::from_utf8_lossy(&output.stderr)))
+    }
+
+    /// Convert an upload to Whisper's sample format through an intermediate WAV, returning the
+    /// samples and, with `trim_silence`, the silences found. The guard deletes the WAV on every
+    /// exit path, including errors
+    async fn load_audio(
+        input_path: &str,
+        config: &Config,
+        ffmpeg: &FfmpegLimiter,
+        options: &TranscribeOptions<'_>,
+    ) -> AppResult<(Vec<f32>, Vec<(f64, f64)>)> {
+        let wav_file = TempFile::new(format!(
+            "{}/{}{}.wav",
+            config.temp_dir,
+            Self::process_temp_prefix(),
+            Uuid::new_v4()
+        ));
+        let silence_detection = options
+            .trim_silence
+            .then_some((config.silence_threshold_db, config.silence_min_duration_seconds));
+        let silences = Self::convert_to_wav(
+            ffmpeg,
+            input_path,
+            wav_file.path(),
+            options.audio_filters,
+            silence_detection,
+        )
+        .await?;
+
+        let samples =
+            Self::load_wav_audio_samples(wav_file.path(), config.silent_audio_threshold_db).await?;
+        Ok((samples, silences))
     }
 
     /// Load audio samples from a WAV file (optimized for Whisper)
-    async fn load_wav_audio_samples(wav_path: &str) -> AppResult<Vec<f32>> {
+    /// Audio whose RMS level is below `silent_threshold_db` (dBFS) is rejected, since Whisper
+    /// would only return an empty or hallucinated transcript for it
+    async fn load_wav_audio_samples(
+        wav_path: &str,
+        silent_threshold_db: f64,
+    ) -> AppResult<Vec<f32>> {
         let audio_bytes = tokio::fs::read(wav_path).await
             .map_err(|e| AppError::FileError(format!("Failed to read WAV file: {}", e)))?;
 
-        // Skip WAV header (44 bytes for standard WAV)
-        if audio_bytes.len() < 44 {
-            return Err(AppError::FileError("Invalid WAV file - too small".to_string()));
+        let samples = Self::decode_wav_samples(&audio_bytes)?;
+
+        let level_db = rms_db(&samples);
+        log::info!(
+            "Loaded {} audio samples from WAV file (RMS {:.1} dBFS)",
+            samples.len(),
+            level_db
+        );
+        if level_db < silent_threshold_db {
+            return Err(AppError::ValidationError(format!(
+                "Audio appears to be silent (RMS level {:.1} dBFS is below {} dBFS)",
+                level_db.max(-120.0),
+                silent_threshold_db
+            )));
+        }
+
+        Ok(samples)
+    }
+
+    /// Decode a WAV file to 16kHz mono f32 samples by walking its RIFF chunks
+    /// Multi-channel audio is averaged down to mono and other sample rates are resampled
+    fn decode_wav_samples(audio_bytes: &[u8]) -> AppResult<Vec<f32>> {
+        if audio_bytes.len() < 12 || &audio_bytes[0..4] != b"RIFF" || &audio_bytes[8..12] != b"WAVE" {
+            return Err(AppError::FileError("Invalid WAV file - missing RIFF/WAVE header".to_string()));
+        }
+
+        let mut format: Option<WavFormat> = None;
+        let mut pcm_data: Option<&[u8]> = None;
+        let mut offset = 12;
+
+        // Each chunk is a 4-byte id, a 4-byte little-endian size and a payload padded to an even length
+        while offset + 8 <= audio_bytes.len() {
+            let chunk_id = &audio_bytes[offset..offset + 4];
+            let chunk_size = u32::from_le_bytes([
+                audio_bytes[offset + 4],
+                audio_bytes[offset + 5],
+                audio_bytes[offset + 6],
+                audio_bytes[offset + 7],
+            ]) as usize;
+            let body_start = offset + 8;
+            let body_end = body_start.saturating_add(chunk_size).min(audio_bytes.len());
+            let body = &audio_bytes[body_start..body_end];
+
+            match chunk_id {
+                b"fmt " => format = Some(WavFormat::parse(body)?),
+                b"data" => {
+                    pcm_data = Some(body);
+                    break;
+                }
+                _ => {
+                    log::debug!("Skipping WAV chunk '{}' ({} bytes)", String::from_utf8_lossy(chunk_id), chunk_size);
+                }
+            }
+
+            offset = body_start.saturating_add(chunk_size).saturating_add(chunk_size % 2);
         }
 
-        let pcm_data = &audio_bytes[44..];
-        let mut samples = Vec::new();
+        let format = format
+            .ok_or_else(|| AppError::FileError("Invalid WAV file - missing fmt chunk before data".to_string()))?;
+
+        let pcm_data = pcm_data
+            .ok_or_else(|| AppError::FileError("Invalid WAV file - missing data chunk".to_string()))?;
+
+        // Decode every frame, averaging the channels down to mono
+        let channels = format.channels as usize;
+        let bytes_per_sample = format.bits_per_sample as usize / 8;
+        let mut samples: Vec<f32> = pcm_data
+            .chunks_exact(bytes_per_sample * channels)
+            .map(|frame| {
+                frame
+                    .chunks_exact(bytes_per_sample)
+                    .map(|sample| format.decode_sample(sample))
+                    .sum::<f32>()
+                    / channels as f32
+            })
+            .collect();
+
+        if format.channels != 1 || format.sample_rate as usize != WHISPER_SAMPLE_RATE || !format.is_pcm16() {
+            log::warn!(
+                "WAV is not 16kHz mono 16-bit PCM (format={}, channels={}, sample_rate={}, bits_per_sample={}); converting",
+                format.audio_format, format.channels, format.sample_rate, format.bits_per_sample
+            );
+        }
 
-        // Convert 16-bit PCM to f32 samples
-        for chunk in pcm_data.chunks_exact(2) {
-            let sample = i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32768.0;
-            samples.push(sample);
+        if format.sample_rate as usize != WHISPER_SAMPLE_RATE {
+            samples = resample_linear(&samples, format.sample_rate, WHISPER_SAMPLE_RATE as u32);
         }
 
         if samples.is_empty() {
@@ -147,7 +928,6 @@ impl TranscriptionService {
             ));
         }
 
-        log::info!("Loaded {} audio samples from WAV file", samples.len());
         Ok(samples)
     }
 
@@ -212,119 +992,750 @@ impl TranscriptionService {
     //     Ok(transcription)
     // }
 
-    /// Save transcription result to database
-    pub async fn save_transcription(
+    /// Create a pending transcript row for a background transcription job
+    pub async fn create_transcription_job(
         pool: &PgPool,
         user_id: Uuid,
-        filename: &str,
-        transcription: &str,
-        file_size: i64,
-        duration_seconds: Option<f64>,
+        job: &NewTranscriptionJob,
     ) -> AppResult<Transcript> {
         let transcript_id = Uuid::new_v4();
         let now = Utc::now();
 
         let transcript = sqlx::query_as::<_, Transcript>(
             r#"
-            INSERT INTO transcripts (id, user_id, filename, transcription, file_size, duration_seconds, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO transcripts (id, user_id, filename, transcription, file_size, duration_seconds, language, status, audio_path, diarize, trim_silence, initial_prompt, text_format, word_timestamps, model_name, capitalize_sentences, numbers_to_digits, temperature, temperature_inc, audio_filters, source_ip, user_agent, created_at)
+            VALUES ($1, $2, $3, '', $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22)
             RETURNING *
             "#
         )
         .bind(transcript_id)
         .bind(user_id)
-        .bind(filename)
-        .bind(transcription)
-        .bind(file_size)
-        .bind(duration_seconds)
+        .bind(&job.filename)
+        .bind(job.file_size)
+        .bind(job.duration_seconds)
+        .bind(job.language.as_deref())
+        .bind(transcript_status::PENDING)
+        .bind(&job.audio_path)
+        .bind(job.diarize)
+        .bind(job.trim_silence)
+        .bind(job.initial_prompt.as_deref())
+        .bind(&job.text_format)
+        .bind(job.word_timestamps)
+        .bind(&job.model_name)
+        .bind(job.capitalize_sentences)
+        .bind(job.numbers_to_digits)
+        .bind(job.temperature)
+        .bind(job.temperature_inc)
+        .bind(&job.audio_filters)
+        .bind(job.source_ip.as_deref())
+        .bind(job.user_agent.as_deref())
         .bind(now)
         .fetch_one(pool)
         .await?;
 
-        log::info!("Transcription saved to database: {}", transcript_id);
+        log::info!("Transcription job created: {}", transcript_id);
         Ok(transcript)
     }
 
-    /// Get user's transcripts with pagination
-    pub async fn get_user_transcripts(
-        pool: &PgPool,
-        user_id: Uuid,
-        page: i64,
-        limit: i64,
-    ) -> AppResult<(Vec<Transcript>, i64)> {
-        let offset = (page - 1) * limit;
+    /// Get a transcript by ID regardless of owner (for background jobs)
+    pub async fn get_transcript_for_job(pool: &PgPool, transcript_id: Uuid) -> AppResult<Transcript> {
+        let transcript = sqlx::query_as::<_, Transcript>("SELECT * FROM transcripts WHERE id = $1")
+            .bind(transcript_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Transcript not found".to_string()))?;
 
-        // Get transcripts
-        let transcripts = sqlx::query_as::<_, Transcript>(
-            r#"
-            SELECT * FROM transcripts 
-            WHERE user_id = $1 
-            ORDER BY created_at DESC 
-            LIMIT $2 OFFSET $3
-            "#,
-        )
-        .bind(user_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(pool)
-        .await?;
+        Ok(transcript)
+    }
 
-        // Get total count
-        let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM transcripts WHERE user_id = $1")
-            .bind(user_id)
-            .fetch_one(pool)
+    /// Mark a transcription job as being processed
+    /// Partial segments left by an earlier, unfinished run are discarded
+    pub async fn mark_processing(pool: &PgPool, transcript_id: Uuid) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("UPDATE transcripts SET status = $2, error_message = NULL WHERE id = $1")
+            .bind(transcript_id)
+            .bind(transcript_status::PROCESSING)
+            .execute(&mut *tx)
             .await?;
 
-        Ok((transcripts, total.0))
+        sqlx::query("DELETE FROM transcript_segments WHERE transcript_id = $1")
+            .bind(transcript_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
     }
 
-    /// Get specific transcript by ID for a user
-    pub async fn get_transcript_by_id(
+    /// Store the result of a finished transcription job
+    /// The transcript and its segments are committed together, or not at all
+    pub async fn complete_transcription(
         pool: &PgPool,
         transcript_id: Uuid,
-        user_id: Uuid,
-    ) -> AppResult<Transcript> {
-        let transcript = sqlx::query_as::<_, Transcript>(
-            "SELECT * FROM transcripts WHERE id = $1 AND user_id = $2",
-        )
-        .bind(transcript_id)
-        .bind(user_id)
-        .fetch_optional(pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Transcript not found".to_string()))?;
+        result: &TranscriptionResult,
+        model_name: &str,
+    ) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
 
-        Ok(transcript)
+        if let Err(e) = Self::save_transcription_tx(&mut tx, transcript_id, result, model_name).await {
+            tx.rollback().await?;
+            log::error!("Failed to save transcription {}: {}", transcript_id, e);
+            return Err(AppError::InternalError(
+                "Failed to save transcription results".to_string(),
+            ));
+        }
+
+        tx.commit().await?;
+
+        log::info!("Transcription saved to database: {}", transcript_id);
+        Ok(())
     }
 
-    /// Delete transcript by ID for a user
-    pub async fn delete_transcript(
-        pool: &PgPool,
+    /// Write a transcription result and its segments inside the caller's transaction
+    pub async fn save_transcription_tx(
+        tx: &mut Transaction<'_, Postgres>,
         transcript_id: Uuid,
-        user_id: Uuid,
+        result: &TranscriptionResult,
+        model_name: &str,
     ) -> AppResult<()> {
-        let result = sqlx::query("DELETE FROM transcripts WHERE id = $1 AND user_id = $2")
+        sqlx::query(
+            r#"
+            UPDATE transcripts
+            SET transcription = $2, original_transcription = $2, language = $3, status = $4,
+                error_message = NULL, audio_path = NULL, model_name = $5, detected_language = $6,
+                word_count = $7, language_probabilities = $8, transcription_seconds = $9,
+                processing_seconds = $10, whisper_threads = $11, duration_seconds = $12
+            WHERE id = $1
+            "#,
+        )
+        .bind(transcript_id)
+        .bind(&result.text)
+        .bind(result.language.as_deref())
+        .bind(transcript_status::COMPLETED)
+        .bind(model_name)
+        .bind(result.detected_language.as_deref())
+        .bind(count_words(&result.text))
+        .bind(sqlx::types::Json(&result.language_probabilities))
+        .bind(result.transcription_seconds)
+        .bind(result.processing_seconds)
+        .bind(result.whisper_threads as i32)
+        .bind(result.duration_seconds)
+        .execute(&mut **tx)
+        .await?;
+
+        Self::save_segments(tx, transcript_id, &result.segments).await
+    }
+
+    /// Mark a transcription job as failed with the reason
+    pub async fn mark_failed(pool: &PgPool, transcript_id: Uuid, error_message: &str) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE transcripts SET status = $2, error_message = $3, audio_path = NULL WHERE id = $1",
+        )
+        .bind(transcript_id)
+        .bind(transcript_status::FAILED)
+        .bind(error_message)
+        .execute(&mut *tx)
+        .await?;
+
+        // Segments streamed before the failure (e.g. a timeout) are not a transcript
+        sqlx::query("DELETE FROM transcript_segments WHERE transcript_id = $1")
             .bind(transcript_id)
-            .bind(user_id)
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(AppError::NotFound("Transcript not found".to_string()));
-        }
-
-        log::info!("Transcript deleted: {}", transcript_id);
+        tx.commit().await?;
         Ok(())
     }
 
-    /// Get audio duration using FFmpeg (helper function)
-    pub async fn get_audio_duration(file_path: &str) -> AppResult<f64> {
-        let output = tokio::process::Command::new("ffprobe")
-            .args([
-                "-v", "quiet",
-                "-show_entries", "format=duration",
+    /// Return jobs stuck in `processing` to `pending`
+    pub async fn reset_processing_jobs(pool: &PgPool) -> AppResult<u64> {
+        let reset = sqlx::query("UPDATE transcripts SET status = $1 WHERE status = $2")
+            .bind(transcript_status::PENDING)
+            .bind(transcript_status::PROCESSING)
+            .execute(pool)
+            .await?;
+
+        Ok(reset.rows_affected())
+    }
+
+    /// Reset jobs left in `processing` by a previous run and return all pending jobs
+    pub async fn reset_interrupted_jobs(pool: &PgPool) -> AppResult<Vec<Transcript>> {
+        let reset = Self::reset_processing_jobs(pool).await?;
+
+        if reset > 0 {
+            log::warn!(
+                "Found {} transcription jobs interrupted while processing",
+                reset
+            );
+        }
+
+        let pending = sqlx::query_as::<_, Transcript>(
+            "SELECT * FROM transcripts WHERE status = $1 ORDER BY created_at ASC",
+        )
+        .bind(transcript_status::PENDING)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(pending)
+    }
+
+    /// Save transcription segments for a transcript, replacing any from an earlier attempt
+    pub async fn save_segments(
+        tx: &mut Transaction<'_, Postgres>,
+        transcript_id: Uuid,
+        segments: &[TranscriptSegment],
+    ) -> AppResult<()> {
+        sqlx::query("DELETE FROM transcript_segments WHERE transcript_id = $1")
+            .bind(transcript_id)
+            .execute(&mut **tx)
+            .await?;
+
+        for (index, segment) in segments.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO transcript_segments (transcript_id, segment_index, start_ms, end_ms, text, speaker, confidence, words)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+            )
+            .bind(transcript_id)
+            .bind(index as i32)
+            .bind(segment.start_ms)
+            .bind(segment.end_ms)
+            .bind(&segment.text)
+            .bind(segment.speaker.as_deref())
+            .bind(segment.confidence)
+            .bind(sqlx::types::Json(&segment.words))
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        log::info!("Saved {} segments for transcript: {}", segments.len(), transcript_id);
+        Ok(())
+    }
+
+    /// Store one segment of a job that is still running, so reconnecting clients can fetch it
+    /// The full segment list replaces these once the job completes
+    pub async fn save_partial_segment(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        index: i32,
+        segment: &TranscriptSegment,
+    ) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO transcript_segments (transcript_id, segment_index, start_ms, end_ms, text, speaker, confidence)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(transcript_id)
+        .bind(index)
+        .bind(segment.start_ms)
+        .bind(segment.end_ms)
+        .bind(&segment.text)
+        .bind(segment.speaker.as_deref())
+        .bind(segment.confidence)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get segments of a transcript owned by a user, in playback order
+    /// When `max_confidence` is set, only segments below it are returned
+    pub async fn get_transcript_segments(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        user_id: Uuid,
+        max_confidence: Option<f32>,
+    ) -> AppResult<Vec<TranscriptSegment>> {
+        // Ensure the transcript exists and belongs to the user
+        Self::load_owned(pool, transcript_id, user_id).await?;
+
+        let segments = sqlx::query_as::<_, TranscriptSegment>(
+            r#"
+            SELECT start_ms, end_ms, text, speaker, confidence, words FROM transcript_segments
+            WHERE transcript_id = $1 AND ($2::REAL IS NULL OR confidence < $2)
+            ORDER BY start_ms ASC, segment_index ASC
+            "#,
+        )
+        .bind(transcript_id)
+        .bind(max_confidence)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(segments)
+    }
+
+    /// Get user's transcripts with pagination, filtered and sorted by `filter`
+    pub async fn get_user_transcripts(
+        pool: &PgPool,
+        user_id: Uuid,
+        page: i64,
+        limit: i64,
+        filter: &TranscriptFilter,
+    ) -> AppResult<(Vec<Transcript>, i64)> {
+        let offset = (page - 1) * limit;
+
+        // Get transcripts
+        let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM transcripts");
+        Self::push_transcript_filter(&mut query, user_id, filter);
+        query
+            .push(" ORDER BY ")
+            .push(filter.sort.column())
+            .push(if filter.ascending { " ASC" } else { " DESC" })
+            .push(" NULLS LAST, id")
+            .push(" LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let transcripts = query.build_query_as::<Transcript>().fetch_all(pool).await?;
+
+        // Get total count
+        let mut count_query = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM transcripts");
+        Self::push_transcript_filter(&mut count_query, user_id, filter);
+        let total: (i64,) = count_query.build_query_as().fetch_one(pool).await?;
+
+        Ok((transcripts, total.0))
+    }
+
+    /// Append the WHERE clause for a user's transcript listing, binding every value
+    fn push_transcript_filter(
+        query: &mut QueryBuilder<'_, Postgres>,
+        user_id: Uuid,
+        filter: &TranscriptFilter,
+    ) {
+        query
+            .push(" WHERE user_id = ")
+            .push_bind(user_id)
+            .push(" AND deleted_at IS NULL");
+
+        if let Some(from) = filter.from {
+            query.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            query.push(" AND created_at < ").push_bind(to);
+        }
+        if let Some(min_duration) = filter.min_duration {
+            query.push(" AND duration_seconds >= ").push_bind(min_duration);
+        }
+        if let Some(max_duration) = filter.max_duration {
+            query.push(" AND duration_seconds <= ").push_bind(max_duration);
+        }
+        if let Some(tag) = &filter.tag {
+            query.push(" AND ").push_bind(tag.clone()).push(" = ANY(tags)");
+        }
+    }
+
+    /// Seconds of audio a user has submitted since `period_start`, live streams included
+    /// Failed jobs are not counted; deleted transcripts still are
+    pub async fn get_usage_seconds(
+        pool: &PgPool,
+        user_id: Uuid,
+        period_start: DateTime<Utc>,
+    ) -> AppResult<f64> {
+        let usage: (Option<f64>,) = sqlx::query_as(
+            r#"
+            SELECT
+                COALESCE((
+                    SELECT SUM(duration_seconds) FROM transcripts
+                    WHERE user_id = $1 AND created_at >= $2 AND status <> $3
+                ), 0)
+                + COALESCE((
+                    SELECT SUM(duration_seconds) FROM live_sessions
+                    WHERE user_id = $1 AND created_at >= $2
+                ), 0)
+            "#,
+        )
+        .bind(user_id)
+        .bind(period_start)
+        .bind(transcript_status::FAILED)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(usage.0.unwrap_or(0.0))
+    }
+
+    /// Record the audio received by a live transcription stream against the user's usage
+    pub async fn record_live_usage(pool: &PgPool, user_id: Uuid, seconds: f64) -> AppResult<()> {
+        sqlx::query("INSERT INTO live_sessions (user_id, duration_seconds) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(seconds)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Totals over a user's transcripts that haven't been deleted
+    /// Words are counted from the stored text; averages only cover completed transcripts
+    pub async fn get_user_stats(pool: &PgPool, user_id: Uuid) -> AppResult<UserStats> {
+        let stats = sqlx::query_as::<_, UserStats>(
+            r#"
+            SELECT
+                COUNT(*) AS total_transcripts,
+                COALESCE(SUM(duration_seconds), 0)::FLOAT8 AS total_audio_seconds,
+                COALESCE(SUM(word_count), 0)::BIGINT AS total_words,
+                COALESCE(AVG(word_count) FILTER (WHERE status = $2), 0)::FLOAT8 AS average_words,
+                COALESCE(AVG(duration_seconds) FILTER (WHERE status = $2), 0)::FLOAT8 AS average_duration_seconds,
+                COUNT(*) FILTER (WHERE status = $3) AS pending,
+                COUNT(*) FILTER (WHERE status = $4) AS processing,
+                COUNT(*) FILTER (WHERE status = $2) AS completed,
+                COUNT(*) FILTER (WHERE status = $5) AS failed
+            FROM transcripts
+            WHERE user_id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(transcript_status::COMPLETED)
+        .bind(transcript_status::PENDING)
+        .bind(transcript_status::PROCESSING)
+        .bind(transcript_status::FAILED)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    /// Get transcripts across all users (administrators only)
+    pub async fn get_all_transcripts(
+        pool: &PgPool,
+        page: i64,
+        limit: i64,
+    ) -> AppResult<(Vec<Transcript>, i64)> {
+        let offset = (page - 1) * limit;
+
+        let transcripts = sqlx::query_as::<_, Transcript>(
+            r#"
+            SELECT * FROM transcripts
+            WHERE deleted_at IS NULL
+            ORDER BY created_at DESC
+            LIMIT $1 OFFSET $2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        let total: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM transcripts WHERE deleted_at IS NULL")
+                .fetch_one(pool)
+                .await?;
+
+        Ok((transcripts, total.0))
+    }
+
+    /// Load a transcript only if it belongs to `user_id`; every per-transcript endpoint goes
+    /// through this guard. Someone else's transcript is `NotFound`, never `Forbidden`, so its
+    /// existence isn't revealed
+    pub async fn load_owned(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        user_id: Uuid,
+    ) -> AppResult<Transcript> {
+        let transcript = sqlx::query_as::<_, Transcript>(
+            "SELECT * FROM transcripts WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(transcript_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Transcript not found".to_string()))?;
+
+        Ok(transcript)
+    }
+
+    /// Update the provided fields of a transcript owned by a user
+    pub async fn update_transcript(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        user_id: Uuid,
+        transcription: Option<&str>,
+        filename: Option<&str>,
+    ) -> AppResult<Transcript> {
+        let transcript = sqlx::query_as::<_, Transcript>(
+            r#"
+            UPDATE transcripts
+            SET transcription = COALESCE($3, transcription),
+                filename = COALESCE($4, filename),
+                word_count = COALESCE($5, word_count),
+                updated_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(transcript_id)
+        .bind(user_id)
+        .bind(transcription)
+        .bind(filename)
+        .bind(transcription.map(count_words))
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Transcript not found".to_string()))?;
+
+        log::info!("Transcript updated: {}", transcript_id);
+        Ok(transcript)
+    }
+
+    /// Add tags to a transcript, keeping the set sorted and within `MAX_TAGS`
+    pub async fn add_tags(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        user_id: Uuid,
+        tags: &[String],
+    ) -> AppResult<Transcript> {
+        let transcript = sqlx::query_as::<_, Transcript>(
+            r#"
+            UPDATE transcripts
+            SET tags = ARRAY(SELECT DISTINCT t FROM unnest(tags || $3::text[]) AS t ORDER BY t),
+                updated_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+              AND (SELECT COUNT(DISTINCT t) FROM unnest(tags || $3::text[]) AS t) <= $4
+            RETURNING *
+            "#,
+        )
+        .bind(transcript_id)
+        .bind(user_id)
+        .bind(tags)
+        .bind(validation::MAX_TAGS as i64)
+        .fetch_optional(pool)
+        .await?;
+
+        match transcript {
+            Some(transcript) => Ok(transcript),
+            None => {
+                // Tell a missing transcript apart from one that would exceed the limit
+                Self::load_owned(pool, transcript_id, user_id).await?;
+                Err(AppError::ValidationError(format!(
+                    "A transcript can have at most {} tags",
+                    validation::MAX_TAGS
+                )))
+            }
+        }
+    }
+
+    /// Remove tags from a transcript; tags it doesn't have are ignored
+    pub async fn remove_tags(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        user_id: Uuid,
+        tags: &[String],
+    ) -> AppResult<Transcript> {
+        let transcript = sqlx::query_as::<_, Transcript>(
+            r#"
+            UPDATE transcripts
+            SET tags = ARRAY(SELECT t FROM unnest(tags) AS t WHERE t <> ALL($3::text[]) ORDER BY t),
+                updated_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(transcript_id)
+        .bind(user_id)
+        .bind(tags)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Transcript not found".to_string()))?;
+
+        Ok(transcript)
+    }
+
+    /// Soft-delete transcript by ID for a user (restorable for `RESTORE_WINDOW_DAYS`)
+    pub async fn delete_transcript(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        user_id: Uuid,
+    ) -> AppResult<()> {
+        let result = sqlx::query(
+            "UPDATE transcripts SET deleted_at = NOW() WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(transcript_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Transcript not found".to_string()));
+        }
+
+        log::info!("Transcript deleted: {}", transcript_id);
+        Ok(())
+    }
+
+    /// Soft-delete several transcripts of a user in one statement, returning the IDs deleted
+    /// IDs that don't exist, belong to someone else or are already deleted are skipped
+    pub async fn delete_transcripts(
+        pool: &PgPool,
+        transcript_ids: &[Uuid],
+        user_id: Uuid,
+    ) -> AppResult<Vec<Uuid>> {
+        let deleted: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE transcripts SET deleted_at = NOW()
+            WHERE id = ANY($1) AND user_id = $2 AND deleted_at IS NULL
+            RETURNING id
+            "#,
+        )
+        .bind(transcript_ids)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        log::info!("{} transcripts deleted for user {}", deleted.len(), user_id);
+        Ok(deleted)
+    }
+
+    /// Restore a soft-deleted transcript that is still within the restore window
+    pub async fn restore_transcript(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        user_id: Uuid,
+    ) -> AppResult<Transcript> {
+        let transcript = sqlx::query_as::<_, Transcript>(
+            r#"
+            UPDATE transcripts SET deleted_at = NULL
+            WHERE id = $1 AND user_id = $2
+                AND deleted_at IS NOT NULL
+                AND deleted_at > NOW() - make_interval(days => $3)
+            RETURNING *
+            "#,
+        )
+        .bind(transcript_id)
+        .bind(user_id)
+        .bind(RESTORE_WINDOW_DAYS)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No restorable transcript found".to_string()))?;
+
+        log::info!("Transcript restored: {}", transcript_id);
+        Ok(transcript)
+    }
+
+    /// Permanently remove transcripts deleted longer ago than the restore window
+    pub async fn purge_expired(pool: &PgPool, storage: &dyn Storage) -> AppResult<u64> {
+        let purged: Vec<(Option<String>,)> = sqlx::query_as(
+            "DELETE FROM transcripts WHERE deleted_at IS NOT NULL AND deleted_at <= NOW() - make_interval(days => $1) RETURNING storage_key",
+        )
+        .bind(RESTORE_WINDOW_DAYS)
+        .fetch_all(pool)
+        .await?;
+
+        // Stored originals go with the transcript once it can no longer be restored
+        for storage_key in purged.iter().filter_map(|(key,)| key.as_deref()) {
+            if let Err(e) = storage.delete(storage_key).await {
+                log::warn!("Failed to delete stored audio {}: {}", storage_key, e);
+            }
+        }
+
+        if !purged.is_empty() {
+            log::info!("Purged {} expired deleted transcripts", purged.len());
+        }
+
+        Ok(purged.len() as u64)
+    }
+
+    /// Remove pending uploads and stored originals of transcripts whose rows were deleted
+    pub async fn remove_transcript_files(storage: &dyn Storage, transcripts: &[Transcript]) {
+        for transcript in transcripts {
+            if let Some(audio_path) = &transcript.audio_path
+                && let Err(e) = tokio::fs::remove_file(audio_path).await
+            {
+                log::warn!("Failed to remove audio file {}: {}", audio_path, e);
+            }
+            if let Some(storage_key) = &transcript.storage_key
+                && let Err(e) = storage.delete(storage_key).await
+            {
+                log::warn!("Failed to delete stored audio {}: {}", storage_key, e);
+            }
+        }
+    }
+
+    /// Reset a failed transcript to pending for another attempt, using a fresh copy of its audio
+    /// Returns `None` when the transcript is no longer retryable
+    pub async fn reset_for_retry(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        user_id: Uuid,
+        audio_path: &str,
+        max_attempts: i32,
+    ) -> AppResult<Option<Transcript>> {
+        let transcript = sqlx::query_as::<_, Transcript>(
+            r#"
+            UPDATE transcripts
+            SET status = $4, error_message = NULL, audio_path = $5, attempt_count = attempt_count + 1
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+              AND status = $3 AND attempt_count < $6
+            RETURNING *
+            "#,
+        )
+        .bind(transcript_id)
+        .bind(user_id)
+        .bind(transcript_status::FAILED)
+        .bind(transcript_status::PENDING)
+        .bind(audio_path)
+        .bind(max_attempts)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(transcript)
+    }
+
+    /// Record where the original upload of a transcript is stored
+    pub async fn set_storage_key(
+        pool: &PgPool,
+        transcript_id: Uuid,
+        storage_key: &str,
+    ) -> AppResult<()> {
+        sqlx::query("UPDATE transcripts SET storage_key = $1 WHERE id = $2")
+            .bind(storage_key)
+            .bind(transcript_id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Reject files FFprobe can't read or that have no audio stream to transcribe
+    pub async fn ensure_audio_stream(ffmpeg: &FfmpegLimiter, file_path: &str) -> AppResult<()> {
+        let _permit = ffmpeg.acquire("FFprobe").await?;
+        let output = tokio::process::Command::new("ffprobe")
+            .args([
+                "-v", "error",
+                "-select_streams", "a",
+                "-show_entries", "stream=codec_type",
+                "-of", "csv=p=0",
+            ])
+            .arg(file::ffmpeg_file_arg(file_path))
+            .output()
+            .await
+            .map_err(|e| AppError::FileError(format!("Failed to run FFprobe: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(ffmpeg_failure("FFprobe", &output));
+        }
+
+        if String::from_utf8_lossy(&output.stdout).trim().is_empty() {
+            return Err(AppError::ValidationError(
+                "The file does not contain an audio stream".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_audio_duration(ffmpeg: &FfmpegLimiter, file_path: &str) -> AppResult<f64> {
+        let _permit = ffmpeg.acquire("FFprobe").await?;
+        let output = tokio::process::Command::new("ffprobe")
+            .args([
+                "-v", "quiet",
+                "-show_entries", "format=duration",
                 "-of", "csv=p=0",
-                file_path
             ])
+            .arg(file::ffmpeg_file_arg(file_path))
             .output()
             .await
             .map_err(|e| AppError::FileError(format!("Failed to run FFprobe: {}", e)))?;
@@ -339,4 +1750,914 @@ impl TranscriptionService {
 
         Ok(duration)
     }
-}
\ No newline at end of file
+}
+
+/// Start and end of the calendar month (UTC) containing `now`, used as the quota period
+pub fn billing_period(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let (next_year, next_month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+
+    let start = Utc
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now);
+    let end = Utc
+        .with_ymd_and_hms(next_year, next_month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now);
+
+    (start, end)
+}
+
+/// Sample rate of the audio Whisper consumes
+pub const WHISPER_SAMPLE_RATE: usize = 16_000;
+
+/// WAVE_FORMAT_PCM: integer samples
+const WAVE_FORMAT_PCM: u16 = 1;
+
+/// WAVE_FORMAT_IEEE_FLOAT: 32 or 64-bit float samples
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+
+/// WAVE_FORMAT_EXTENSIBLE: the real format is in the sub-format GUID
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Sample layout read from a WAV `fmt` chunk
+#[derive(Debug, Clone, Copy)]
+struct WavFormat {
+    audio_format: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+}
+
+impl WavFormat {
+    /// Parse a `fmt` chunk, rejecting layouts the loader can't decode
+    fn parse(body: &[u8]) -> AppResult<Self> {
+        if body.len() < 16 {
+            return Err(AppError::FileError("Invalid WAV file - truncated fmt chunk".to_string()));
+        }
+
+        let mut audio_format = u16::from_le_bytes([body[0], body[1]]);
+        let channels = u16::from_le_bytes([body[2], body[3]]);
+        let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+        let bits_per_sample = u16::from_le_bytes([body[14], body[15]]);
+
+        // The first two bytes of the sub-format GUID hold the actual format code
+        if audio_format == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+            audio_format = u16::from_le_bytes([body[24], body[25]]);
+        }
+
+        let supported = match audio_format {
+            WAVE_FORMAT_PCM => matches!(bits_per_sample, 8 | 16 | 24 | 32),
+            WAVE_FORMAT_IEEE_FLOAT => matches!(bits_per_sample, 32 | 64),
+            _ => false,
+        };
+
+        if !supported || channels == 0 || sample_rate == 0 {
+            return Err(AppError::FileError(format!(
+                "Unsupported WAV format: format={}, channels={}, sample_rate={}, bits_per_sample={}",
+                audio_format, channels, sample_rate, bits_per_sample
+            )));
+        }
+
+        Ok(Self {
+            audio_format,
+            channels,
+            sample_rate,
+            bits_per_sample,
+        })
+    }
+
+    /// Whether samples are already in the 16-bit PCM layout FFmpeg is asked for
+    fn is_pcm16(&self) -> bool {
+        self.audio_format == WAVE_FORMAT_PCM && self.bits_per_sample == 16
+    }
+
+    /// Decode one little-endian sample to -1.0..1.0
+    fn decode_sample(&self, bytes: &[u8]) -> f32 {
+        match (self.audio_format, self.bits_per_sample) {
+            // 8-bit PCM is unsigned, centred on 128
+            (WAVE_FORMAT_PCM, 8) => (bytes[0] as f32 - 128.0) / 128.0,
+            (WAVE_FORMAT_PCM, 16) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            (WAVE_FORMAT_PCM, 24) => {
+                // Shift into the top of an i32 so the sign bit is extended
+                i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) as f32 / 2_147_483_648.0
+            }
+            (WAVE_FORMAT_PCM, 32) => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2_147_483_648.0
+            }
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            (WAVE_FORMAT_IEEE_FLOAT, 64) => f64::from_le_bytes([
+                bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+            ]) as f32,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Resample mono audio by linear interpolation between neighbouring samples
+fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if samples.is_empty() || from_rate == to_rate {
+        return samples.to_vec();
+    }
+
+    let step = from_rate as f64 / to_rate as f64;
+    let output_len = (samples.len() as f64 / step).floor() as usize;
+
+    (0..output_len)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index];
+            let next = samples.get(index + 1).copied().unwrap_or(current);
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+/// Region of the decoded audio transcribed in one Whisper pass
+/// Segments are kept when their midpoint lies in `keep_from_ms..keep_until_ms`
+#[derive(Debug, Clone)]
+struct AudioChunk {
+    start: usize,
+    end: usize,
+    keep_from_ms: i64,
+    keep_until_ms: i64,
+}
+
+impl AudioChunk {
+    /// Whether a segment (in trimmed-audio time) belongs to this chunk rather than a neighbour
+    fn keeps(&self, segment: &TranscriptSegment) -> bool {
+        let midpoint = (segment.start_ms + segment.end_ms) / 2;
+        midpoint >= self.keep_from_ms && midpoint < self.keep_until_ms
+    }
+}
+
+/// Progress of concurrently transcribed chunks, reported as their average
+struct ChunkProgress {
+    per_chunk: Vec<i32>,
+    callback: Option<ProgressCallback>,
+}
+
+impl ChunkProgress {
+    fn report(&mut self, index: usize, value: i32) {
+        self.per_chunk[index] = value;
+        let average = self.per_chunk.iter().sum::<i32>() / self.per_chunk.len() as i32;
+        if let Some(callback) = self.callback.as_mut() {
+            callback(average);
+        }
+    }
+}
+
+/// Receives each new segment's start and end (in 10ms units) and text while Whisper runs
+type SegmentHandler = Box<dyn FnMut(i64, i64, &str)>;
+
+/// Stream new segments to `handler` through whisper.cpp's raw new-segment callback, which
+/// whisper-rs 0.10 has no safe wrapper for. `handler` must stay alive and in place until
+/// the `state.full` call using `params` has returned.
+fn set_segment_handler(params: &mut FullParams<'_, '_>, handler: &mut SegmentHandler) {
+    // SAFETY: the trampoline only reads segments from the state it is given, and the caller
+    // keeps `handler` alive for as long as whisper.cpp may call it
+    unsafe {
+        params.set_new_segment_callback(Some(new_segment_trampoline));
+        params.set_new_segment_callback_user_data(
+            handler as *mut SegmentHandler as *mut std::ffi::c_void,
+        );
+    }
+}
+
+/// Forward the `n_new` latest segments of a Whisper state to the handler in `user_data`
+/// whisper.cpp only calls this from inside `full()`, so `user_data` (the handler registered
+/// by `set_segment_handler`) must outlive that call and must not move while it runs.
+unsafe extern "C" fn new_segment_trampoline(
+    _ctx: *mut whisper_rs::WhisperSysContext,
+    state: *mut whisper_rs::WhisperSysState,
+    n_new: std::os::raw::c_int,
+    user_data: *mut std::ffi::c_void,
+) {
+    // SAFETY: `user_data` is the handler registered by `set_segment_handler`, and `state` is
+    // the live state whisper.cpp is decoding into
+    unsafe {
+        let handler = &mut *(user_data as *mut SegmentHandler);
+        let total = whisper_rs_sys::whisper_full_n_segments_from_state(state);
+        for index in (total - n_new).max(0)..total {
+            let text = whisper_rs_sys::whisper_full_get_segment_text_from_state(state, index);
+            if text.is_null() {
+                continue;
+            }
+            let text = std::ffi::CStr::from_ptr(text).to_string_lossy();
+            let t0 = whisper_rs_sys::whisper_full_get_segment_t0_from_state(state, index);
+            let t1 = whisper_rs_sys::whisper_full_get_segment_t1_from_state(state, index);
+            // Unwinding into whisper.cpp would abort; a failing handler only loses the segment
+            let call = std::panic::AssertUnwindSafe(|| handler(t0, t1, &text));
+            if std::panic::catch_unwind(call).is_err() {
+                log::warn!("Segment handler panicked; partial segment dropped");
+            }
+        }
+    }
+}
+
+/// Make Whisper stop at its next encode or decode step once `flag` is set
+/// `flag` must stay alive until the `state.full` call using `params` has returned.
+fn set_abort_flag(params: &mut FullParams<'_, '_>, flag: &AtomicBool) {
+    // SAFETY: the trampoline only reads the flag, which the caller keeps alive for as long
+    // as whisper.cpp may call it
+    unsafe {
+        params.set_abort_callback(Some(abort_trampoline));
+        params.set_abort_callback_user_data(flag as *const AtomicBool as *mut std::ffi::c_void);
+    }
+}
+
+/// Tell whisper.cpp whether the flag registered by `set_abort_flag` is set
+unsafe extern "C" fn abort_trampoline(user_data: *mut std::ffi::c_void) -> bool {
+    // SAFETY: `user_data` is the flag registered by `set_abort_flag`
+    unsafe { (*(user_data as *const AtomicBool)).load(Ordering::Relaxed) }
+}
+
+/// Whether a file name follows a pattern this service uses in the temp directory:
+/// `ai-scribe-<pid>-<uuid>.wav` intermediates and `<timestamp>_<uuid>[.ext]` uploads
+fn is_temp_file_name(name: &str) -> bool {
+    if name.starts_with("ai-scribe-") {
+        return true;
+    }
+
+    let stem = name.split('.').next().unwrap_or(name);
+    match stem.split_once('_') {
+        Some((timestamp, uuid)) => {
+            !timestamp.is_empty()
+                && timestamp.bytes().all(|byte| byte.is_ascii_digit())
+                && Uuid::parse_str(uuid).is_ok()
+        }
+        None => false,
+    }
+}
+
+/// Cancels chunk transcription when it is abandoned, e.g. on timeout. Chunks still waiting
+/// for a slot never start, and chunks inside Whisper stop at their next encode or decode step.
+struct AbortOnDrop {
+    handles: Vec<tokio::task::AbortHandle>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+/// RMS level of samples in dBFS; negative infinity for digital silence or no samples
+fn rms_db(samples: &[f32]) -> f64 {
+    if samples.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    let sum_of_squares: f64 = samples.iter().map(|&sample| (sample as f64).powi(2)).sum();
+    let rms = (sum_of_squares / samples.len() as f64).sqrt();
+    20.0 * rms.log10()
+}
+
+pub fn samples_to_ms(samples: usize) -> i64 {
+    (samples * 1000 / WHISPER_SAMPLE_RATE) as i64
+}
+
+/// Number of whitespace-separated words in a text
+pub fn count_words(text: &str) -> i32 {
+    text.split_whitespace().count() as i32
+}
+
+/// Join segment texts into the full transcription according to a `text_format` value.
+/// Whisper starts each segment with a space when it begins a new word; `verbatim` keeps that
+/// convention as the separator, the other formats trim it and insert their own.
+pub fn join_segments(segments: &[TranscriptSegment], format: &str) -> String {
+    if format == text_format::VERBATIM {
+        return segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect::<String>()
+            .trim()
+            .to_string();
+    }
+
+    let separator = if format == text_format::LINES { "\n" } else { " " };
+    segments
+        .iter()
+        .map(|segment| segment.text.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Most frequent value, preferring the earliest on ties
+fn most_common(values: Vec<String>) -> Option<String> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for value in values {
+        match counts.iter_mut().find(|(existing, _)| *existing == value) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+
+    let max_count = counts.iter().map(|(_, count)| *count).max()?;
+    counts
+        .into_iter()
+        .find(|(_, count)| *count == max_count)
+        .map(|(value, _)| value)
+}
+
+/// Speech kept on each side of a trimmed silence, so words aren't clipped
+const SILENCE_PADDING_SECONDS: f64 = 0.25;
+
+/// FFmpeg filter expression of an `audio_filter` name
+/// Uploads only ever pick from these fixed expressions. The output format options still
+/// apply after them, so the WAV stays 16kHz mono whatever a filter does to the sample rate.
+fn audio_filter_expression(name: &str) -> Option<&'static str> {
+    match name {
+        audio_filter::HIGHPASS => Some("highpass=f=80"),
+        audio_filter::LOWPASS => Some("lowpass=f=7500"),
+        audio_filter::DENOISE => Some("afftdn=nf=-25"),
+        audio_filter::LOUDNORM => Some("loudnorm=I=-16:TP=-1.5:LRA=11"),
+        _ => None,
+    }
+}
+
+/// Maps timestamps in silence-trimmed audio back to the original recording
+#[derive(Debug, Default)]
+struct SilenceMap {
+    /// (position in the trimmed audio, total milliseconds removed before it)
+    cuts: Vec<(i64, i64)>,
+}
+
+impl SilenceMap {
+    /// Cut silent intervals (in seconds) out of the samples
+    /// Nothing is cut when the whole recording would be removed
+    fn trim(samples: &mut Vec<f32>, silences: &[(f64, f64)]) -> Self {
+        if silences.is_empty() {
+            return Self::default();
+        }
+
+        let total = samples.len();
+        let sample_rate = WHISPER_SAMPLE_RATE as f64;
+        let padding = (SILENCE_PADDING_SECONDS * sample_rate) as usize;
+
+        let mut kept = Vec::with_capacity(total);
+        let mut cuts = Vec::new();
+        let mut cursor = 0;
+        let mut removed = 0;
+
+        // Float to integer casts saturate, so negative starts and open-ended silences clamp
+        for &(start, end) in silences {
+            let cut_start = ((start * sample_rate) as usize)
+                .saturating_add(padding)
+                .clamp(cursor, total);
+            let cut_end = ((end * sample_rate) as usize).saturating_sub(padding).min(total);
+            if cut_end <= cut_start {
+                continue;
+            }
+
+            kept.extend_from_slice(&samples[cursor..cut_start]);
+            removed += cut_end - cut_start;
+            cuts.push((samples_to_ms(kept.len()), samples_to_ms(removed)));
+            cursor = cut_end;
+        }
+        kept.extend_from_slice(&samples[cursor..]);
+
+        if kept.is_empty() || cuts.is_empty() {
+            return Self::default();
+        }
+
+        *samples = kept;
+        Self { cuts }
+    }
+
+    /// Total milliseconds removed from the recording
+    fn removed_ms(&self) -> i64 {
+        self.cuts.last().map(|(_, removed)| *removed).unwrap_or(0)
+    }
+
+    fn to_original_ms(&self, trimmed_ms: i64) -> i64 {
+        let shift = self
+            .cuts
+            .iter()
+            .take_while(|(position, _)| *position <= trimmed_ms)
+            .last()
+            .map(|(_, removed)| *removed)
+            .unwrap_or(0);
+        trimmed_ms + shift
+    }
+
+    /// Shift a segment back to original-audio time
+    /// A segment ending exactly at a cut ends before the removed silence, not after it
+    fn restore(&self, segment: &mut TranscriptSegment) {
+        (segment.start_ms, segment.end_ms) = self.restore_span(segment.start_ms, segment.end_ms);
+        for word in segment.words.iter_mut() {
+            (word.start_ms, word.end_ms) = self.restore_span(word.start_ms, word.end_ms);
+        }
+    }
+
+    fn restore_span(&self, start_ms: i64, end_ms: i64) -> (i64, i64) {
+        let start_ms = self.to_original_ms(start_ms);
+        let end_ms = self.to_original_ms(end_ms - 1) + 1;
+        (start_ms, end_ms.max(start_ms))
+    }
+}
+
+/// Turn a failed FFmpeg/FFprobe run into an error. Failures caused by the upload itself
+/// become a concise validation error; the full stderr is only logged at debug level.
+fn ffmpeg_failure(tool: &str, output: &std::process::Output) -> AppError {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    log::debug!("{} failed ({}): {}", tool, output.status, stderr);
+
+    match describe_ffmpeg_failure(&stderr) {
+        Some(cause) => AppError::ValidationError(cause.to_string()),
+        None => AppError::FileError(format!("{} failed ({})", tool, output.status)),
+    }
+}
+
+/// Recognise common FFmpeg failure signatures caused by the input file
+fn describe_ffmpeg_failure(stderr: &str) -> Option<&'static str> {
+    let stderr = stderr.to_lowercase();
+
+    if stderr.contains("does not contain any stream")
+        || stderr.contains("matches no streams")
+        || stderr.contains("output file does not contain any stream")
+    {
+        Some("The file does not contain an audio stream")
+    } else if (stderr.contains("decoder") && stderr.contains("not found"))
+        || stderr.contains("unknown decoder")
+        || stderr.contains("unsupported codec")
+        || stderr.contains("could not find codec parameters")
+    {
+        Some("The audio codec is not supported")
+    } else if stderr.contains("moov atom not found")
+        || stderr.contains("invalid data found when processing input")
+        || stderr.contains("header missing")
+        || stderr.contains("end of file")
+    {
+        Some("The file is corrupt or truncated and could not be decoded")
+    } else {
+        None
+    }
+}
+
+/// Parse `silence_start`/`silence_end` pairs from FFmpeg silencedetect output
+/// A silence still open at the end of the file runs to the end of the recording
+fn parse_silences(stderr: &str) -> Vec<(f64, f64)> {
+    fn value_after(line: &str, key: &str) -> Option<f64> {
+        line.split(key).nth(1)?.split_whitespace().next()?.parse().ok()
+    }
+
+    let mut silences = Vec::new();
+    let mut silence_start = None;
+    for line in stderr.lines() {
+        if let Some(start) = value_after(line, "silence_start: ") {
+            silence_start = Some(start);
+        } else if let Some(end) = value_after(line, "silence_end: ")
+            && let Some(start) = silence_start.take()
+        {
+            silences.push((start, end));
+        }
+    }
+    if let Some(start) = silence_start {
+        silences.push((start, f64::INFINITY));
+    }
+
+    silences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+
+    /// RIFF chunk: id, little-endian size and a body padded to an even length
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    /// `fmt ` chunk body for PCM audio
+    fn fmt_body(channels: u16, sample_rate: u32, bits_per_sample: u16) -> Vec<u8> {
+        let block_align = channels * bits_per_sample / 8;
+        let mut body = WAVE_FORMAT_PCM.to_le_bytes().to_vec();
+        body.extend_from_slice(&channels.to_le_bytes());
+        body.extend_from_slice(&sample_rate.to_le_bytes());
+        body.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        body.extend_from_slice(&block_align.to_le_bytes());
+        body.extend_from_slice(&bits_per_sample.to_le_bytes());
+        body
+    }
+
+    /// 16-bit little-endian PCM samples
+    fn pcm16(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+    }
+
+    fn wav(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    #[test]
+    fn decodes_16khz_mono_pcm() {
+        let bytes = wav(&[
+            chunk(b"fmt ", &fmt_body(1, 16000, 16)),
+            chunk(b"data", &pcm16(&[0, 16384, -32768])),
+        ]);
+
+        let samples = TranscriptionService::decode_wav_samples(&bytes).unwrap();
+
+        assert_eq!(samples, vec![0.0, 0.5, -1.0]);
+    }
+
+    #[test]
+    fn skips_list_chunk_before_data() {
+        let bytes = wav(&[
+            chunk(b"fmt ", &fmt_body(1, 16000, 16)),
+            chunk(b"LIST", b"INFOISFT\x06\x00\x00\x00Lavf\x00\x00"),
+            chunk(b"data", &pcm16(&[16384, 16384])),
+        ]);
+
+        let samples = TranscriptionService::decode_wav_samples(&bytes).unwrap();
+
+        assert_eq!(samples, vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn skips_pad_byte_after_odd_sized_chunk() {
+        let bytes = wav(&[
+            chunk(b"fmt ", &fmt_body(1, 16000, 16)),
+            chunk(b"junk", b"odd"),
+            chunk(b"data", &pcm16(&[-16384])),
+        ]);
+
+        let samples = TranscriptionService::decode_wav_samples(&bytes).unwrap();
+
+        assert_eq!(samples, vec![-0.5]);
+    }
+
+    #[test]
+    fn averages_stereo_to_mono() {
+        let bytes = wav(&[
+            chunk(b"fmt ", &fmt_body(2, 16000, 16)),
+            chunk(b"data", &pcm16(&[16384, 0, -32768, 0])),
+        ]);
+
+        let samples = TranscriptionService::decode_wav_samples(&bytes).unwrap();
+
+        assert_eq!(samples, vec![0.25, -0.5]);
+    }
+
+    #[test]
+    fn rejects_missing_data_chunk() {
+        let bytes = wav(&[chunk(b"fmt ", &fmt_body(1, 16000, 16))]);
+
+        let result = TranscriptionService::decode_wav_samples(&bytes);
+
+        assert!(matches!(result, Err(AppError::FileError(message)) if message.contains("data")));
+    }
+
+    #[test]
+    fn rejects_data_before_fmt_chunk() {
+        let bytes = wav(&[
+            chunk(b"data", &pcm16(&[0])),
+            chunk(b"fmt ", &fmt_body(1, 16000, 16)),
+        ]);
+
+        let result = TranscriptionService::decode_wav_samples(&bytes);
+
+        assert!(matches!(result, Err(AppError::FileError(message)) if message.contains("fmt")));
+    }
+
+    #[test]
+    fn rejects_zero_channels() {
+        let result = WavFormat::parse(&fmt_body(0, 16000, 16));
+
+        assert!(matches!(result, Err(AppError::FileError(_))));
+    }
+
+    #[test]
+    fn rejects_truncated_fmt_chunk() {
+        let result = WavFormat::parse(&fmt_body(1, 16000, 16)[..12]);
+
+        assert!(matches!(result, Err(AppError::FileError(_))));
+    }
+
+    #[test]
+    fn reads_format_code_from_extensible_fmt() {
+        let mut body = fmt_body(1, 16000, 32);
+        body[0..2].copy_from_slice(&WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        // cbSize, valid bits and channel mask, then the sub-format GUID
+        body.extend_from_slice(&[22, 0, 32, 0, 4, 0, 0, 0]);
+        body.extend_from_slice(&WAVE_FORMAT_IEEE_FLOAT.to_le_bytes());
+        body.extend_from_slice(&[0; 14]);
+
+        let format = WavFormat::parse(&body).unwrap();
+
+        assert_eq!(format.audio_format, WAVE_FORMAT_IEEE_FLOAT);
+    }
+
+    #[test]
+    fn decodes_signed_24_bit_samples() {
+        let format = WavFormat::parse(&fmt_body(1, 16000, 24)).unwrap();
+
+        assert_eq!(format.decode_sample(&[0x00, 0x00, 0x80]), -1.0);
+        assert_eq!(format.decode_sample(&[0x00, 0x00, 0x40]), 0.5);
+    }
+
+    #[test]
+    fn dropping_abort_guard_raises_whisper_abort_flag() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let user_data = Arc::as_ptr(&cancelled) as *mut std::ffi::c_void;
+        let guard = AbortOnDrop {
+            handles: Vec::new(),
+            cancelled: cancelled.clone(),
+        };
+
+        assert!(!unsafe { abort_trampoline(user_data) });
+        drop(guard);
+        assert!(unsafe { abort_trampoline(user_data) });
+    }
+
+    #[test]
+    fn resamples_to_16khz() {
+        let samples = resample_linear(&[0.0, 1.0, 0.0, 1.0], 32000, 16000);
+
+        assert_eq!(samples, vec![0.0, 0.0]);
+    }
+
+    /// User who owns the transcripts created by `create_job`
+    async fn create_user(pool: &PgPool, email: &str) -> Uuid {
+        let params = argon2::Params::new(8, 1, 1, None).unwrap();
+        crate::services::UserService::register_user(pool, email, "Password 1", &params)
+            .await
+            .unwrap()
+            .id
+    }
+
+    async fn create_job(pool: &PgPool, user_id: Uuid, filename: &str) -> Transcript {
+        let job = NewTranscriptionJob {
+            filename: filename.to_string(),
+            file_size: 1024,
+            duration_seconds: Some(1.0),
+            language: None,
+            audio_path: format!("/tmp/{}", filename),
+            diarize: false,
+            trim_silence: false,
+            initial_prompt: None,
+            text_format: text_format::PLAIN.to_string(),
+            word_timestamps: false,
+            model_name: "base".to_string(),
+            capitalize_sentences: false,
+            numbers_to_digits: false,
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            audio_filters: Vec::new(),
+            source_ip: None,
+            user_agent: None,
+        };
+        TranscriptionService::create_transcription_job(pool, user_id, &job).await.unwrap()
+    }
+
+    /// Backdate a transcript's deletion by `age`
+    async fn deleted_ago(pool: &PgPool, transcript_id: Uuid, age: chrono::Duration) {
+        sqlx::query("UPDATE transcripts SET deleted_at = $2 WHERE id = $1")
+            .bind(transcript_id)
+            .bind(Utc::now() - age)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    fn restore_window() -> chrono::Duration {
+        chrono::Duration::days(RESTORE_WINDOW_DAYS as i64)
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn deleted_transcripts_are_hidden_from_listings(pool: PgPool) {
+        let user_id = create_user(&pool, "listing@example.com").await;
+        let kept = create_job(&pool, user_id, "kept.wav").await;
+        let deleted = create_job(&pool, user_id, "deleted.wav").await;
+
+        TranscriptionService::delete_transcript(&pool, deleted.id, user_id).await.unwrap();
+
+        let (transcripts, total) = TranscriptionService::get_user_transcripts(
+            &pool,
+            user_id,
+            1,
+            10,
+            &TranscriptFilter::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(transcripts.iter().map(|t| t.id).collect::<Vec<_>>(), vec![kept.id]);
+        assert_eq!(total, 1);
+        let result = TranscriptionService::load_owned(&pool, deleted.id, user_id).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn restores_only_within_window(pool: PgPool) {
+        let user_id = create_user(&pool, "restore@example.com").await;
+        let recent = create_job(&pool, user_id, "recent.wav").await;
+        let expired = create_job(&pool, user_id, "expired.wav").await;
+        let live = create_job(&pool, user_id, "live.wav").await;
+        deleted_ago(&pool, recent.id, restore_window() - chrono::Duration::minutes(1)).await;
+        deleted_ago(&pool, expired.id, restore_window() + chrono::Duration::minutes(1)).await;
+
+        let restored = TranscriptionService::restore_transcript(&pool, recent.id, user_id)
+            .await
+            .unwrap();
+        assert!(restored.deleted_at.is_none());
+        TranscriptionService::load_owned(&pool, recent.id, user_id).await.unwrap();
+
+        let result = TranscriptionService::restore_transcript(&pool, expired.id, user_id).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        // Only deleted transcripts can be restored
+        let result = TranscriptionService::restore_transcript(&pool, live.id, user_id).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn purge_removes_only_expired_deletions(pool: PgPool) {
+        let user_id = create_user(&pool, "purge@example.com").await;
+        let live = create_job(&pool, user_id, "live.wav").await;
+        let recent = create_job(&pool, user_id, "recent.wav").await;
+        let expired = create_job(&pool, user_id, "expired.wav").await;
+        deleted_ago(&pool, recent.id, restore_window() - chrono::Duration::minutes(1)).await;
+        deleted_ago(&pool, expired.id, restore_window() + chrono::Duration::minutes(1)).await;
+        let storage = LocalStorage::new(&std::env::temp_dir().to_string_lossy());
+
+        let purged = TranscriptionService::purge_expired(&pool, &storage).await.unwrap();
+
+        assert_eq!(purged, 1);
+        let remaining: Vec<Uuid> =
+            sqlx::query_scalar("SELECT id FROM transcripts ORDER BY created_at")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(remaining, vec![live.id, recent.id]);
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn usage_includes_live_streams(pool: PgPool) {
+        let user_id = create_user(&pool, "usage@example.com").await;
+        create_job(&pool, user_id, "upload.wav").await;
+        TranscriptionService::record_live_usage(&pool, user_id, 30.0).await.unwrap();
+        let period_start = Utc::now() - chrono::Duration::days(1);
+
+        let used_seconds =
+            TranscriptionService::get_usage_seconds(&pool, user_id, period_start).await.unwrap();
+
+        assert_eq!(used_seconds, 31.0);
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn completed_jobs_are_charged_their_decoded_length(pool: PgPool) {
+        let user_id = create_user(&pool, "decoded@example.com").await;
+        let transcript = create_job(&pool, user_id, "no-duration.webm").await;
+        // FFprobe couldn't time the upload
+        sqlx::query("UPDATE transcripts SET duration_seconds = NULL WHERE id = $1")
+            .bind(transcript.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let result = TranscriptionResult {
+            text: "Hello".to_string(),
+            language: Some("en".to_string()),
+            detected_language: None,
+            language_probabilities: Vec::new(),
+            segments: Vec::new(),
+            duration_seconds: 42.0,
+            transcription_seconds: 1.0,
+            processing_seconds: 2.0,
+            whisper_threads: 1,
+        };
+
+        TranscriptionService::complete_transcription(&pool, transcript.id, &result, "base")
+            .await
+            .unwrap();
+
+        let period_start = Utc::now() - chrono::Duration::days(1);
+        let used_seconds =
+            TranscriptionService::get_usage_seconds(&pool, user_id, period_start).await.unwrap();
+        assert_eq!(used_seconds, 42.0);
+    }
+
+    #[test]
+    fn billing_period_is_the_calendar_month() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 17, 13, 45, 0).unwrap();
+
+        let (start, end) = billing_period(now);
+
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn billing_period_rolls_december_over_into_january() {
+        let now = Utc.with_ymd_and_hms(2024, 12, 31, 23, 59, 59).unwrap();
+
+        let (start, end) = billing_period(now);
+
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 12, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn load_owned_hides_other_users_transcripts(pool: PgPool) {
+        let owner = create_user(&pool, "owner@example.com").await;
+        let other = create_user(&pool, "other@example.com").await;
+        let transcript = create_job(&pool, owner, "owned.wav").await;
+
+        let loaded = TranscriptionService::load_owned(&pool, transcript.id, owner).await.unwrap();
+        assert_eq!(loaded.id, transcript.id);
+
+        // Someone else's transcript looks exactly like a missing one
+        let result = TranscriptionService::load_owned(&pool, transcript.id, other).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        let result = TranscriptionService::load_owned(&pool, Uuid::new_v4(), owner).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn load_owned_treats_deleted_transcripts_as_missing(pool: PgPool) {
+        let owner = create_user(&pool, "deleted-owner@example.com").await;
+        let transcript = create_job(&pool, owner, "deleted.wav").await;
+        TranscriptionService::delete_transcript(&pool, transcript.id, owner).await.unwrap();
+
+        let result = TranscriptionService::load_owned(&pool, transcript.id, owner).await;
+
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    /// Run `load_audio` on a WAV of `samples` with its own temp dir, returning the result and
+    /// whatever files were left in the temp dir
+    async fn load_audio_in_temp_dir(samples: &[i16]) -> (AppResult<Vec<f32>>, Vec<String>) {
+        let temp_dir = std::env::temp_dir().join(format!("ai-scribe-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let input = TempFile::new(format!("{}.wav", temp_dir.display()));
+        let bytes = wav(&[
+            chunk(b"fmt ", &fmt_body(1, 16000, 16)),
+            chunk(b"data", &pcm16(samples)),
+        ]);
+        std::fs::write(input.path(), bytes).unwrap();
+        let mut config = Config::for_tests();
+        config.temp_dir = temp_dir.to_string_lossy().to_string();
+        let options = TranscribeOptions {
+            language: None,
+            trim_silence: false,
+            initial_prompt: None,
+            text_format: text_format::PLAIN,
+            word_timestamps: false,
+            postprocess: PostProcessOptions::default(),
+            decoding: DecodingOptions::from_config(&config),
+            audio_filters: &[],
+        };
+
+        let ffmpeg = FfmpegLimiter::new(1);
+        let result = TranscriptionService::load_audio(input.path(), &config, &ffmpeg, &options)
+            .await
+            .map(|(samples, _)| samples);
+
+        let left: Vec<String> = std::fs::read_dir(&temp_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        std::fs::remove_dir_all(&temp_dir).ok();
+        (result, left)
+    }
+
+    #[tokio::test]
+    async fn removes_intermediate_wav_after_loading() {
+        let tone: Vec<i16> = (0..1600).map(|i| if i % 2 == 0 { 8000 } else { -8000 }).collect();
+
+        let (result, left) = load_audio_in_temp_dir(&tone).await;
+
+        assert_eq!(result.unwrap().len(), tone.len());
+        assert!(left.is_empty(), "left behind: {:?}", left);
+    }
+
+    #[tokio::test]
+    async fn removes_intermediate_wav_when_loading_fails() {
+        // Converts fine, then fails the silence check after the WAV is written
+        let (result, left) = load_audio_in_temp_dir(&[0; 1600]).await;
+
+        assert!(matches!(result, Err(AppError::ValidationError(_))));
+        assert!(left.is_empty(), "left behind: {:?}", left);
+    }
+}
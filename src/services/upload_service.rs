@@ -0,0 +1,128 @@
+use crate::errors::{AppError, AppResult};
+use crate::models::Upload;
+use dashmap::DashSet;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Uploads with a request currently writing to them, so parts can't interleave
+#[derive(Clone, Default)]
+pub struct UploadLocks {
+    active: Arc<DashSet<Uuid>>,
+}
+
+impl UploadLocks {
+    /// Claim an upload for one request, failing with `Conflict` while another holds it
+    pub fn try_lock(&self, upload_id: Uuid) -> AppResult<UploadLock> {
+        if !self.active.insert(upload_id) {
+            return Err(AppError::Conflict(
+                "Another request is still writing to this upload".to_string(),
+            ));
+        }
+
+        Ok(UploadLock {
+            active: self.active.clone(),
+            upload_id,
+        })
+    }
+}
+
+/// An upload claimed by a request, released when dropped
+pub struct UploadLock {
+    active: Arc<DashSet<Uuid>>,
+    upload_id: Uuid,
+}
+
+impl Drop for UploadLock {
+    fn drop(&mut self) {
+        self.active.remove(&self.upload_id);
+    }
+}
+
+/// Resumable upload bookkeeping
+pub struct UploadService;
+
+impl UploadService {
+    /// Record a new upload whose parts will be written to `path`
+    pub async fn create(
+        pool: &PgPool,
+        user_id: Uuid,
+        filename: &str,
+        path: &str,
+        total_size: i64,
+    ) -> AppResult<Upload> {
+        let upload = sqlx::query_as::<_, Upload>(
+            r#"
+            INSERT INTO uploads (id, user_id, filename, path, total_size)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(filename)
+        .bind(path)
+        .bind(total_size)
+        .fetch_one(pool)
+        .await?;
+
+        log::info!("Resumable upload created: {} ({} bytes)", upload.id, total_size);
+        Ok(upload)
+    }
+
+    /// Get one of the user's uploads
+    pub async fn get(pool: &PgPool, upload_id: Uuid, user_id: Uuid) -> AppResult<Upload> {
+        sqlx::query_as::<_, Upload>("SELECT * FROM uploads WHERE id = $1 AND user_id = $2")
+            .bind(upload_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Upload not found".to_string()))
+    }
+
+    /// Move the received offset from `from` to `to` once the bytes in between are on disk
+    pub async fn advance(pool: &PgPool, upload_id: Uuid, from: i64, to: i64) -> AppResult<()> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE uploads SET received_bytes = $3, updated_at = NOW()
+            WHERE id = $1 AND received_bytes = $2
+            "#,
+        )
+        .bind(upload_id)
+        .bind(from)
+        .bind(to)
+        .execute(pool)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(AppError::Conflict("Upload offset changed while receiving data".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Forget an upload; its file is left to the caller
+    pub async fn delete(pool: &PgPool, upload_id: Uuid) -> AppResult<()> {
+        sqlx::query("DELETE FROM uploads WHERE id = $1")
+            .bind(upload_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete uploads that received nothing for longer than `max_age`, along with their files
+    pub async fn expire_stale(pool: &PgPool, max_age: Duration) -> AppResult<usize> {
+        let paths: Vec<String> = sqlx::query_scalar(
+            "DELETE FROM uploads WHERE updated_at < NOW() - make_interval(secs => $1) RETURNING path",
+        )
+        .bind(max_age.as_secs_f64())
+        .fetch_all(pool)
+        .await?;
+
+        for path in &paths {
+            tokio::fs::remove_file(path).await.ok();
+        }
+
+        Ok(paths.len())
+    }
+}
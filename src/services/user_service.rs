@@ -1,7 +1,8 @@
 use crate::errors::{AppError, AppResult};
-use crate::models::{Claims, User};
-use crate::utils::{jwt, password};
-use chrono::Utc;
+use crate::models::{user_role, Claims, Session, Transcript, User};
+use crate::utils::{jwt, password, token, validation};
+use argon2::Params;
+use chrono::{DateTime, Duration, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -10,50 +11,78 @@ pub struct UserService;
 
 impl UserService {
     /// Register a new user
-    pub async fn register_user(pool: &PgPool, email: &str, password: &str) -> AppResult<User> {
+    pub async fn register_user(
+        pool: &PgPool,
+        email: &str,
+        password: &str,
+        argon2_params: &Params,
+    ) -> AppResult<User> {
+        let email = validation::normalize_email(email);
+        let email = email.as_str();
+
         // Check if user already exists
-        let existing_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+        let existing_user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = $1")
             .bind(email)
             .fetch_optional(pool)
             .await?;
 
         if existing_user.is_some() {
-            return Err(AppError::ValidationError(
+            return Err(AppError::Conflict(
                 "User with this email already exists".to_string(),
             ));
         }
 
         // Hash password
-        let password_hash = password::hash_password(password)?;
+        let password_hash = password::hash_password(password, argon2_params)?;
 
-        // Create new user
+        // Create new user; the very first account becomes the administrator
         let user_id = Uuid::new_v4();
         let now = Utc::now();
 
         let user = sqlx::query_as::<_, User>(
             r#"
-            INSERT INTO users (id, email, password_hash, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO users (id, email, password_hash, role, created_at, updated_at)
+            VALUES (
+                $1, $2, $3,
+                CASE WHEN EXISTS (SELECT 1 FROM users) THEN $4 ELSE $5 END,
+                $6, $7
+            )
             RETURNING *
             "#,
         )
         .bind(user_id)
         .bind(email)
         .bind(password_hash)
+        .bind(user_role::USER)
+        .bind(user_role::ADMIN)
         .bind(now)
         .bind(now)
         .fetch_one(pool)
-        .await?;
+        .await
+        .map_err(|e| match e {
+            // A concurrent registration won the race past the check above
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                AppError::Conflict("User with this email already exists".to_string())
+            }
+            e => e.into(),
+        })?;
 
-        log::info!("New user registered: {}", email);
+        log::info!("New user registered: {} ({})", email, user.role);
         Ok(user)
     }
 
     /// Authenticate user and return user if valid
-    pub async fn authenticate_user(pool: &PgPool, email: &str, password: &str) -> AppResult<User> {
+    pub async fn authenticate_user(
+        pool: &PgPool,
+        email: &str,
+        password: &str,
+        argon2_params: &Params,
+    ) -> AppResult<User> {
+        let email = validation::normalize_email(email);
+
         // Find user by email
-        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
-            .bind(email)
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = $1")
+            .bind(&email)
             .fetch_optional(pool)
             .await?
             .ok_or_else(|| AppError::AuthError("Invalid email or password".to_string()))?;
@@ -63,10 +92,41 @@ impl UserService {
             return Err(AppError::AuthError("Invalid email or password".to_string()));
         }
 
+        // Only reveal that the account is disabled to someone who knows its password
+        if user.disabled_at.is_some() {
+            return Err(AppError::Forbidden);
+        }
+
+        // Transparently upgrade hashes created with weaker cost parameters
+        if password::needs_rehash(&user.password_hash, argon2_params)
+            && let Err(e) = Self::rehash_password(pool, user.id, password, argon2_params).await
+        {
+            log::warn!("Failed to rehash password for user {}: {}", user.id, e);
+        }
+
         log::info!("User authenticated: {}", email);
         Ok(user)
     }
 
+    /// Rehash a verified password with the current cost parameters
+    async fn rehash_password(
+        pool: &PgPool,
+        user_id: Uuid,
+        password: &str,
+        argon2_params: &Params,
+    ) -> AppResult<()> {
+        let password_hash = password::hash_password(password, argon2_params)?;
+
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+            .bind(password_hash)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+
+        log::info!("Password rehashed with updated parameters for user: {}", user_id);
+        Ok(())
+    }
+
     /// Get user by ID
     pub async fn get_user_by_id(pool: &PgPool, user_id: Uuid) -> AppResult<User> {
         let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
@@ -79,13 +139,587 @@ impl UserService {
     }
 
     /// Verify refresh token and return claims
-    pub fn verify_refresh_token(token: &str, secret: &str) -> AppResult<Claims> {
-        let claims = jwt::verify_token(token, secret)?;
+    pub async fn verify_refresh_token(
+        pool: &PgPool,
+        token: &str,
+        keys: &jwt::JwtKeys,
+    ) -> AppResult<Claims> {
+        let claims = jwt::verify_token(token, keys)?;
 
         if claims.token_type != "refresh" {
             return Err(AppError::AuthError("Invalid token type".to_string()));
         }
 
+        // Reject tokens that were revoked on logout
+        let revoked: Option<(String,)> =
+            sqlx::query_as("SELECT jti FROM revoked_tokens WHERE jti = $1")
+                .bind(&claims.jti)
+                .fetch_optional(pool)
+                .await?;
+
+        if revoked.is_some() {
+            return Err(AppError::AuthError("Refresh token has been revoked".to_string()));
+        }
+
+        // Every valid refresh token must belong to an active session
+        let session = sqlx::query_as::<_, Session>("SELECT * FROM sessions WHERE id = $1")
+            .bind(&claims.jti)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::AuthError("Session not found".to_string()))?;
+
+        if session.revoked_at.is_some() {
+            // A rotated token being presented again means it was likely stolen
+            Self::revoke_session_family(pool, &session.family_id).await?;
+            return Err(AppError::AuthError("Refresh token has been revoked".to_string()));
+        }
+
+        if session.expires_at <= Utc::now() {
+            return Err(AppError::AuthError("Session has expired".to_string()));
+        }
+
         Ok(claims)
     }
+
+    /// Replace a refresh token's session with a new one in the same family
+    pub async fn rotate_session(
+        pool: &PgPool,
+        claims: &Claims,
+        new_session_id: &str,
+        user_agent: Option<&str>,
+        ip: Option<&str>,
+        expires_in_days: i64,
+    ) -> AppResult<()> {
+        let mut tx = pool.begin().await?;
+
+        // Only one caller can rotate a given token; a concurrent loser is treated as a replay
+        let rotated: Option<(String, Uuid, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            UPDATE sessions SET revoked_at = NOW(), last_used_at = NOW()
+            WHERE id = $1 AND revoked_at IS NULL
+            RETURNING family_id, user_id, created_at
+            "#,
+        )
+        .bind(&claims.jti)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let (family_id, user_id, created_at) = match rotated {
+            Some(rotated) => rotated,
+            None => {
+                tx.rollback().await?;
+                if let Some((family_id,)) =
+                    sqlx::query_as::<_, (String,)>("SELECT family_id FROM sessions WHERE id = $1")
+                        .bind(&claims.jti)
+                        .fetch_optional(pool)
+                        .await?
+                {
+                    Self::revoke_session_family(pool, &family_id).await?;
+                }
+                return Err(AppError::AuthError("Refresh token has been revoked".to_string()));
+            }
+        };
+
+        let expires_at = Utc::now() + Duration::days(expires_in_days);
+
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (id, family_id, user_id, user_agent, ip, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(new_session_id)
+        .bind(&family_id)
+        .bind(user_id)
+        .bind(user_agent)
+        .bind(ip)
+        .bind(created_at)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Revoke every session descended from the same login
+    async fn revoke_session_family(pool: &PgPool, family_id: &str) -> AppResult<()> {
+        let result = sqlx::query(
+            "UPDATE sessions SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(family_id)
+        .execute(pool)
+        .await?;
+
+        log::warn!(
+            "Refresh token reuse detected, revoked {} session(s) in family {}",
+            result.rows_affected(),
+            family_id
+        );
+        Ok(())
+    }
+
+    /// Revoke a refresh token so it can no longer be used
+    pub async fn revoke_refresh_token(pool: &PgPool, claims: &Claims) -> AppResult<()> {
+        let user_id: Uuid = claims
+            .sub
+            .parse()
+            .map_err(|_| AppError::AuthError("Invalid user ID in token".to_string()))?;
+        let expires_at = DateTime::from_timestamp(claims.exp, 0).unwrap_or_else(Utc::now);
+
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_tokens (jti, user_id, expires_at, revoked_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+        )
+        .bind(&claims.jti)
+        .bind(user_id)
+        .bind(expires_at)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        sqlx::query("UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+            .bind(&claims.jti)
+            .execute(pool)
+            .await?;
+
+        log::info!("Refresh token revoked for user: {}", user_id);
+        Ok(())
+    }
+
+    /// Delete a user account along with its transcripts and sessions
+    /// Returns the deleted transcripts so callers can remove their files
+    pub async fn delete_user(pool: &PgPool, user_id: Uuid) -> AppResult<Vec<Transcript>> {
+        let mut tx = pool.begin().await?;
+
+        let transcripts = sqlx::query_as::<_, Transcript>("SELECT * FROM transcripts WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        // Transcripts, segments and sessions are removed by ON DELETE CASCADE
+        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        tx.commit().await?;
+
+        log::info!("User deleted: {}", user_id);
+        Ok(transcripts)
+    }
+
+    /// Record a new session for an issued refresh token
+    pub async fn create_session(
+        pool: &PgPool,
+        session_id: &str,
+        user_id: Uuid,
+        user_agent: Option<&str>,
+        ip: Option<&str>,
+        expires_in_days: i64,
+    ) -> AppResult<()> {
+        let expires_at = Utc::now() + Duration::days(expires_in_days);
+
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (id, family_id, user_id, user_agent, ip, expires_at)
+            VALUES ($1, $1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .bind(user_agent)
+        .bind(ip)
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// List a user's active sessions, most recently used first
+    pub async fn get_active_sessions(pool: &PgPool, user_id: Uuid) -> AppResult<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT * FROM sessions
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            ORDER BY last_used_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// Revoke one of a user's sessions
+    pub async fn revoke_session(pool: &PgPool, user_id: Uuid, session_id: &str) -> AppResult<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE sessions SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound("Session not found".to_string()));
+        }
+
+        log::info!("Session {} revoked for user: {}", session_id, user_id);
+        Ok(())
+    }
+
+    /// Create a single-use password reset token for a user
+    /// Returns `None` when no user has the email, so callers can respond generically
+    pub async fn create_password_reset(pool: &PgPool, email: &str) -> AppResult<Option<String>> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = $1")
+            .bind(validation::normalize_email(email))
+            .fetch_optional(pool)
+            .await?;
+
+        let user = match user {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+
+        let reset_token = token::generate_secure_token();
+        let expires_at = Utc::now() + Duration::minutes(30);
+
+        // Only the most recent reset token stays valid
+        sqlx::query("DELETE FROM password_resets WHERE user_id = $1")
+            .bind(user.id)
+            .execute(pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO password_resets (user_id, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(user.id)
+        .bind(token::hash_token(&reset_token))
+        .bind(expires_at)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        log::info!("Password reset requested for user: {}", user.id);
+        Ok(Some(reset_token))
+    }
+
+    /// Reset a user's password using a valid reset token, signing out every session
+    pub async fn reset_password(
+        pool: &PgPool,
+        reset_token: &str,
+        new_password: &str,
+        argon2_params: &Params,
+    ) -> AppResult<()> {
+        let reset: (Uuid,) = sqlx::query_as(
+            "SELECT user_id FROM password_resets WHERE token_hash = $1 AND expires_at > NOW()",
+        )
+        .bind(token::hash_token(reset_token))
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::ValidationError("Invalid or expired reset token".to_string()))?;
+
+        let user_id = reset.0;
+        let password_hash = password::hash_password(new_password, argon2_params)?;
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+            .bind(password_hash)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM password_resets WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        log::info!("Password reset completed for user: {}", user_id);
+        Ok(())
+    }
+
+    /// Create an email verification token for a newly registered user
+    pub async fn create_email_verification(pool: &PgPool, user_id: Uuid) -> AppResult<String> {
+        let verification_token = token::generate_secure_token();
+        let expires_at = Utc::now() + Duration::hours(24);
+
+        sqlx::query(
+            r#"
+            INSERT INTO email_verifications (user_id, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(user_id)
+        .bind(token::hash_token(&verification_token))
+        .bind(expires_at)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        Ok(verification_token)
+    }
+
+    /// Replace the verification token of an unverified account, e.g. after the first one expired
+    /// Returns `None` for unknown or already verified emails, so callers can answer the same way
+    pub async fn renew_email_verification(pool: &PgPool, email: &str) -> AppResult<Option<String>> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = $1")
+            .bind(validation::normalize_email(email))
+            .fetch_optional(pool)
+            .await?;
+
+        let user = match user {
+            Some(user) if !user.email_verified => user,
+            _ => return Ok(None),
+        };
+
+        // Only the most recent verification token stays valid
+        sqlx::query("DELETE FROM email_verifications WHERE user_id = $1")
+            .bind(user.id)
+            .execute(pool)
+            .await?;
+
+        Self::create_email_verification(pool, user.id).await.map(Some)
+    }
+
+    /// Mark a user's email as verified using a verification token
+    pub async fn verify_email(pool: &PgPool, verification_token: &str) -> AppResult<User> {
+        let verification: (Uuid,) = sqlx::query_as(
+            "SELECT user_id FROM email_verifications WHERE token_hash = $1 AND expires_at > NOW()",
+        )
+        .bind(token::hash_token(verification_token))
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::ValidationError("Invalid or expired verification token".to_string()))?;
+
+        let user_id = verification.0;
+        let mut tx = pool.begin().await?;
+
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET email_verified = TRUE WHERE id = $1 RETURNING *",
+        )
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM email_verifications WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        log::info!("Email verified for user: {}", user_id);
+        Ok(user)
+    }
+
+    /// Disable or re-enable a user account
+    /// Disabling also revokes every session, so the account's refresh tokens stop working
+    pub async fn set_disabled(pool: &PgPool, user_id: Uuid, disabled: bool) -> AppResult<User> {
+        let mut tx = pool.begin().await?;
+
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET disabled_at = CASE WHEN $2 THEN COALESCE(disabled_at, NOW()) ELSE NULL END
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(disabled)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        if disabled {
+            sqlx::query(
+                "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+            )
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        log::info!(
+            "User {} {}",
+            user_id,
+            if disabled { "disabled" } else { "enabled" }
+        );
+        Ok(user)
+    }
+
+    /// Check whether a user account still exists and is not disabled
+    pub async fn is_active(pool: &PgPool, user_id: Uuid) -> AppResult<bool> {
+        let active: Option<(bool,)> =
+            sqlx::query_as("SELECT disabled_at IS NULL FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(active.map(|v| v.0).unwrap_or(false))
+    }
+
+    /// Check whether a user has verified their email address
+    pub async fn is_email_verified(pool: &PgPool, user_id: Uuid) -> AppResult<bool> {
+        let verified: Option<(bool,)> =
+            sqlx::query_as("SELECT email_verified FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(pool)
+                .await?;
+
+        Ok(verified.map(|v| v.0).unwrap_or(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    /// Cheapest valid cost, to keep the tests fast
+    fn test_params() -> Params {
+        Params::new(8, 1, 1, None).unwrap()
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn login_upgrades_weaker_password_hash(pool: PgPool) {
+        let stronger = Params::new(16, 2, 1, None).unwrap();
+        let user =
+            UserService::register_user(&pool, "rehash@example.com", "Password 1", &test_params())
+                .await
+                .unwrap();
+        assert!(password::needs_rehash(&user.password_hash, &stronger));
+
+        UserService::authenticate_user(&pool, &user.email, "Password 1", &stronger)
+            .await
+            .unwrap();
+
+        let upgraded = UserService::get_user_by_id(&pool, user.id).await.unwrap();
+        assert_ne!(upgraded.password_hash, user.password_hash);
+        assert!(!password::needs_rehash(&upgraded.password_hash, &stronger));
+        assert!(password::verify_password("Password 1", &upgraded.password_hash).unwrap());
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn current_password_hash_is_left_alone(pool: PgPool) {
+        let user =
+            UserService::register_user(&pool, "current@example.com", "Password 1", &test_params())
+                .await
+                .unwrap();
+
+        UserService::authenticate_user(&pool, &user.email, "Password 1", &test_params())
+            .await
+            .unwrap();
+
+        let unchanged = UserService::get_user_by_id(&pool, user.id).await.unwrap();
+        assert_eq!(unchanged.password_hash, user.password_hash);
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn revoked_refresh_token_is_rejected(pool: PgPool) {
+        let keys = jwt::JwtKeys::from_config(&Config::for_tests()).unwrap();
+        let user =
+            UserService::register_user(&pool, "logout@example.com", "Password 1", &test_params())
+                .await
+                .unwrap();
+        UserService::create_session(&pool, "session-a", user.id, None, None, 7).await.unwrap();
+        let refresh_token =
+            jwt::generate_refresh_token(user.id, &user.email, &user.role, "session-a", &keys, 7)
+                .unwrap();
+        let claims = UserService::verify_refresh_token(&pool, &refresh_token, &keys).await.unwrap();
+
+        UserService::revoke_refresh_token(&pool, &claims).await.unwrap();
+
+        let result = UserService::verify_refresh_token(&pool, &refresh_token, &keys).await;
+        assert!(matches!(result, Err(AppError::AuthError(_))));
+        assert!(UserService::get_active_sessions(&pool, user.id).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn reset_password_revokes_every_session(pool: PgPool) {
+        let user =
+            UserService::register_user(&pool, "reset@example.com", "Old password 1", &test_params())
+                .await
+                .unwrap();
+        UserService::create_session(&pool, "session-a", user.id, None, None, 7).await.unwrap();
+        UserService::create_session(&pool, "session-b", user.id, None, None, 7).await.unwrap();
+        let reset_token = UserService::create_password_reset(&pool, &user.email)
+            .await
+            .unwrap()
+            .unwrap();
+
+        UserService::reset_password(&pool, &reset_token, "New password 1", &test_params())
+            .await
+            .unwrap();
+
+        assert!(UserService::get_active_sessions(&pool, user.id).await.unwrap().is_empty());
+        let user = UserService::get_user_by_id(&pool, user.id).await.unwrap();
+        assert!(password::verify_password("New password 1", &user.password_hash).unwrap());
+        // The token is single-use
+        let reused =
+            UserService::reset_password(&pool, &reset_token, "Other password 1", &test_params()).await;
+        assert!(matches!(reused, Err(AppError::ValidationError(_))));
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn renewed_verification_replaces_the_old_token(pool: PgPool) {
+        let user =
+            UserService::register_user(&pool, "renew@example.com", "Password 1", &test_params())
+                .await
+                .unwrap();
+        let first = UserService::create_email_verification(&pool, user.id).await.unwrap();
+
+        let renewed = UserService::renew_email_verification(&pool, "Renew@Example.com")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let stale = UserService::verify_email(&pool, &first).await;
+        assert!(matches!(stale, Err(AppError::ValidationError(_))));
+        let verified = UserService::verify_email(&pool, &renewed).await.unwrap();
+        assert!(verified.email_verified);
+    }
+
+    #[sqlx::test(migrations = "./src/migrations")]
+    async fn verification_is_not_renewed_for_verified_or_unknown_emails(pool: PgPool) {
+        let user =
+            UserService::register_user(&pool, "verified@example.com", "Password 1", &test_params())
+                .await
+                .unwrap();
+        let token = UserService::create_email_verification(&pool, user.id).await.unwrap();
+        UserService::verify_email(&pool, &token).await.unwrap();
+
+        let verified = UserService::renew_email_verification(&pool, &user.email).await.unwrap();
+        let unknown =
+            UserService::renew_email_verification(&pool, "nobody@example.com").await.unwrap();
+
+        assert!(verified.is_none());
+        assert!(unknown.is_none());
+    }
 }
@@ -0,0 +1,331 @@
+use crate::AppState;
+use crate::errors::{AppError, AppResult};
+use crate::models::{Transcript, Webhook};
+use crate::services::TranscriptionService;
+use crate::utils::token;
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Header carrying the hex HMAC-SHA256 of the request body
+pub const SIGNATURE_HEADER: &str = "X-Scribe-Signature";
+
+/// Maximum delivery attempts per notification
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled after each failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Number of characters of the transcription included in the payload
+const PREVIEW_CHARS: usize = 200;
+
+/// Timeout of a single delivery attempt
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Webhook service for job completion callbacks
+pub struct WebhookService;
+
+impl WebhookService {
+    /// HTTP client for deliveries; it never follows redirects and refuses to connect to hosts
+    /// that resolve to loopback, private or link-local addresses
+    pub fn build_client() -> AppResult<reqwest::Client> {
+        reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .map_err(|e| AppError::InternalError(format!("Failed to build HTTP client: {}", e)))
+    }
+
+    /// Check that a webhook URL uses http(s) and that its host only resolves to public addresses
+    pub async fn check_url(url: &str) -> AppResult<()> {
+        let invalid = |message: &str| AppError::ValidationError(message.to_string());
+
+        let parsed = reqwest::Url::parse(url).map_err(|_| invalid("Invalid webhook URL"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(invalid("Webhook URL must use http or https"));
+        }
+        let host = parsed.host_str().ok_or_else(|| invalid("Webhook URL must have a host"))?;
+
+        // IP literals are connected to directly, without going through the resolver
+        let ips = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            Ok(ip) => vec![ip],
+            Err(_) => tokio::net::lookup_host((host, parsed.port_or_known_default().unwrap_or(80)))
+                .await
+                .map_err(|_| invalid("Webhook host could not be resolved"))?
+                .map(|addr| addr.ip())
+                .collect(),
+        };
+
+        if ips.is_empty() || !ips.into_iter().all(is_public_ip) {
+            return Err(invalid("Webhook URL must point to a public address"));
+        }
+        Ok(())
+    }
+
+    /// Get the webhook registered by a user, if any
+    pub async fn get_webhook(pool: &PgPool, user_id: Uuid) -> AppResult<Option<Webhook>> {
+        let webhook = sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(webhook)
+    }
+
+    /// Register or replace a user's webhook with a freshly generated signing secret
+    pub async fn upsert_webhook(pool: &PgPool, user_id: Uuid, url: &str) -> AppResult<Webhook> {
+        let webhook = sqlx::query_as::<_, Webhook>(
+            r#"
+            INSERT INTO webhooks (user_id, url, secret)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id)
+            DO UPDATE SET url = EXCLUDED.url, secret = EXCLUDED.secret, updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(url)
+        .bind(token::generate_secure_token())
+        .fetch_one(pool)
+        .await?;
+
+        log::info!("Webhook registered for user: {}", user_id);
+        Ok(webhook)
+    }
+
+    /// Remove a user's webhook; returns whether one existed
+    pub async fn delete_webhook(pool: &PgPool, user_id: Uuid) -> AppResult<bool> {
+        let result = sqlx::query("DELETE FROM webhooks WHERE user_id = $1")
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Notify the owner's webhook that a job finished, delivering in the background
+    pub fn notify_job_finished(app_state: &AppState, transcript_id: Uuid) {
+        let app_state = app_state.clone();
+
+        tokio::spawn(
+            async move {
+                if let Err(e) = Self::deliver_job_finished(&app_state, transcript_id).await {
+                    log::error!("Webhook notification for job {} failed: {}", transcript_id, e);
+                }
+            }
+            .instrument(tracing::Span::current()),
+        );
+    }
+
+    async fn deliver_job_finished(app_state: &AppState, transcript_id: Uuid) -> AppResult<()> {
+        let transcript =
+            TranscriptionService::get_transcript_for_job(&app_state.db, transcript_id).await?;
+
+        let webhook = match Self::get_webhook(&app_state.db, transcript.user_id).await? {
+            Some(webhook) => webhook,
+            None => return Ok(()),
+        };
+
+        // The host may have been re-pointed since registration
+        Self::check_url(&webhook.url).await?;
+
+        let body = Self::payload(&transcript).to_string().into_bytes();
+        let signature = sign(&webhook.secret, &body);
+
+        Self::deliver(&app_state.http_client, &webhook.url, body, &signature, transcript_id).await;
+        Ok(())
+    }
+
+    fn payload(transcript: &Transcript) -> serde_json::Value {
+        serde_json::json!({
+            "event": format!("transcription.{}", transcript.status),
+            "transcript_id": transcript.id,
+            "status": transcript.status,
+            "transcription": transcript.transcription.chars().take(PREVIEW_CHARS).collect::<String>(),
+            "error_message": transcript.error_message,
+            "timestamp": chrono::Utc::now(),
+        })
+    }
+
+    /// POST the payload, retrying network errors, 429s and 5xx responses with backoff
+    async fn deliver(
+        client: &reqwest::Client,
+        url: &str,
+        body: Vec<u8>,
+        signature: &str,
+        transcript_id: Uuid,
+    ) {
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let result = client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+                .body(body.clone())
+                .send()
+                .await;
+
+            let retryable = match result {
+                Ok(response) if response.status().is_success() => {
+                    log::info!(
+                        "Webhook delivered for job {} on attempt {} ({})",
+                        transcript_id,
+                        attempt,
+                        response.status()
+                    );
+                    return;
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    log::warn!(
+                        "Webhook for job {} returned {} on attempt {}",
+                        transcript_id,
+                        status,
+                        attempt
+                    );
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Webhook for job {} failed on attempt {}: {}",
+                        transcript_id,
+                        attempt,
+                        e
+                    );
+                    true
+                }
+            };
+
+            if !retryable {
+                break;
+            }
+
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        log::error!("Giving up on webhook delivery for job {}", transcript_id);
+    }
+}
+
+/// DNS resolver that fails for names resolving to any non-public address, so deliveries
+/// connect to exactly the addresses that were checked
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(format!("{} resolves to non-public address {}", host, addr.ip()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether an address is routable on the public internet
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ipv4(mapped);
+            }
+            let segments = ip.segments();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || segments[..6] == [0; 6] // IPv4-compatible
+                || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] // NAT64
+                || (segments[0] & 0xfe00) == 0xfc00 // unique local
+                || (segments[0] & 0xffc0) == 0xfe80 // link-local
+                || (segments[0] & 0xffc0) == 0xfec0 // site-local
+                || (segments[0] == 0x2001 && segments[1] == 0xdb8)) // documentation
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(a == 0
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || (a == 100 && (b & 0xc0) == 64) // shared address space
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+        || (a == 198 && (b & 0xfe) == 18) // benchmarking
+        || a >= 240) // reserved
+}
+
+/// Hex-encoded HMAC-SHA256 of `body` keyed by the webhook secret
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_loopback_private_and_link_local_urls() {
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://localhost/hook",
+            "http://[::1]/hook",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.5/hook",
+            "https://192.168.1.20/hook",
+            "http://[::ffff:172.16.0.1]/hook",
+            "http://[fe80::1]/hook",
+            "http://0.0.0.0/hook",
+        ] {
+            assert!(
+                matches!(WebhookService::check_url(url).await, Err(AppError::ValidationError(_))),
+                "{} should be rejected",
+                url
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_public_addresses_over_http() {
+        assert!(WebhookService::check_url("https://93.184.215.14/hook").await.is_ok());
+        assert!(WebhookService::check_url("http://[2606:4700::1111]/hook").await.is_ok());
+        assert!(WebhookService::check_url("ftp://93.184.215.14/hook").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn resolver_refuses_names_of_internal_hosts() {
+        let name = "localhost".parse::<Name>().unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+    }
+
+    #[test]
+    fn classifies_special_purpose_ranges() {
+        for ip in ["100.64.0.1", "198.18.0.1", "240.0.0.1", "fd00::1", "64:ff9b::a00:1", "2001:db8::1"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} should not be public", ip);
+        }
+        for ip in ["8.8.8.8", "1.1.1.1", "2a00:1450::1"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} should be public", ip);
+        }
+    }
+}
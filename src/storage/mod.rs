@@ -0,0 +1,174 @@
+// storage/mod.rs - Object storage for original audio uploads
+use crate::config::Config;
+use crate::errors::{AppError, AppResult};
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::ByteStream;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncRead;
+
+/// Readable object contents
+pub type StorageReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Backend storing objects under string keys
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Store the contents of a local file under `key`, replacing any existing object
+    async fn put(&self, key: &str, source_path: &str) -> AppResult<()>;
+
+    /// Open the object stored under `key`
+    async fn get(&self, key: &str) -> AppResult<StorageReader>;
+
+    /// Delete the object stored under `key`; missing objects are not an error
+    async fn delete(&self, key: &str) -> AppResult<()>;
+
+    /// Copy the object stored under `key` to a local file
+    async fn get_to_file(&self, key: &str, destination_path: &str) -> AppResult<()> {
+        let mut reader = self.get(key).await?;
+        let mut file = tokio::fs::File::create(destination_path).await?;
+        tokio::io::copy(&mut reader, &mut file).await?;
+        Ok(())
+    }
+}
+
+/// Build the storage backend selected by `STORAGE_BACKEND`
+pub async fn build_storage(config: &Config) -> AppResult<Arc<dyn Storage>> {
+    match config.storage_backend.as_str() {
+        "s3" => {
+            let bucket = config.s3_bucket.clone().ok_or_else(|| {
+                AppError::ConfigError("S3_BUCKET is required for the s3 storage backend".to_string())
+            })?;
+            Ok(Arc::new(S3Storage::new(bucket).await))
+        }
+        _ => Ok(Arc::new(LocalStorage::new(&config.storage_local_dir))),
+    }
+}
+
+/// Stores objects as files under a base directory
+pub struct LocalStorage {
+    base_dir: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(base_dir: &str) -> Self {
+        Self {
+            base_dir: PathBuf::from(base_dir),
+        }
+    }
+
+    /// Resolve a key to a path, refusing keys that would escape the base directory
+    fn path_for(&self, key: &str) -> AppResult<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(AppError::StorageError(format!("Invalid storage key: {}", key)));
+        }
+
+        Ok(self.base_dir.join(relative))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, source_path: &str) -> AppResult<()> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::copy(source_path, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> AppResult<StorageReader> {
+        let path = self.path_for(key)?;
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => Ok(Box::new(file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(AppError::NotFound("Stored audio not found".to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> AppResult<()> {
+        let path = self.path_for(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Stores objects in an S3 (or S3-compatible) bucket
+/// Credentials, region and endpoint come from the standard AWS environment variables
+pub struct S3Storage {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3Storage {
+    pub async fn new(bucket: String) -> Self {
+        let aws_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        Self {
+            client: aws_sdk_s3::Client::new(&aws_config),
+            bucket,
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, key: &str, source_path: &str) -> AppResult<()> {
+        let body = ByteStream::from_path(source_path)
+            .await
+            .map_err(|e| AppError::StorageError(format!("Failed to read {}: {}", source_path, e)))?;
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| AppError::StorageError(format!("Failed to upload {}: {}", key, e)))?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> AppResult<StorageReader> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| {
+                let e = e.into_service_error();
+                if e.is_no_such_key() {
+                    AppError::NotFound("Stored audio not found".to_string())
+                } else {
+                    AppError::StorageError(format!("Failed to download {}: {}", key, e))
+                }
+            })?;
+
+        Ok(Box::new(output.body.into_async_read()))
+    }
+
+    async fn delete(&self, key: &str) -> AppResult<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| AppError::StorageError(format!("Failed to delete {}: {}", key, e)))?;
+
+        Ok(())
+    }
+}
@@ -1,22 +1,35 @@
-use crate::errors::{AppError, AppResult};
+use crate::errors::{AppError, AppResult, FieldError};
 use crate::models::Claims;
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, Params, Version,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 use chrono::{Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode};
 use uuid::Uuid;
 
 /// JWT utility functions
 pub mod jwt {
     use super::*;
+    use crate::config::Config;
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use jsonwebtoken::Algorithm as JwtAlgorithm;
+    use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+    use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+    use rsa::traits::PublicKeyParts;
+    use rsa::{RsaPrivateKey, RsaPublicKey};
+    use sha2::{Digest, Sha256};
+
+    /// Largest clock skew tolerance allowed, since tokens stay usable this long past expiry
+    pub const MAX_JWT_LEEWAY_SECONDS: u64 = 300;
 
     /// Generate an access token for a user
     pub fn generate_access_token(
         user_id: Uuid,
         email: &str,
-        secret: &str,
+        role: &str,
+        keys: &JwtKeys,
         expires_in_minutes: i64,
     ) -> AppResult<String> {
         let now = Utc::now();
@@ -28,21 +41,22 @@ pub mod jwt {
             iat: now.timestamp(),
             exp: exp.timestamp(),
             token_type: "access".to_string(),
+            role: role.to_string(),
+            jti: Uuid::new_v4().to_string(),
+            iss: keys.issuer.clone(),
+            aud: keys.audience.clone(),
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(secret.as_ref()),
-        )
-        .map_err(AppError::JwtError)
+        keys.sign(&claims)
     }
 
-    /// Generate a refresh token for a user
+    /// Generate a refresh token for a user, identified by the session ID `jti`
     pub fn generate_refresh_token(
         user_id: Uuid,
         email: &str,
-        secret: &str,
+        role: &str,
+        jti: &str,
+        keys: &JwtKeys,
         expires_in_days: i64,
     ) -> AppResult<String> {
         let now = Utc::now();
@@ -54,38 +68,193 @@ pub mod jwt {
             iat: now.timestamp(),
             exp: exp.timestamp(),
             token_type: "refresh".to_string(),
+            role: role.to_string(),
+            jti: jti.to_string(),
+            iss: keys.issuer.clone(),
+            aud: keys.audience.clone(),
         };
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(secret.as_ref()),
-        )
-        .map_err(AppError::JwtError)
+        keys.sign(&claims)
     }
 
-    /// Verify and decode a JWT token
-    pub fn verify_token(token: &str, secret: &str) -> AppResult<Claims> {
-        let validation = Validation::default();
+    /// Verify and decode a JWT token, picking the verification key by the header's `kid`
+    /// `exp` and `iat` are both checked, allowing the configured leeway for clock skew.
+    /// `iss` and `aud` must match `JWT_ISSUER` and `JWT_AUDIENCE` when those are set.
+    pub fn verify_token(token: &str, keys: &JwtKeys) -> AppResult<Claims> {
+        let header = decode_header(token).map_err(AppError::JwtError)?;
+        let mut validation = Validation::new(keys.algorithm);
+        validation.validate_exp = true;
+        validation.leeway = keys.leeway_seconds;
+
+        let mut required_claims = vec!["exp"];
+        if let Some(issuer) = &keys.issuer {
+            validation.set_issuer(&[issuer]);
+            required_claims.push("iss");
+        }
+        match &keys.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                required_claims.push("aud");
+            }
+            // Otherwise a token carrying `aud` would be rejected for want of an expected value
+            None => validation.validate_aud = false,
+        }
+        validation.set_required_spec_claims(&required_claims);
+
+        let key = keys.decoding_key(header.kid.as_deref())?;
+        let claims = decode::<Claims>(token, key, &validation)
+            .map_err(AppError::JwtError)?
+            .claims;
+
+        // jsonwebtoken doesn't look at `iat` (deserializing `Claims` requires it to be present),
+        // so tokens issued in the future are rejected here
+        if claims.iat > Utc::now().timestamp() + keys.leeway_seconds as i64 {
+            return Err(AppError::JwtError(
+                jsonwebtoken::errors::ErrorKind::ImmatureSignature.into(),
+            ));
+        }
 
-        decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(secret.as_ref()),
-            &validation,
-        )
-        .map(|token_data| token_data.claims)
-        .map_err(AppError::JwtError)
+        Ok(claims)
+    }
+
+    /// Signing and verification keys for the configured JWT algorithm
+    pub struct JwtKeys {
+        algorithm: JwtAlgorithm,
+        kid: Option<String>,
+        encoding_key: EncodingKey,
+        /// Verification keys by key ID; the first entry is the current signing key
+        decoding_keys: Vec<(Option<String>, DecodingKey)>,
+        /// Public keys published at `/.well-known/jwks.json`
+        jwks: serde_json::Value,
+        /// Clock skew tolerated when verifying `exp` and `iat`
+        leeway_seconds: u64,
+        /// `iss` claim issued and required, when configured
+        issuer: Option<String>,
+        /// `aud` claim issued and required, when configured
+        audience: Option<String>,
+    }
+
+    impl JwtKeys {
+        /// Load keys from configuration. HS256 uses `JWT_SECRET`; RS256 signs with the
+        /// private key at `JWT_PRIVATE_KEY_PATH` and also accepts `JWT_PUBLIC_KEY_PATHS`
+        /// so tokens signed by a rotated-out key stay valid until they expire.
+        pub fn from_config(config: &Config) -> AppResult<Self> {
+            match config.jwt_algorithm.as_str() {
+                "RS256" => Self::rs256(config),
+                _ => Ok(Self {
+                    algorithm: JwtAlgorithm::HS256,
+                    kid: None,
+                    encoding_key: EncodingKey::from_secret(config.jwt_secret.as_ref()),
+                    decoding_keys: vec![(
+                        None,
+                        DecodingKey::from_secret(config.jwt_secret.as_ref()),
+                    )],
+                    // A shared secret is never published
+                    jwks: serde_json::json!({ "keys": [] }),
+                    leeway_seconds: config.jwt_leeway_seconds,
+                    issuer: config.jwt_issuer.clone(),
+                    audience: config.jwt_audience.clone(),
+                }),
+            }
+        }
+
+        fn rs256(config: &Config) -> AppResult<Self> {
+            let private_key_path = config.jwt_private_key_path.as_deref().ok_or_else(|| {
+                AppError::ConfigError("JWT_PRIVATE_KEY_PATH must be set for RS256".to_string())
+            })?;
+            let private_pem = std::fs::read_to_string(private_key_path).map_err(|e| {
+                AppError::ConfigError(format!("Failed to read {}: {}", private_key_path, e))
+            })?;
+
+            let private_key = RsaPrivateKey::from_pkcs8_pem(&private_pem)
+                .or_else(|_| RsaPrivateKey::from_pkcs1_pem(&private_pem))
+                .map_err(|e| {
+                    AppError::ConfigError(format!("Invalid RSA private key {}: {}", private_key_path, e))
+                })?;
+            let encoding_key = EncodingKey::from_rsa_pem(private_pem.as_bytes()).map_err(|e| {
+                AppError::ConfigError(format!("Invalid RSA private key {}: {}", private_key_path, e))
+            })?;
+
+            let mut public_keys = vec![private_key.to_public_key()];
+            for path in &config.jwt_public_key_paths {
+                let pem = std::fs::read_to_string(path)
+                    .map_err(|e| AppError::ConfigError(format!("Failed to read {}: {}", path, e)))?;
+                let public_key = RsaPublicKey::from_public_key_pem(&pem)
+                    .or_else(|_| RsaPublicKey::from_pkcs1_pem(&pem))
+                    .map_err(|e| AppError::ConfigError(format!("Invalid RSA public key {}: {}", path, e)))?;
+                public_keys.push(public_key);
+            }
+
+            let mut decoding_keys = Vec::with_capacity(public_keys.len());
+            let mut jwks = Vec::with_capacity(public_keys.len());
+            for public_key in &public_keys {
+                let n = URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be());
+                let e = URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be());
+                let kid = Self::thumbprint(&n, &e);
+
+                let decoding_key = DecodingKey::from_rsa_components(&n, &e).map_err(|err| {
+                    AppError::ConfigError(format!("Invalid RSA public key: {}", err))
+                })?;
+                decoding_keys.push((Some(kid.clone()), decoding_key));
+                jwks.push(serde_json::json!({
+                    "kty": "RSA",
+                    "use": "sig",
+                    "alg": "RS256",
+                    "kid": kid,
+                    "n": n,
+                    "e": e,
+                }));
+            }
+
+            Ok(Self {
+                algorithm: JwtAlgorithm::RS256,
+                kid: decoding_keys[0].0.clone(),
+                encoding_key,
+                decoding_keys,
+                jwks: serde_json::json!({ "keys": jwks }),
+                leeway_seconds: config.jwt_leeway_seconds,
+                issuer: config.jwt_issuer.clone(),
+                audience: config.jwt_audience.clone(),
+            })
+        }
+
+        /// RFC 7638 JWK thumbprint, used as the key ID
+        fn thumbprint(n: &str, e: &str) -> String {
+            let canonical = format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n);
+            URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+        }
+
+        /// Sign claims with the current key, tagging the header with its key ID
+        fn sign(&self, claims: &Claims) -> AppResult<String> {
+            let mut header = Header::new(self.algorithm);
+            header.kid = self.kid.clone();
+            encode(&header, claims, &self.encoding_key).map_err(AppError::JwtError)
+        }
+
+        /// Verification key for a token; tokens without a `kid` use the current key
+        fn decoding_key(&self, kid: Option<&str>) -> AppResult<&DecodingKey> {
+            match kid {
+                None => Ok(&self.decoding_keys[0].1),
+                Some(kid) => self
+                    .decoding_keys
+                    .iter()
+                    .find(|(key_id, _)| key_id.as_deref() == Some(kid))
+                    .map(|(_, key)| key)
+                    .ok_or_else(|| AppError::AuthError("Unknown token signing key".to_string())),
+            }
+        }
+
+        /// JSON Web Key Set of the public verification keys
+        pub fn jwks(&self) -> &serde_json::Value {
+            &self.jwks
+        }
     }
 
     /// Extract token from Authorization header
     pub fn extract_token_from_header(auth_header: &str) -> AppResult<&str> {
-        if auth_header.starts_with("Bearer ") {
-            Ok(&auth_header[7..])
-        } else {
-            Err(AppError::AuthError(
-                "Invalid authorization header format".to_string(),
-            ))
-        }
+        auth_header
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| AppError::AuthError("Invalid authorization header format".to_string()))
     }
 }
 
@@ -93,14 +262,73 @@ pub mod jwt {
 pub mod password {
     use super::*;
 
-    /// Hash a password using Argon2
-    pub fn hash_password(password: &str) -> AppResult<String> {
+    /// Rules a new password must meet, on top of the 8-character minimum in the request models
+    #[derive(Debug, Clone, Copy)]
+    pub struct PasswordPolicy {
+        pub min_length: usize,
+        pub require_mixed_case: bool,
+        pub require_digit: bool,
+        pub require_symbol: bool,
+        pub reject_common: bool,
+    }
+
+    /// Frequently used passwords rejected when `reject_common` is set, ignoring case
+    const COMMON_PASSWORDS: &[&str] = &[
+        "password", "password1", "password12", "password123", "password1234", "passw0rd",
+        "p@ssw0rd", "p@ssword", "12345678", "123456789", "1234567890", "0123456789",
+        "87654321", "11111111", "00000000", "12341234", "qwertyui", "qwerty123", "qwerty12345",
+        "1q2w3e4r", "1qaz2wsx", "qazwsxedc", "asdfghjk", "zxcvbnm1", "abcd1234", "abc12345",
+        "iloveyou", "iloveyou1", "sunshine", "sunshine1", "princess", "football", "baseball",
+        "superman", "starwars", "trustno1", "whatever", "letmein1", "welcome1", "welcome123",
+        "changeme", "changeme1", "admin123", "administrator", "computer", "internet",
+        "mustang1", "michael1", "jennifer", "corvette", "liverpool", "master123", "dragon123",
+        "monkey123", "shadow123", "secret123", "test1234", "testtest", "default1",
+    ];
+
+    /// Check a new password against the policy
+    /// Every failed rule is reported as a separate message under `field`.
+    pub fn validate_strength(
+        password: &str,
+        policy: &PasswordPolicy,
+        field: &str,
+    ) -> AppResult<()> {
+        let mut messages = Vec::new();
+
+        if password.chars().count() < policy.min_length {
+            messages.push(format!("Password must be at least {} characters", policy.min_length));
+        }
+        let mixed_case =
+            password.chars().any(char::is_uppercase) && password.chars().any(char::is_lowercase);
+        if policy.require_mixed_case && !mixed_case {
+            messages.push("Password must contain both upper and lower case letters".to_string());
+        }
+        if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            messages.push("Password must contain a digit".to_string());
+        }
+        let has_symbol = password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace());
+        if policy.require_symbol && !has_symbol {
+            messages.push("Password must contain a symbol".to_string());
+        }
+        if policy.reject_common && COMMON_PASSWORDS.contains(&password.to_lowercase().as_str()) {
+            messages.push("Password is too common".to_string());
+        }
+
+        if messages.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::ValidationErrors(vec![FieldError {
+            field: field.to_string(),
+            messages,
+        }]))
+    }
+
+    /// Hash a password using Argon2id with the given cost parameters
+    pub fn hash_password(password: &str, params: &Params) -> AppResult<String> {
         let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone());
 
         let password_hash = argon2
-            .hash_password(password.as_bytes(), &salt)
-            .unwrap()
+            .hash_password(password.as_bytes(), &salt)?
             .to_string();
 
         Ok(password_hash)
@@ -109,81 +337,726 @@ pub mod password {
     /// Verify a password against its hash
     pub fn verify_password(password: &str, hash: &str) -> AppResult<bool> {
         let parsed_hash = PasswordHash::new(hash)
-            .map_err(|_| AppError::AuthError("Invalid password hash".to_string()))
-            .unwrap();
+            .map_err(|_| AppError::AuthError("Invalid password hash".to_string()))?;
+        // Verification uses the algorithm and cost parameters encoded in the stored hash
         let argon2 = Argon2::default();
 
+        // Only a mismatch means "wrong password"; bad parameters in the hash are errors
         match argon2.verify_password(password.as_bytes(), &parsed_hash) {
             Ok(()) => Ok(true),
-            Err(_) => Ok(false),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Check whether a stored hash uses a different algorithm or weaker cost than `params`
+    pub fn needs_rehash(hash: &str, params: &Params) -> bool {
+        let parsed_hash = match PasswordHash::new(hash) {
+            Ok(parsed_hash) => parsed_hash,
+            Err(_) => return true,
+        };
+
+        if parsed_hash.algorithm != Algorithm::Argon2id.ident() {
+            return true;
+        }
+
+        match Params::try_from(&parsed_hash) {
+            Ok(current) => {
+                current.m_cost() < params.m_cost()
+                    || current.t_cost() < params.t_cost()
+                    || current.p_cost() < params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Cheapest valid cost, to keep the tests fast
+        fn test_params() -> Params {
+            Params::new(8, 1, 1, None).unwrap()
+        }
+
+        #[test]
+        fn weaker_hash_needs_rehash() {
+            let hash = hash_password("correct horse", &test_params()).unwrap();
+
+            assert!(needs_rehash(&hash, &Params::new(16, 1, 1, None).unwrap()));
+            assert!(needs_rehash(&hash, &Params::new(8, 2, 1, None).unwrap()));
+        }
+
+        #[test]
+        fn hash_with_current_params_needs_no_rehash() {
+            let hash = hash_password("correct horse", &test_params()).unwrap();
+
+            assert!(!needs_rehash(&hash, &test_params()));
+        }
+
+        #[test]
+        fn unparseable_hash_needs_rehash() {
+            assert!(needs_rehash("not a password hash", &test_params()));
+        }
+
+        #[test]
+        fn verifies_a_hashed_password() {
+            let hash = hash_password("correct horse", &test_params()).unwrap();
+
+            assert!(verify_password("correct horse", &hash).unwrap());
+            assert!(!verify_password("wrong horse", &hash).unwrap());
+        }
+
+        #[test]
+        fn garbage_hash_is_an_error() {
+            let result = verify_password("correct horse", "not a password hash");
+
+            assert!(matches!(result, Err(AppError::AuthError(_))));
+        }
+
+        #[test]
+        fn invalid_params_in_hash_are_an_error() {
+            // Parses as a PHC string, but 1 KiB of memory is below Argon2's minimum
+            let hash = "$argon2id$v=19$m=1,t=1,p=1$c29tZXNhbHQ$RdescudvJCsgt3ub+b+dWRWJTmaaJObG";
+
+            let result = verify_password("correct horse", hash);
+
+            assert!(matches!(result, Err(AppError::ArgonError(_))));
         }
     }
 }
 
+/// Secure random token utilities
+pub mod token {
+    use argon2::password_hash::rand_core::RngCore;
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+
+    /// Generate a cryptographically random, URL-safe token
+    pub fn generate_secure_token() -> String {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        hex::encode(bytes)
+    }
+
+    /// Hash a token for storage so a database leak doesn't expose usable tokens
+    pub fn hash_token(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+}
+
 /// File handling utilities
 pub mod file {
     use super::*;
     use std::path::Path;
 
+    /// Audio file extensions accepted for upload when `SUPPORTED_FORMATS` isn't set
+    pub const DEFAULT_SUPPORTED_FORMATS: &[&str] = &[
+        "wav", "mp3", "m4a", "flac", "ogg", "aac", "wma", "aiff", "au", "webm", "opus", "3gp",
+        "amr",
+    ];
+
     /// Check if a file extension is supported for audio transcription
-    // pub fn is_supported_audio_format(filename: &str) -> bool {
-    //     let supported_formats = ["wav", "mp3", "m4a", "flac", "ogg"];
+    pub fn is_supported_audio_format(filename: &str, supported_formats: &[String]) -> bool {
+        Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| {
+                let ext = ext.to_lowercase();
+                supported_formats.contains(&ext)
+            })
+            .unwrap_or(false)
+    }
 
-    //     if let Some(ext) = Path::new(filename).extension() {
-    //         if let Some(ext_str) = ext.to_str() {
-    //             return supported_formats.contains(&ext_str.to_lowercase().as_str());
-    //         }
-    //     }
+    /// Argument naming a local file for FFmpeg or FFprobe
+    /// FFmpeg has no `--` to end its options, so files are passed with the `file:` protocol:
+    /// the path can then never be read as an option (a leading `-`) or as another protocol
+    /// (`http:`, `concat:`), whatever it contains. Paths handed to FFmpeg are server-generated
+    /// anyway; client filenames are only ever used for display.
+    pub fn ffmpeg_file_arg(path: &str) -> String {
+        format!("file:{}", path)
+    }
 
-    //     false
-    // }
+    /// Argument naming a local file for other helper programs
+    /// A relative path gets a `./` prefix, so it can't start with `-` and be read as a flag.
+    pub fn command_file_arg(path: &str) -> String {
+        if Path::new(path).is_absolute() || path.starts_with("./") {
+            path.to_string()
+        } else {
+            format!("./{}", path)
+        }
+    }
+
+    /// Temporary file that is deleted when the guard is dropped
+    pub struct TempFile {
+        path: String,
+    }
+
+    impl TempFile {
+        /// Take ownership of a temporary file path
+        pub fn new(path: impl Into<String>) -> Self {
+            Self { path: path.into() }
+        }
+
+        /// Path of the temporary file
+        pub fn path(&self) -> &str {
+            &self.path
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            match std::fs::remove_file(&self.path) {
+                Ok(()) => log::debug!("Removed temporary file: {}", self.path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => log::warn!("Failed to remove temporary file {}: {}", self.path, e),
+            }
+        }
+    }
+
+    /// Longest accepted filename, in characters
+    pub const MAX_FILENAME_LEN: usize = 255;
+
+    /// Reduce a client-supplied filename to a safe display name. Directory components are
+    /// dropped, characters other than letters, digits, spaces and `-_.()` (including control
+    /// characters and null bytes) become `_`, leading dots are removed so `..` can't survive,
+    /// and the stem is shortened to fit `MAX_FILENAME_LEN`, keeping the extension.
+    pub fn sanitize_filename(filename: &str) -> AppResult<String> {
+        // Both separators, since Windows clients may send backslashes
+        let base = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+        let cleaned: String = base
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | '(' | ')') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let cleaned = cleaned.trim().trim_start_matches('.').trim_start();
+
+        if !cleaned.chars().any(char::is_alphanumeric) {
+            return Err(AppError::ValidationError(
+                "Filename must contain at least one letter or digit".to_string(),
+            ));
+        }
+
+        if cleaned.chars().count() <= MAX_FILENAME_LEN {
+            return Ok(cleaned.to_string());
+        }
+
+        let (stem, extension) = match cleaned.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() && extension.len() <= 10 => {
+                (stem, format!(".{}", extension))
+            }
+            _ => (cleaned, String::new()),
+        };
+        let stem: String = stem
+            .chars()
+            .take(MAX_FILENAME_LEN - extension.chars().count())
+            .collect();
+        Ok(format!("{}{}", stem.trim_end(), extension))
+    }
 
     /// Generate a unique filename for uploaded files
+    /// Only a short alphanumeric extension is carried over; nothing else of the original is used
     pub fn generate_unique_filename(original_filename: &str) -> String {
         let uuid = Uuid::new_v4();
         let timestamp = Utc::now().timestamp();
 
-        if let Some(ext) = Path::new(original_filename).extension() {
-            format!("{}_{}.{}", timestamp, uuid, ext.to_string_lossy())
-        } else {
-            format!("{}_{}", timestamp, uuid)
+        let extension = Path::new(original_filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .filter(|ext| ext.len() <= 10 && ext.bytes().all(|byte| byte.is_ascii_alphanumeric()));
+
+        match extension {
+            Some(ext) => format!("{}_{}.{}", timestamp, uuid, ext),
+            None => format!("{}_{}", timestamp, uuid),
+        }
+    }
+
+    /// Number of leading bytes needed to recognise every supported container
+    pub const SNIFF_LEN: usize = 12;
+
+    /// Audio container detected from a file's leading bytes
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AudioFormat {
+        Wav,
+        Mp3,
+        Aac,
+        Flac,
+        Ogg,
+        Mp4,
+        Aiff,
+        Au,
+        WebM,
+        Wma,
+        Amr,
+    }
+
+    impl AudioFormat {
+        /// MIME type reported for uploads of this format
+        pub fn content_type(&self) -> &'static str {
+            match self {
+                AudioFormat::Wav => "audio/wav",
+                AudioFormat::Mp3 => "audio/mpeg",
+                AudioFormat::Aac => "audio/aac",
+                AudioFormat::Flac => "audio/flac",
+                AudioFormat::Ogg => "audio/ogg",
+                AudioFormat::Mp4 => "audio/mp4",
+                AudioFormat::Aiff => "audio/aiff",
+                AudioFormat::Au => "audio/basic",
+                AudioFormat::WebM => "audio/webm",
+                AudioFormat::Wma => "audio/x-ms-wma",
+                AudioFormat::Amr => "audio/amr",
+            }
         }
     }
 
+    /// Detect the audio container from magic bytes, ignoring the filename.
+    /// Expects at least `SNIFF_LEN` bytes unless the file is shorter.
+    pub fn sniff_audio_format(bytes: &[u8]) -> Option<AudioFormat> {
+        const ASF_GUID: [u8; 8] = [0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11];
+
+        if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE" {
+            return Some(AudioFormat::Wav);
+        }
+        if bytes.len() >= 12
+            && &bytes[0..4] == b"FORM"
+            && (&bytes[8..12] == b"AIFF" || &bytes[8..12] == b"AIFC")
+        {
+            return Some(AudioFormat::Aiff);
+        }
+        if bytes.len() >= 8 && &bytes[4..8] == b"ftyp" {
+            // MP4/M4A/3GP family (ISO base media)
+            return Some(AudioFormat::Mp4);
+        }
+        if bytes.starts_with(b"ID3") {
+            return Some(AudioFormat::Mp3);
+        }
+        if bytes.starts_with(b"fLaC") {
+            return Some(AudioFormat::Flac);
+        }
+        if bytes.starts_with(b"OggS") {
+            return Some(AudioFormat::Ogg);
+        }
+        if bytes.starts_with(b".snd") {
+            return Some(AudioFormat::Au);
+        }
+        if bytes.starts_with(b"#!AMR") {
+            return Some(AudioFormat::Amr);
+        }
+        if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            // EBML header (WebM/Matroska)
+            return Some(AudioFormat::WebM);
+        }
+        if bytes.starts_with(&ASF_GUID) {
+            return Some(AudioFormat::Wma);
+        }
+        if bytes.len() >= 2 && bytes[0] == 0xFF {
+            // ADTS AAC sets the layer bits to 00, MPEG audio frames never do
+            if bytes[1] & 0xF6 == 0xF0 {
+                return Some(AudioFormat::Aac);
+            }
+            if bytes[1] & 0xE0 == 0xE0 && (bytes[1] >> 1) & 0x03 != 0 {
+                return Some(AudioFormat::Mp3);
+            }
+        }
+
+        None
+    }
+
     /// Validate file size
     pub fn validate_file_size(size: usize, max_size: usize) -> AppResult<()> {
         if size > max_size {
-            return Err(AppError::ValidationError(format!(
+            return Err(AppError::PayloadTooLarge(format!(
                 "File size {} bytes exceeds maximum allowed size of {} bytes",
                 size, max_size
             )));
         }
         Ok(())
     }
+
+    /// Validate audio length against `MAX_AUDIO_DURATION`, when one is set
+    pub fn validate_audio_duration(duration_seconds: f64, max_duration: Option<f64>) -> AppResult<()> {
+        if let Some(max_duration) = max_duration
+            && duration_seconds > max_duration
+        {
+            return Err(AppError::ValidationError(format!(
+                "Audio duration {:.1} seconds exceeds maximum allowed duration of {:.1} seconds",
+                duration_seconds, max_duration
+            )));
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn audio_duration_is_checked_only_when_limited() {
+            assert!(validate_audio_duration(600.0, Some(600.0)).is_ok());
+            assert!(matches!(
+                validate_audio_duration(600.5, Some(600.0)),
+                Err(AppError::ValidationError(_))
+            ));
+            assert!(validate_audio_duration(86_400.0, None).is_ok());
+        }
+
+        #[test]
+        fn sanitize_drops_directory_components() {
+            assert_eq!(sanitize_filename("../../etc/passwd").unwrap(), "passwd");
+            assert_eq!(sanitize_filename("/var/tmp/song.mp3").unwrap(), "song.mp3");
+            assert_eq!(sanitize_filename("C:\\Users\\me\\memo.m4a").unwrap(), "memo.m4a");
+        }
+
+        #[test]
+        fn sanitize_never_keeps_dot_dot() {
+            assert_eq!(sanitize_filename("..wav").unwrap(), "wav");
+            assert!(sanitize_filename("foo/..").is_err());
+            assert_eq!(sanitize_filename(".hidden.wav").unwrap(), "hidden.wav");
+        }
+
+        #[test]
+        fn sanitize_replaces_control_and_unsafe_characters() {
+            assert_eq!(sanitize_filename("a\0b\n.wav").unwrap(), "a_b_.wav");
+            assert_eq!(sanitize_filename("rm *; ls|x.mp3").unwrap(), "rm __ ls_x.mp3");
+            assert_eq!(sanitize_filename("café (1).ogg").unwrap(), "café (1).ogg");
+        }
+
+        #[test]
+        fn sanitize_shortens_overlong_names_keeping_extension() {
+            let long = format!("{}.wav", "a".repeat(400));
+
+            let sanitized = sanitize_filename(&long).unwrap();
+
+            assert_eq!(sanitized.chars().count(), MAX_FILENAME_LEN);
+            assert!(sanitized.ends_with("a.wav"));
+        }
+
+        #[test]
+        fn sanitize_shortens_by_characters_not_bytes() {
+            let sanitized = sanitize_filename(&"é".repeat(300)).unwrap();
+
+            assert_eq!(sanitized.chars().count(), MAX_FILENAME_LEN);
+        }
+
+        #[test]
+        fn sanitize_rejects_names_left_empty() {
+            for filename in ["", "   ", "..", "...", "/", "dir/", "\0\0", "***", "._."] {
+                assert!(
+                    matches!(sanitize_filename(filename), Err(AppError::ValidationError(_))),
+                    "{:?} was accepted",
+                    filename
+                );
+            }
+        }
+
+        #[test]
+        fn ffmpeg_arg_never_reads_as_an_option() {
+            assert_eq!(ffmpeg_file_arg("-i evil"), "file:-i evil");
+            assert_eq!(ffmpeg_file_arg("/tmp/-y.wav"), "file:/tmp/-y.wav");
+            // Other protocols are read as part of the local path
+            assert_eq!(ffmpeg_file_arg("http://host/a.wav"), "file:http://host/a.wav");
+        }
+
+        #[test]
+        fn command_arg_never_reads_as_a_flag() {
+            assert_eq!(command_file_arg("-i evil"), "./-i evil");
+            assert_eq!(command_file_arg("--help"), "./--help");
+            assert_eq!(command_file_arg("temp/audio.wav"), "./temp/audio.wav");
+            assert_eq!(command_file_arg("./audio.wav"), "./audio.wav");
+            assert_eq!(command_file_arg("/tmp/-audio.wav"), "/tmp/-audio.wav");
+        }
+
+        #[test]
+        fn hostile_upload_name_stays_out_of_paths() {
+            let stored = generate_unique_filename("-i evil");
+
+            assert!(!stored.starts_with('-'));
+            assert!(!stored.contains("evil"));
+            // The display name may still start with a dash; it never reaches a command line
+            assert_eq!(sanitize_filename("-i evil").unwrap(), "-i evil");
+        }
+
+        #[test]
+        fn unique_filename_keeps_only_a_safe_extension() {
+            assert!(generate_unique_filename("../../evil.wav").ends_with(".wav"));
+            assert!(!generate_unique_filename("evil.wav/../x").contains(".."));
+            assert!(!generate_unique_filename("song.mp3;rm -rf").contains(';'));
+        }
+    }
 }
 
 /// Validation utilities
 pub mod validation {
     use super::*;
+    use crate::models::{audio_filter, text_format};
     use validator::Validate;
 
-    /// Validate a struct and return appropriate error
+    /// Validate a struct and return field-level errors
     pub fn validate_request<T: Validate>(request: &T) -> AppResult<()> {
         request.validate().map_err(|e| {
-            let error_message = e
+            let mut field_errors: Vec<FieldError> = e
                 .field_errors()
                 .into_iter()
-                .map(|(field, errors)| {
-                    let field_errors: Vec<String> = errors
+                .map(|(field, errors)| FieldError {
+                    field: field.to_string(),
+                    messages: errors
                         .iter()
-                        .filter_map(|e| e.message.as_ref().map(|m| m.to_string()))
-                        .collect();
-                    format!("{}: {}", field, field_errors.join(", "))
+                        .map(|e| {
+                            e.message
+                                .as_ref()
+                                .map(|m| m.to_string())
+                                .unwrap_or_else(|| e.code.to_string())
+                        })
+                        .collect(),
                 })
-                .collect::<Vec<String>>()
-                .join("; ");
+                .collect();
+
+            // HashMap iteration order is random, keep responses stable
+            field_errors.sort_by(|a, b| a.field.cmp(&b.field));
 
-            AppError::ValidationError(error_message)
+            AppError::ValidationErrors(field_errors)
         })
     }
+
+    /// Normalize an email for storage and lookup (trimmed, lowercase)
+    pub fn normalize_email(email: &str) -> String {
+        email.trim().to_lowercase()
+    }
+
+    /// ISO-639-1 language codes accepted for transcription
+    pub const SUPPORTED_LANGUAGES: &[&str] = &[
+        "en", "es", "fr", "de", "it", "pt", "nl", "ru", "zh", "ja", "ko", "ar", "hi", "tr",
+        "pl", "uk", "sv", "da", "no", "fi", "cs", "el", "he", "id", "vi", "th", "ro", "hu",
+    ];
+
+    /// Validate a requested transcription language
+    /// Returns `None` when the language should be auto-detected by Whisper
+    pub fn validate_language(language: Option<&str>) -> AppResult<Option<&'static str>> {
+        let code = match language.map(|l| l.trim().to_lowercase()) {
+            None => return Ok(None),
+            Some(code) if code.is_empty() || code == "auto" => return Ok(None),
+            Some(code) => code,
+        };
+
+        SUPPORTED_LANGUAGES
+            .iter()
+            .find(|supported| **supported == code)
+            .map(|supported| Some(*supported))
+            .ok_or_else(|| {
+                AppError::ValidationError(format!(
+                    "Unsupported language '{}'. Use 'auto' or one of: {}",
+                    code,
+                    SUPPORTED_LANGUAGES.join(", ")
+                ))
+            })
+    }
+
+    /// Validate a Whisper temperature form field, which must be a number from 0 to 1
+    pub fn validate_temperature(field: &str, value: &str) -> AppResult<f32> {
+        value
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|temperature| (0.0..=1.0).contains(temperature))
+            .ok_or_else(|| {
+                AppError::ValidationError(format!("{} must be a number between 0 and 1", field))
+            })
+    }
+
+    /// Validate a requested transcription text format
+    pub fn validate_text_format(format: &str) -> AppResult<&'static str> {
+        let format = format.trim().to_lowercase();
+
+        text_format::ALL
+            .iter()
+            .find(|supported| **supported == format)
+            .copied()
+            .ok_or_else(|| {
+                AppError::ValidationError(format!(
+                    "Unsupported format '{}'. Use one of: {}",
+                    format,
+                    text_format::ALL.join(", ")
+                ))
+            })
+    }
+
+    /// Validate a comma-separated list of audio filters ("none" or an empty list for none)
+    /// Only names from `audio_filter::ALL` are accepted, so no FFmpeg arguments come from
+    /// clients. The result is deduplicated and in the order the filters are applied.
+    pub fn validate_audio_filters(filters: &str) -> AppResult<Vec<String>> {
+        let mut requested = Vec::new();
+        for filter in filters.split(',').map(|filter| filter.trim().to_lowercase()) {
+            if filter.is_empty() || filter == "none" {
+                continue;
+            }
+            if !audio_filter::ALL.contains(&filter.as_str()) {
+                return Err(AppError::ValidationError(format!(
+                    "Unsupported audio filter '{}'. Use any of: {}",
+                    filter,
+                    audio_filter::ALL.join(", ")
+                )));
+            }
+            requested.push(filter);
+        }
+
+        Ok(audio_filter::ALL
+            .iter()
+            .filter(|filter| requested.iter().any(|requested| requested == *filter))
+            .map(|filter| filter.to_string())
+            .collect())
+    }
+
+    /// Maximum initial prompt length; Whisper only keeps about 224 prompt tokens
+    pub const MAX_INITIAL_PROMPT_CHARS: usize = 800;
+
+    /// Validate an initial prompt, returning `None` when it is empty
+    pub fn validate_initial_prompt(prompt: Option<&str>) -> AppResult<Option<String>> {
+        let prompt = match prompt.map(str::trim) {
+            None | Some("") => return Ok(None),
+            Some(prompt) => prompt,
+        };
+
+        if prompt.chars().count() > MAX_INITIAL_PROMPT_CHARS {
+            return Err(AppError::ValidationError(format!(
+                "Prompt must be at most {} characters",
+                MAX_INITIAL_PROMPT_CHARS
+            )));
+        }
+
+        Ok(Some(prompt.to_string()))
+    }
+
+    /// Maximum number of tags on one transcript
+    pub const MAX_TAGS: usize = 20;
+
+    /// Maximum length of a single tag
+    pub const MAX_TAG_CHARS: usize = 32;
+
+    /// Normalize a tag (trimmed, lowercase) and check its length
+    pub fn validate_tag(tag: &str) -> AppResult<String> {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || tag.chars().count() > MAX_TAG_CHARS {
+            return Err(AppError::ValidationError(format!(
+                "Tags must be between 1 and {} characters",
+                MAX_TAG_CHARS
+            )));
+        }
+        Ok(tag)
+    }
+
+    /// Normalize and deduplicate a list of tags
+    pub fn validate_tags(tags: &[String]) -> AppResult<Vec<String>> {
+        let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+        for tag in tags {
+            let tag = validate_tag(tag)?;
+            if !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+
+        if normalized.len() > MAX_TAGS {
+            return Err(AppError::ValidationError(format!(
+                "A transcript can have at most {} tags",
+                MAX_TAGS
+            )));
+        }
+        Ok(normalized)
+    }
+}
+
+/// Network address utilities
+pub mod network {
+    use serde::Deserialize;
+    use std::net::IpAddr;
+
+    /// An IP address or CIDR range, e.g. `10.0.0.1` or `10.0.0.0/8`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+    pub struct IpNetwork {
+        address: IpAddr,
+        prefix_len: u8,
+    }
+
+    impl IpNetwork {
+        /// Parse an address, which matches only itself, or an `address/prefix` range
+        pub fn parse(value: &str) -> Option<Self> {
+            let (address, prefix_len) = match value.trim().split_once('/') {
+                Some((address, prefix_len)) => (address, Some(prefix_len)),
+                None => (value.trim(), None),
+            };
+            let address: IpAddr = address.parse().ok()?;
+            let max_len = if address.is_ipv4() { 32 } else { 128 };
+            let prefix_len = match prefix_len {
+                Some(prefix_len) => prefix_len.parse().ok().filter(|len| *len <= max_len)?,
+                None => max_len,
+            };
+            Some(Self { address, prefix_len })
+        }
+
+        /// Whether `ip` is in this range
+        /// IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) match the IPv4 address they carry.
+        pub fn contains(&self, ip: IpAddr) -> bool {
+            match (self.address, ip.to_canonical()) {
+                (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                    Self::same_prefix(network.to_bits().into(), ip.to_bits().into(), 32, self.prefix_len)
+                }
+                (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                    Self::same_prefix(network.to_bits(), ip.to_bits(), 128, self.prefix_len)
+                }
+                _ => false,
+            }
+        }
+
+        fn same_prefix(network: u128, ip: u128, bits: u8, prefix_len: u8) -> bool {
+            let shift = u32::from(bits - prefix_len);
+            network.checked_shr(shift).unwrap_or(0) == ip.checked_shr(shift).unwrap_or(0)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn ip(value: &str) -> IpAddr {
+            value.parse().unwrap()
+        }
+
+        #[test]
+        fn single_address_matches_only_itself() {
+            let network = IpNetwork::parse("10.0.0.1").unwrap();
+
+            assert!(network.contains(ip("10.0.0.1")));
+            assert!(!network.contains(ip("10.0.0.2")));
+        }
+
+        #[test]
+        fn range_matches_addresses_sharing_its_prefix() {
+            let network = IpNetwork::parse("10.0.0.0/8").unwrap();
+
+            assert!(network.contains(ip("10.255.1.2")));
+            assert!(!network.contains(ip("11.0.0.1")));
+            assert!(IpNetwork::parse("0.0.0.0/0").unwrap().contains(ip("192.0.2.1")));
+            assert!(IpNetwork::parse("fd00::/8").unwrap().contains(ip("fd12::1")));
+        }
+
+        #[test]
+        fn mapped_ipv6_matches_ipv4_range() {
+            let network = IpNetwork::parse("127.0.0.1").unwrap();
+
+            assert!(network.contains(ip("::ffff:127.0.0.1")));
+            assert!(!network.contains(ip("::1")));
+        }
+
+        #[test]
+        fn invalid_networks_are_rejected() {
+            for value in ["", "proxy.internal", "10.0.0.0/33", "::/129", "10.0.0.0/x"] {
+                assert!(IpNetwork::parse(value).is_none(), "{}", value);
+            }
+        }
+    }
 }